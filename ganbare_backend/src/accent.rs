@@ -0,0 +1,256 @@
+//! A structured model of the pitch accent markup that is stored inline in `words.word`.
+//!
+//! A word is written in kana, and the accent nucleus (the last high mora before the pitch
+//! drops) is marked by writing `・` (or the older `*`) right after it. A word without a
+//! nucleus mark is heiban; it may be explicitly terminated with `＝`. Sentences mark the
+//! rise of each accentuated phrase with `／` and its end with `・` or `＝`; they aren't
+//! parsed into morae, but their markup is checked by `validate`.
+
+use serde::Serialize;
+use unicode_normalization::UnicodeNormalization;
+use error_chain::bail;
+
+use crate::errors::*;
+
+pub const NUCLEUS_MARK: char = '・';
+pub const OLD_NUCLEUS_MARK: char = '*';
+pub const FLAT_END_MARK: char = '＝';
+pub const RISING_MARK: char = '／';
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Pattern {
+    /// No drop at all, the pitch stays high until the following particle.
+    Heiban,
+    /// The pitch drops after the first mora.
+    Atamadaka,
+    /// The pitch drops after some mora in the middle of the word.
+    Nakadaka,
+    /// The pitch drops after the last mora, so only a following particle is low.
+    Odaka,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct AccentedWord {
+    pub morae: Vec<String>,
+    /// Zero-based index of the mora after which the pitch drops. `None` for heiban.
+    pub nucleus: Option<usize>,
+    pub pattern: Pattern,
+}
+
+impl AccentedWord {
+    /// The conventional accent number: the one-based position of the nucleus, 0 for heiban.
+    pub fn accent_number(&self) -> usize {
+        self.nucleus.map(|n| n + 1).unwrap_or(0)
+    }
+
+    /// The word without any markup.
+    pub fn kana(&self) -> String {
+        self.morae.concat()
+    }
+
+    /// Renders the word back into the markup it's stored with.
+    /// The output is normalized: `*` is written as `・`, and the optional `＝` is left out.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for (i, mora) in self.morae.iter().enumerate() {
+            out.push_str(mora);
+            if Some(i) == self.nucleus {
+                out.push(NUCLEUS_MARK);
+            }
+        }
+        out
+    }
+}
+
+fn is_small_kana(c: char) -> bool {
+    match c {
+        'ぁ' | 'ぃ' | 'ぅ' | 'ぇ' | 'ぉ' | 'ゃ' | 'ゅ' | 'ょ' | 'ゎ' | 'ァ' | 'ィ' | 'ゥ' | 'ェ' |
+        'ォ' | 'ャ' | 'ュ' | 'ョ' | 'ヮ' => true,
+        _ => false,
+    }
+}
+
+pub(crate) fn is_kana(c: char) -> bool {
+    match c {
+        '\u{3041}'..='\u{3096}' | '\u{309D}'..='\u{309E}' | '\u{30A1}'..='\u{30FA}' |
+        '\u{30FC}'..='\u{30FE}' => true,
+        _ => false,
+    }
+}
+
+/// Normalizes the width and composition of kana, so that half-width katakana
+/// and decomposed dakuten are handled the same as the ordinary forms.
+/// The full-width markup characters are kept as they are.
+pub fn normalize(word: &str) -> String {
+    word.nfkc()
+        .map(|c| match c {
+            '=' => FLAT_END_MARK,
+            '/' => RISING_MARK,
+            c => c,
+        })
+        .collect()
+}
+
+/// The normalized word without the accent markup, for showing it to the user.
+pub fn strip_marks(word: &str) -> String {
+    normalize(word)
+        .chars()
+        .filter(|&c| {
+                    c != NUCLEUS_MARK && c != OLD_NUCLEUS_MARK && c != FLAT_END_MARK &&
+                    c != RISING_MARK
+                })
+        .collect()
+}
+
+/// Parses a marked-up word into morae and finds its accent nucleus.
+pub fn parse(word: &str) -> Result<AccentedWord> {
+    let word = normalize(word);
+
+    let mut morae: Vec<String> = Vec::new();
+    let mut nucleus = None;
+    let mut flat_end = false;
+
+    for c in word.chars() {
+        if flat_end {
+            bail!(ErrorKind::InvalidAccent(format!("Nothing may follow {} in {:?}",
+                                                   FLAT_END_MARK,
+                                                   word)));
+        }
+        match c {
+            NUCLEUS_MARK | OLD_NUCLEUS_MARK => {
+                if morae.is_empty() {
+                    bail!(ErrorKind::InvalidAccent(format!("The accent mark precedes every \
+                                                            mora in {:?}",
+                                                           word)));
+                }
+                if nucleus.is_some() {
+                    bail!(ErrorKind::InvalidAccent(format!("More than one accent mark in {:?}",
+                                                           word)));
+                }
+                nucleus = Some(morae.len() - 1);
+            }
+            FLAT_END_MARK => {
+                if morae.is_empty() || nucleus.is_some() {
+                    bail!(ErrorKind::InvalidAccent(format!("{} only ends a heiban word: {:?}",
+                                                           FLAT_END_MARK,
+                                                           word)));
+                }
+                flat_end = true;
+            }
+            RISING_MARK => {
+                bail!(ErrorKind::InvalidAccent(format!("{} is only used in sentences: {:?}",
+                                                       RISING_MARK,
+                                                       word)));
+            }
+            c if is_small_kana(c) => {
+                let after_mark = nucleus.is_some() && nucleus == morae.len().checked_sub(1);
+                match morae.last_mut() {
+                    Some(mora) if !after_mark => mora.push(c),
+                    _ => {
+                        bail!(ErrorKind::InvalidAccent(format!("{:?} doesn't belong to any \
+                                                                mora in {:?}",
+                                                               c,
+                                                               word)))
+                    }
+                }
+            }
+            c if is_kana(c) => morae.push(c.to_string()),
+            c => {
+                bail!(ErrorKind::InvalidAccent(format!("{:?} is not kana in {:?}", c, word)));
+            }
+        }
+    }
+
+    if morae.is_empty() {
+        bail!(ErrorKind::InvalidAccent(format!("No morae in {:?}", word)));
+    }
+
+    let pattern = match nucleus {
+        None => Pattern::Heiban,
+        Some(0) => Pattern::Atamadaka,
+        Some(n) if n == morae.len() - 1 => Pattern::Odaka,
+        Some(_) => Pattern::Nakadaka,
+    };
+
+    Ok(AccentedWord {
+           morae,
+           nucleus,
+           pattern,
+       })
+}
+
+/// Checks the markup of a sentence: every phrase that starts with `／`
+/// must end with a single `・` or `＝` before the next phrase starts.
+fn validate_sentence(sentence: &str) -> Result<()> {
+    let sentence = normalize(sentence);
+    let mut in_phrase = false;
+    let mut prev_was_mark = true;
+
+    for c in sentence.chars() {
+        match c {
+            RISING_MARK => {
+                if in_phrase {
+                    bail!(ErrorKind::InvalidAccent(format!("A phrase doesn't end before the \
+                                                            next {} in {:?}",
+                                                           RISING_MARK,
+                                                           sentence)));
+                }
+                in_phrase = true;
+                prev_was_mark = true;
+            }
+            NUCLEUS_MARK | OLD_NUCLEUS_MARK | FLAT_END_MARK => {
+                if !in_phrase || prev_was_mark {
+                    bail!(ErrorKind::InvalidAccent(format!("A stray accent mark in {:?}",
+                                                           sentence)));
+                }
+                in_phrase = false;
+                prev_was_mark = true;
+            }
+            _ => prev_was_mark = false,
+        }
+    }
+    if in_phrase {
+        bail!(ErrorKind::InvalidAccent(format!("The last phrase doesn't end in {:?}", sentence)));
+    }
+    Ok(())
+}
+
+/// Validates the accent markup of anything that is stored in `words.word`.
+pub fn validate(word: &str) -> Result<()> {
+    if normalize(word).contains(RISING_MARK) {
+        validate_sentence(word)
+    } else {
+        parse(word).map(|_| ())
+    }
+}
+
+#[test]
+fn test_parse() {
+    let w = parse("きょ・うと").unwrap();
+    assert_eq!(w.morae, vec!["きょ", "う", "と"]);
+    assert_eq!(w.pattern, Pattern::Atamadaka);
+    assert_eq!(w.accent_number(), 1);
+
+    assert_eq!(parse("いこ・う").unwrap().pattern, Pattern::Nakadaka);
+    assert_eq!(parse("はし・").unwrap().pattern, Pattern::Odaka);
+    assert_eq!(parse("はし").unwrap().pattern, Pattern::Heiban);
+    assert_eq!(parse("さかな＝").unwrap().pattern, Pattern::Heiban);
+    assert_eq!(parse("が*っこう").unwrap().render(), "が・っこう");
+    assert_eq!(parse("ｷｮｳﾄ").unwrap().morae, vec!["キョ", "ウ", "ト"]);
+
+    assert!(parse("").is_err());
+    assert!(parse("・はし").is_err());
+    assert!(parse("は・し・").is_err());
+    assert!(parse("は・し＝").is_err());
+    assert!(parse("ゃき").is_err());
+    assert!(parse("京都").is_err());
+}
+
+#[test]
+fn test_validate_sentence() {
+    assert!(validate("これは／はし・です").is_ok());
+    assert!(validate("これは／さかな＝です").is_ok());
+    assert!(validate("これは／はしです").is_err());
+    assert!(validate("これは／・はしです").is_err());
+}
@@ -3,7 +3,6 @@ use super::*;
 use std::path::PathBuf;
 use std::path::Path;
 use rand::thread_rng;
-use chrono;
 use std::fs;
 use std::mem;
 use mime;
@@ -20,7 +19,7 @@ fn save_file(path: &mut PathBuf, orig_filename: &str, audio_dir: &Path) -> Resul
         .extension()
         .and_then(|s| s.to_str())
         .unwrap_or("noextension"));
-    new_path.push(chrono::offset::Utc::now().to_rfc3339());
+    new_path.push(clock::now().to_rfc3339());
     info!("Renaming {:?} to {:?}", &*path, &new_path);
     fs::rename(&*path, &new_path).chain_err(|| "Can't rename the audio file.")?;
     mem::swap(path, &mut new_path);
@@ -437,6 +436,7 @@ pub fn load_random_from_bundle(conn: &Connection, bundle_id: i32) -> Result<Audi
     let mut q_audio_files: Vec<(AudioFile, Narrator)> =
         audio_files::table.inner_join(narrators::table)
             .filter(narrators::published.eq(true))
+            .filter(narrators::id.ne_all(trash::trashed_ids(conn, "narrator")?))
             .filter(audio_files::bundle_id.eq(bundle_id))
             .get_results(&**conn)
             .chain_err(|| "Can't load quiz!")?;
@@ -449,8 +449,10 @@ pub fn load_random_from_bundle(conn: &Connection, bundle_id: i32) -> Result<Audi
 
 pub fn get_all_bundles(conn: &Connection) -> Result<Vec<(AudioBundle, Vec<AudioFile>)>> {
     use schema::audio_bundles;
-    let bundles: Vec<AudioBundle> = audio_bundles::table.order(audio_bundles::listname.asc())
-        .get_results(&**conn)?;
+    let bundles: Vec<AudioBundle> =
+        audio_bundles::table.filter(audio_bundles::id.ne_all(trash::trashed_ids(conn, "bundle")?))
+            .order(audio_bundles::listname.asc())
+            .get_results(&**conn)?;
 
     let audio_files =
         AudioFile::belonging_to(&bundles).load::<AudioFile>(&**conn)?.grouped_by(&bundles);
@@ -461,7 +463,9 @@ pub fn get_all_bundles(conn: &Connection) -> Result<Vec<(AudioBundle, Vec<AudioF
 
 pub fn get_narrators(conn: &Connection) -> Result<Vec<Narrator>> {
     use schema::narrators;
-    let narrators: Vec<Narrator> = narrators::table.get_results(&**conn)?;
+    let narrators: Vec<Narrator> =
+        narrators::table.filter(narrators::id.ne_all(trash::trashed_ids(conn, "narrator")?))
+            .get_results(&**conn)?;
     Ok(narrators)
 }
 
@@ -533,3 +537,25 @@ pub fn for_quiz(conn: &Connection, user_id: i32, pending_id: i32) -> Result<(Str
         file.mime.parse().expect("The mimetype from the database should be always valid.")))
 
 }
+
+/// The second audio of a comparison quiz. (The first one is served by `for_quiz`.)
+pub fn for_comparison(conn: &Connection, user_id: i32, pending_id: i32) -> Result<(String, mime::Mime)> {
+    use schema::{audio_files, pending_items, c_asked_data};
+    use diesel::result::Error::NotFound;
+
+    let (_, asked): (PendingItem, CAskedData) = pending_items::table.inner_join(c_asked_data::table)
+        .filter(pending_items::id.eq(pending_id))
+        .filter(pending_items::user_id.eq(user_id))
+        .filter(pending_items::pending.eq(true))
+        .get_result(&**conn)
+        .map_err(|e| match e {
+                     e @ NotFound => Error::with_chain(e, ErrorKind::FileNotFound),
+                     e => Error::with_chain(e, "Couldn't get the file!"),
+                 })?;
+
+    let file: AudioFile = audio_files::table.filter(audio_files::id.eq(asked.second_audio_id))
+        .get_result(&**conn)?;
+
+    Ok((file.file_path,
+        file.mime.parse().expect("The mimetype from the database should be always valid.")))
+}
@@ -147,6 +147,8 @@ fn normalize_unicode() {
         if cleaned_word != w.word {
             println!("Non-normalized unicode found: {:?}", w);
             w.word = cleaned_word;
+            w.reading = kana::reading(&w.word);
+            w.romaji = kana::to_romaji(&w.reading);
             let _: Word = w.save_changes(conn).unwrap();
         }
     }
@@ -332,7 +334,7 @@ fn fix_skill_names() {
 }
 
 fn merge_redundant_skills() {
-    use schema::{words, quiz_questions, exercises, skill_nuggets};
+    use schema::{words, quiz_questions, exercises, skill_nuggets, skill_prerequisites};
     use diesel::expression::dsl::*;
 
     let conn = db::connect(&*DATABASE_URL).unwrap();
@@ -370,6 +372,30 @@ SELECT MIN(id), COUNT(id), skill_summary FROM skill_nuggets GROUP BY skill_summa
                 .set(exercises::skill_id.eq(original_id))
                 .execute(&conn)
                 .expect("DB error");
+            // The prerequisites of the duplicate would be deleted in cascade, so move them
+            let prerequisites: Vec<SkillPrerequisite> =
+                skill_prerequisites::table.filter(skill_prerequisites::skill_id.eq(d.id)
+                                                      .or(skill_prerequisites::prerequisite_id
+                                                              .eq(d.id)))
+                    .get_results(&conn)
+                    .expect("DB error");
+            for p in prerequisites {
+                let moved = SkillPrerequisite {
+                    skill_id: if p.skill_id == d.id { original_id } else { p.skill_id },
+                    prerequisite_id: if p.prerequisite_id == d.id {
+                        original_id
+                    } else {
+                        p.prerequisite_id
+                    },
+                    required_level: p.required_level,
+                };
+                if moved.skill_id != moved.prerequisite_id {
+                    diesel::insert_into(skill_prerequisites::table).values(&moved)
+                        .on_conflict_do_nothing()
+                        .execute(&conn)
+                        .expect("DB error");
+                }
+            }
             diesel::delete(skill_nuggets::table.filter(skill_nuggets::id.eq(d.id)))
                 .execute(&conn)
                 .expect("DB errer");
@@ -54,9 +54,6 @@ fn import_batch(path: &str, narrator: &str, sentences: bool) {
         let mut last_char =
             word.chars().next_back().expect("The word surely is longer than 0 characters!");
 
-        if !sentences && word.chars().filter(|c| c == &'・' || c == &'*').count() > 1 {
-            panic!("Invalid filename! More than one accent marks: {:?}", word);
-        }
         if sentences && !word.contains('、') {
             panic!("Invalid filename! No 、: {:?}", word);
         }
@@ -67,6 +64,13 @@ fn import_batch(path: &str, narrator: &str, sentences: bool) {
                 word.chars().next_back().expect("The word surely is longer than 0 characters!");
         }
 
+        // The sentences are validated after the word is marked up in them.
+        if !sentences {
+            if let Err(e) = accent::validate(&word) {
+                panic!("Invalid filename! {}", e);
+            }
+        }
+
         if audio::exists(&pooled_conn, &path).expect("Crapshoot") {
             println!("That audio file already exists in the system. Skipping.");
             continue;
@@ -97,7 +101,9 @@ fn import_batch(path: &str, narrator: &str, sentences: bool) {
 
         println!("{:?}", w);
 
-        manage::create_or_update_word(&pooled_conn, w, &AUDIO_DIR).unwrap();
+        if let Err(e) = manage::create_or_update_word(&pooled_conn, w, &AUDIO_DIR) {
+            println!("Couldn't import {:?}: {}. Skipping.", word, e);
+        }
     }
 }
 
@@ -200,6 +206,11 @@ fn full_sentence<'a>(conn: &Connection,
         return None;
     }
 
+    if let Err(e) = accent::validate(&sentence) {
+        println!("Invalid accent markup in the sentence {}: {}. Skipping.", &sentence, e);
+        return None;
+    }
+
     Some(manage::NewWordFromStrings {
              word: sentence,
              explanation: explanation,
@@ -0,0 +1,93 @@
+use chrono::{DateTime, Duration, Utc};
+use std::cell::RefCell;
+use std::sync::{Arc, Mutex};
+
+/// The source of the current time for all the time-dependent logic:
+/// breaks, due dates, session expiry, nag email grace periods etc.
+///
+/// The clock is attached to the current thread, the same way the database connection
+/// is attached to the request handler. By default it's the system clock, but tests and
+/// simulations can swap in a `FakeClock` with `with_clock` or `set_clock`.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        chrono::offset::Utc::now()
+    }
+}
+
+/// A clock that stands still until it's moved with `set` or `advance`.
+pub struct FakeClock {
+    time: Mutex<DateTime<Utc>>,
+}
+
+impl FakeClock {
+    pub fn new(time: DateTime<Utc>) -> FakeClock {
+        FakeClock { time: Mutex::new(time) }
+    }
+
+    pub fn set(&self, time: DateTime<Utc>) {
+        *self.time.lock().expect("The lock is never held over a panic.") = time;
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.time.lock().expect("The lock is never held over a panic.") += duration;
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.time.lock().expect("The lock is never held over a panic.")
+    }
+}
+
+thread_local! {
+    static CLOCK: RefCell<Arc<dyn Clock>> = RefCell::new(Arc::new(SystemClock));
+}
+
+/// The current time according to the clock of this thread.
+pub fn now() -> DateTime<Utc> {
+    CLOCK.with(|c| c.borrow().now())
+}
+
+/// Replaces the clock of this thread. Returns the earlier clock.
+pub fn set_clock(clock: Arc<dyn Clock>) -> Arc<dyn Clock> {
+    CLOCK.with(|c| c.replace(clock))
+}
+
+/// Runs `f` with `clock` as the clock of this thread, restoring the earlier clock afterwards.
+pub fn with_clock<T, F: FnOnce() -> T>(clock: Arc<dyn Clock>, f: F) -> T {
+
+    struct Restore(Option<Arc<dyn Clock>>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            if let Some(clock) = self.0.take() {
+                set_clock(clock);
+            }
+        }
+    }
+
+    let _restore = Restore(Some(set_clock(clock)));
+    f()
+}
+
+#[test]
+fn test_fake_clock() {
+    use chrono::TimeZone;
+
+    let start = Utc.ymd(2017, 1, 1).and_hms(12, 0, 0);
+    let clock = Arc::new(FakeClock::new(start));
+
+    with_clock(clock.clone(), || {
+        assert_eq!(now(), start);
+        clock.advance(Duration::days(2));
+        assert_eq!(now(), start + Duration::days(2));
+    });
+
+    assert!(now() > start + Duration::days(2));
+}
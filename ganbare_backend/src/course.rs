@@ -0,0 +1,760 @@
+//! Exporting the course content into a portable archive, and importing such an archive
+//! into another instance.
+//!
+//! The archive is a plain ustar file: `manifest.json` comes first, and it's followed by the
+//! audio files under `audio/` and the images linked from the texts under `images/`.
+//! The manifest refers to the content by the ids of the exporting instance; the importer
+//! maps them to new ids. Narrators, skill nuggets and events are matched by their names,
+//! and audio files by their hash, so importing an archive doesn't duplicate them.
+
+use super::*;
+use serde::{Serialize, Deserialize};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::fs;
+use regex::Regex;
+use data_encoding::HEXLOWER;
+use error_chain::bail;
+
+/// Bump this when the manifest changes incompatibly.
+pub const FORMAT_VERSION: i32 = 1;
+
+const MANIFEST_NAME: &str = "manifest.json";
+
+lazy_static! {
+    static ref IMAGE_LINK: Regex = Regex::new(r#"/api/images/([A-Za-z0-9_.:\-]+)"#)
+        .expect("<- that is a valid regex there");
+    static ref FILE_NAME: Regex = Regex::new(r#"^[A-Za-z0-9_:\-][A-Za-z0-9_.:\-]*$"#)
+        .expect("<- that is a valid regex there");
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Manifest {
+    pub version: i32,
+    pub exported: DateTime<Utc>,
+    pub narrators: Vec<CourseNarrator>,
+    pub audio_bundles: Vec<CourseAudioBundle>,
+    pub audio_files: Vec<CourseAudioFile>,
+    pub skill_nuggets: Vec<CourseSkill>,
+    pub skill_prerequisites: Vec<CoursePrerequisite>,
+    pub words: Vec<CourseWord>,
+    pub questions: Vec<CourseQuestion>,
+    pub exercises: Vec<CourseExercise>,
+    pub comparison_pairs: Vec<CourseComparisonPair>,
+    pub events: Vec<CourseEvent>,
+    /// The file names of the images linked from the texts.
+    pub images: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CourseNarrator {
+    pub id: i32,
+    pub name: String,
+    pub published: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CourseAudioBundle {
+    pub id: i32,
+    pub listname: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CourseAudioFile {
+    pub id: i32,
+    pub narrator_id: i32,
+    pub bundle_id: i32,
+    /// The name of the file in the archive, under `audio/`.
+    pub file_name: String,
+    pub mime: String,
+    /// SHA-512 of the file, in hex.
+    pub sha2: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CourseSkill {
+    pub id: i32,
+    pub skill_summary: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CoursePrerequisite {
+    pub skill_id: i32,
+    pub prerequisite_id: i32,
+    pub required_level: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CourseWord {
+    pub id: i32,
+    pub word: String,
+    pub explanation: String,
+    pub audio_bundle: i32,
+    pub skill_nugget: i32,
+    pub published: bool,
+    pub skill_level: i32,
+    pub priority: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CourseQuestion {
+    pub id: i32,
+    pub skill_id: i32,
+    pub q_name: String,
+    pub q_explanation: String,
+    pub question_text: String,
+    pub published: bool,
+    pub skill_level: i32,
+    pub answers: Vec<CourseAnswer>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CourseAnswer {
+    pub a_audio_bundle: Option<i32>,
+    pub q_audio_bundle: i32,
+    pub answer_text: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CourseExercise {
+    pub id: i32,
+    pub skill_id: i32,
+    pub published: bool,
+    pub skill_level: i32,
+    /// The ids of the words that are the variants of the exercise.
+    pub variants: Vec<i32>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CourseComparisonPair {
+    pub skill_id: i32,
+    pub word_a: i32,
+    pub word_b: i32,
+    pub published: bool,
+    pub skill_level: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CourseEvent {
+    pub name: String,
+    pub published: bool,
+    /// The name of the required user group.
+    pub required_group: Option<String>,
+    pub priority: i32,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct ImportSummary {
+    pub words: usize,
+    pub questions: usize,
+    pub exercises: usize,
+    pub comparison_pairs: usize,
+    pub events: usize,
+    pub new_audio_files: usize,
+    pub existing_audio_files: usize,
+    pub images: usize,
+}
+
+fn invalid(reason: String) -> Error {
+    ErrorKind::InvalidCourseArchive(reason).into()
+}
+
+fn padding(len: usize) -> usize {
+    (512 - len % 512) % 512
+}
+
+fn write_entry<W: Write>(out: &mut W, name: &str, data: &[u8]) -> Result<()> {
+    if name.len() >= 100 {
+        return Err(invalid(format!("The file name {:?} is too long.", name)));
+    }
+    if data.len() as u64 >= 0o77777777777 {
+        return Err(invalid(format!("The file {:?} is too large.", name)));
+    }
+
+    let mut header = [0_u8; 512];
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    header[100..108].copy_from_slice(b"0000644\0");
+    header[108..116].copy_from_slice(b"0000000\0");
+    header[116..124].copy_from_slice(b"0000000\0");
+    header[124..136].copy_from_slice(format!("{:011o}\0", data.len()).as_bytes());
+    header[136..148].copy_from_slice(format!("{:011o}\0", clock::now().timestamp()).as_bytes());
+    header[148..156].copy_from_slice(b"        ");
+    header[156] = b'0';
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+    let checksum: u32 = header.iter().map(|&b| b as u32).sum();
+    header[148..156].copy_from_slice(format!("{:06o}\0 ", checksum).as_bytes());
+
+    out.write_all(&header)?;
+    out.write_all(data)?;
+    out.write_all(&[0_u8; 512][..padding(data.len())])?;
+    Ok(())
+}
+
+fn write_end<W: Write>(out: &mut W) -> Result<()> {
+    out.write_all(&[0_u8; 1024])?;
+    Ok(())
+}
+
+/// Reads the next regular file of the archive. Returns `None` at the end of the archive.
+fn read_entry<R: Read>(input: &mut R) -> Result<Option<(String, Vec<u8>)>> {
+    loop {
+        let mut header = [0_u8; 512];
+        input.read_exact(&mut header)?;
+        if header.iter().all(|&b| b == 0) {
+            return Ok(None);
+        }
+
+        let name_end = header[..100].iter().position(|&b| b == 0).unwrap_or(100);
+        let name = std::str::from_utf8(&header[..name_end])
+            .map_err(|_| invalid("A file name isn't valid UTF-8.".to_owned()))?
+            .to_owned();
+
+        let size = std::str::from_utf8(&header[124..136])
+            .ok()
+            .map(|s| s.trim_matches(|c| c == '\0' || c == ' '))
+            .and_then(|s| usize::from_str_radix(s, 8).ok())
+            .ok_or_else(|| invalid(format!("The size of {:?} is malformed.", name)))?;
+
+        let mut data = vec![0_u8; size];
+        input.read_exact(&mut data)?;
+        let mut pad = [0_u8; 512];
+        input.read_exact(&mut pad[..padding(size)])?;
+
+        // Directories and such are skipped
+        if header[156] == b'0' || header[156] == 0 {
+            return Ok(Some((name, data)));
+        }
+    }
+}
+
+/// The file names of the images linked from `text`.
+fn image_links(text: &str) -> impl Iterator<Item = &str> {
+    IMAGE_LINK.captures_iter(text)
+        .filter_map(|c| c.get(1))
+        .map(|m| m.as_str())
+}
+
+fn remap(ids: &HashMap<i32, i32>, id: i32, what: &str) -> Result<i32> {
+    ids.get(&id)
+        .cloned()
+        .ok_or_else(|| invalid(format!("The manifest refers to an unknown {} {}.", what, id)))
+}
+
+fn load_manifest(conn: &Connection, audio_dir: &Path) -> Result<Manifest> {
+    use schema::{narrators, audio_bundles, audio_files, skill_nuggets, skill_prerequisites, words,
+                 quiz_questions, question_answers, exercises, exercise_variants, comparison_pairs,
+                 events, user_groups};
+
+    let narrators: Vec<Narrator> = narrators::table.order(narrators::id).get_results(&**conn)?;
+    let bundles: Vec<AudioBundle> = audio_bundles::table.order(audio_bundles::id)
+        .get_results(&**conn)?;
+    let files: Vec<AudioFile> = audio_files::table.order(audio_files::id).get_results(&**conn)?;
+    let skills: Vec<SkillNugget> = skill_nuggets::table.order(skill_nuggets::id)
+        .get_results(&**conn)?;
+    let prerequisites: Vec<SkillPrerequisite> = skill_prerequisites::table.get_results(&**conn)?;
+    // The trashed content isn't exported. The trashed audio is, since the answers may use it.
+    let trashed_words = trash::trashed_ids(conn, "word")?;
+    let trashed_questions = trash::trashed_ids(conn, "question")?;
+    let trashed_exercises = trash::trashed_ids(conn, "exercise")?;
+    let trashed_pairs = trash::trashed_ids(conn, "comparison")?;
+
+    let words: Vec<Word> = words::table.filter(words::id.ne_all(&trashed_words))
+        .order(words::id)
+        .get_results(&**conn)?;
+    let questions: Vec<QuizQuestion> =
+        quiz_questions::table.filter(quiz_questions::id.ne_all(&trashed_questions))
+            .order(quiz_questions::id)
+            .get_results(&**conn)?;
+    let answers: Vec<Answer> =
+        question_answers::table.filter(question_answers::question_id.ne_all(&trashed_questions))
+            .order(question_answers::id)
+            .get_results(&**conn)?;
+    let exercises: Vec<Exercise> = exercises::table.filter(exercises::id.ne_all(&trashed_exercises))
+        .order(exercises::id)
+        .get_results(&**conn)?;
+    let variants: Vec<ExerciseVariant> =
+        exercise_variants::table.filter(exercise_variants::exercise_id.ne_all(&trashed_exercises))
+            .filter(exercise_variants::id.ne_all(&trashed_words))
+            .get_results(&**conn)?;
+    let pairs: Vec<ComparisonPair> =
+        comparison_pairs::table.filter(comparison_pairs::id.ne_all(&trashed_pairs))
+            .filter(comparison_pairs::word_a.ne_all(&trashed_words))
+            .filter(comparison_pairs::word_b.ne_all(&trashed_words))
+            .order(comparison_pairs::id)
+            .get_results(&**conn)?;
+    let events: Vec<(Event, Option<UserGroup>)> = events::table.left_outer_join(user_groups::table)
+        .order(events::id)
+        .get_results(&**conn)?;
+
+    let mut audio_files = Vec::with_capacity(files.len());
+    for f in files {
+        let hash = audio::audio_file_hash(&f.file_path, audio_dir)
+            .chain_err(|| format!("Can't read the audio file {:?}!", f.file_path))?;
+        match f.file_sha2 {
+            Some(ref sha2) if sha2[..] != hash[..] => {
+                bail!("The audio file {:?} doesn't match its file_sha2!", f.file_path)
+            }
+            Some(_) => (),
+            None => warn!("The audio file {:?} has no file_sha2 to verify.", f.file_path),
+        }
+        audio_files.push(CourseAudioFile {
+                             id: f.id,
+                             narrator_id: f.narrators_id,
+                             bundle_id: f.bundle_id,
+                             file_name: f.file_path,
+                             mime: f.mime,
+                             sha2: HEXLOWER.encode(&hash[..]),
+                         });
+    }
+
+    let mut answers_by_question: HashMap<i32, Vec<CourseAnswer>> = HashMap::new();
+    for a in answers {
+        answers_by_question.entry(a.question_id).or_insert_with(Vec::new).push(CourseAnswer {
+            a_audio_bundle: a.a_audio_bundle,
+            q_audio_bundle: a.q_audio_bundle,
+            answer_text: a.answer_text,
+        });
+    }
+
+    let mut variants_by_exercise: HashMap<i32, Vec<i32>> = HashMap::new();
+    for v in variants {
+        variants_by_exercise.entry(v.exercise_id).or_insert_with(Vec::new).push(v.id);
+    }
+
+    let mut images: Vec<String> = words.iter()
+        .flat_map(|w| image_links(&w.explanation))
+        .chain(questions.iter().flat_map(|q| {
+            image_links(&q.q_explanation).chain(image_links(&q.question_text))
+        }))
+        .chain(answers_by_question.values().flat_map(|a| a).flat_map(|a| image_links(&a.answer_text)))
+        .map(str::to_owned)
+        .collect();
+    images.sort();
+    images.dedup();
+
+    Ok(Manifest {
+        version: FORMAT_VERSION,
+        exported: clock::now(),
+        narrators: narrators.into_iter()
+            .map(|n| CourseNarrator { id: n.id, name: n.name, published: n.published })
+            .collect(),
+        audio_bundles: bundles.into_iter()
+            .map(|b| CourseAudioBundle { id: b.id, listname: b.listname })
+            .collect(),
+        audio_files,
+        skill_nuggets: skills.into_iter()
+            .map(|s| CourseSkill { id: s.id, skill_summary: s.skill_summary })
+            .collect(),
+        skill_prerequisites: prerequisites.into_iter()
+            .map(|p| CoursePrerequisite {
+                skill_id: p.skill_id,
+                prerequisite_id: p.prerequisite_id,
+                required_level: p.required_level,
+            })
+            .collect(),
+        words: words.into_iter()
+            .map(|w| CourseWord {
+                id: w.id,
+                word: w.word,
+                explanation: w.explanation,
+                audio_bundle: w.audio_bundle,
+                skill_nugget: w.skill_nugget,
+                published: w.published,
+                skill_level: w.skill_level,
+                priority: w.priority,
+            })
+            .collect(),
+        questions: questions.into_iter()
+            .map(|q| CourseQuestion {
+                answers: answers_by_question.remove(&q.id).unwrap_or_else(Vec::new),
+                id: q.id,
+                skill_id: q.skill_id,
+                q_name: q.q_name,
+                q_explanation: q.q_explanation,
+                question_text: q.question_text,
+                published: q.published,
+                skill_level: q.skill_level,
+            })
+            .collect(),
+        exercises: exercises.into_iter()
+            .map(|e| CourseExercise {
+                variants: variants_by_exercise.remove(&e.id).unwrap_or_else(Vec::new),
+                id: e.id,
+                skill_id: e.skill_id,
+                published: e.published,
+                skill_level: e.skill_level,
+            })
+            .collect(),
+        comparison_pairs: pairs.into_iter()
+            .map(|p| CourseComparisonPair {
+                skill_id: p.skill_id,
+                word_a: p.word_a,
+                word_b: p.word_b,
+                published: p.published,
+                skill_level: p.skill_level,
+            })
+            .collect(),
+        events: events.into_iter()
+            .map(|(e, group)| CourseEvent {
+                name: e.name,
+                published: e.published,
+                required_group: group.map(|g| g.group_name),
+                priority: e.priority,
+            })
+            .collect(),
+        images,
+    })
+}
+
+/// Exports all of the course content, with the audio files and images, into `out`.
+/// The audio files are verified against their hashes before anything is written.
+pub fn export<W: Write>(conn: &Connection,
+                        audio_dir: &Path,
+                        images_dir: &Path,
+                        out: &mut W)
+                        -> Result<Manifest> {
+
+    let mut manifest = load_manifest(conn, audio_dir)?;
+
+    manifest.images.retain(|name| if images_dir.join(name).is_file() {
+                               true
+                           } else {
+                               warn!("The linked image {:?} doesn't exist. Skipping it.", name);
+                               false
+                           });
+
+    let json = serde_json::to_vec_pretty(&manifest).chain_err(|| "Couldn't serialize the manifest!")?;
+    write_entry(out, MANIFEST_NAME, &json)?;
+
+    for f in &manifest.audio_files {
+        let data = fs::read(audio_dir.join(&f.file_name))?;
+        write_entry(out, &format!("audio/{}", f.file_name), &data)?;
+    }
+
+    for name in &manifest.images {
+        let data = fs::read(images_dir.join(name))?;
+        write_entry(out, &format!("images/{}", name), &data)?;
+    }
+
+    write_end(out)?;
+
+    Ok(manifest)
+}
+
+/// Imports an archive made by `export`. The audio files are verified against the hashes in the
+/// manifest before anything is saved. Nothing is imported if any part of the import fails.
+pub fn import<R: Read>(conn: &Connection,
+                       audio_dir: &Path,
+                       images_dir: &Path,
+                       input: &mut R)
+                       -> Result<ImportSummary> {
+
+    let manifest: Manifest = match read_entry(input)? {
+        Some((ref name, ref data)) if name == MANIFEST_NAME => {
+            serde_json::from_slice(data)
+                .chain_err(|| ErrorKind::InvalidCourseArchive("Malformed manifest.".to_owned()))?
+        }
+        _ => return Err(invalid(format!("The archive doesn't start with {}.", MANIFEST_NAME))),
+    };
+
+    if manifest.version > FORMAT_VERSION {
+        return Err(invalid(format!("The archive is of version {}, but only versions up to {} \
+                                    are supported.",
+                                   manifest.version,
+                                   FORMAT_VERSION)));
+    }
+
+    // The files are unpacked to a temporary directory first,
+    // and moved into place only after the database is updated
+    let tmp_dir = tempdir::TempDir::new("ganbare_course_import")?;
+    let tmp_audio = tmp_dir.path().join("audio");
+    let tmp_images = tmp_dir.path().join("images");
+    fs::create_dir(&tmp_audio)?;
+    fs::create_dir(&tmp_images)?;
+
+    while let Some((name, data)) = read_entry(input)? {
+        let (dir, file_name) = if name.starts_with("audio/") {
+            (&tmp_audio, &name["audio/".len()..])
+        } else if name.starts_with("images/") {
+            (&tmp_images, &name["images/".len()..])
+        } else {
+            warn!("Unexpected file {:?} in the archive. Skipping it.", name);
+            continue;
+        };
+        if !FILE_NAME.is_match(file_name) {
+            return Err(invalid(format!("Invalid file name {:?}.", name)));
+        }
+        fs::write(dir.join(file_name), &data)?;
+    }
+
+    let mut hashes = HashMap::new();
+    for f in &manifest.audio_files {
+        if !FILE_NAME.is_match(&f.file_name) || !tmp_audio.join(&f.file_name).is_file() {
+            return Err(invalid(format!("The audio file {:?} is missing.", f.file_name)));
+        }
+        let hash = audio::audio_file_hash(&f.file_name, &tmp_audio)?;
+        if HEXLOWER.encode(&hash[..]) != f.sha2 {
+            return Err(invalid(format!("The audio file {:?} doesn't match its hash.",
+                                       f.file_name)));
+        }
+        hashes.insert(f.id, hash);
+    }
+
+    // The new audio files are copied to a staging directory next to the audio files during
+    // the import, and renamed into place after the commit
+    let staging_dir = tempdir::TempDir::new_in(audio_dir, "course_import_staging")?;
+
+    conn.transaction(|| {
+            import_content(conn, &manifest, &hashes, &tmp_audio, staging_dir.path(), audio_dir)
+        })
+        .and_then(|mut summary| {
+            for entry in fs::read_dir(staging_dir.path())? {
+                let entry = entry?;
+                fs::rename(entry.path(), audio_dir.join(entry.file_name()))
+                    .chain_err(|| "Can't move the imported audio file.")?;
+            }
+            for name in &manifest.images {
+                let target = images_dir.join(name);
+                // The image file names are random, so an existing file is the same image
+                if FILE_NAME.is_match(name) && !target.exists() {
+                    fs::copy(tmp_images.join(name), target)
+                        .chain_err(|| format!("The image {:?} is missing.", name))?;
+                    summary.images += 1;
+                }
+            }
+            Ok(summary)
+        })
+}
+
+fn import_content(conn: &Connection,
+                  manifest: &Manifest,
+                  hashes: &HashMap<i32, [u8; 64]>,
+                  tmp_audio: &Path,
+                  staging_dir: &Path,
+                  audio_dir: &Path)
+                  -> Result<ImportSummary> {
+    use schema::{narrators, audio_bundles, audio_files, words, quiz_questions, question_answers,
+                 exercises, exercise_variants, comparison_pairs, events, user_groups};
+
+    let mut summary = ImportSummary::default();
+
+    let mut narrator_ids = HashMap::new();
+    for n in &manifest.narrators {
+        let existing: Option<Narrator> = narrators::table.filter(narrators::name.eq(&n.name))
+            .get_result(&**conn)
+            .optional()?;
+        let narrator = match existing {
+            Some(narrator) => narrator,
+            None => {
+                diesel::insert_into(narrators::table)
+                    .values((narrators::name.eq(&n.name), narrators::published.eq(n.published)))
+                    .get_result(&**conn)?
+            }
+        };
+        narrator_ids.insert(n.id, narrator.id);
+    }
+
+    let mut skill_ids = HashMap::new();
+    for s in &manifest.skill_nuggets {
+        skill_ids.insert(s.id, skill::get_create_by_name(conn, &s.skill_summary)?.id);
+    }
+
+    // An audio file that already exists takes its bundle along:
+    // the rest of the files of the bundle are added to the existing bundle.
+    let mut bundle_ids = HashMap::new();
+    let mut new_files = vec![];
+    for f in &manifest.audio_files {
+        let hash = &hashes[&f.id][..];
+        let existing: Option<AudioFile> = audio_files::table.filter(audio_files::file_sha2.eq(hash))
+            .first(&**conn)
+            .optional()?;
+        match existing {
+            Some(existing) => {
+                bundle_ids.entry(f.bundle_id).or_insert(existing.bundle_id);
+                summary.existing_audio_files += 1;
+            }
+            None => new_files.push(f),
+        }
+    }
+
+    for b in &manifest.audio_bundles {
+        if !bundle_ids.contains_key(&b.id) {
+            let bundle: AudioBundle = diesel::insert_into(audio_bundles::table)
+                .values(&NewAudioBundle { listname: &b.listname })
+                .get_result(&**conn)?;
+            bundle_ids.insert(b.id, bundle.id);
+        }
+    }
+
+    for f in new_files {
+        let target = audio_dir.join(&f.file_name);
+        if target.exists() {
+            return Err(invalid(format!("A different audio file with the name {:?} already \
+                                        exists.",
+                                       f.file_name)));
+        }
+        diesel::insert_into(audio_files::table).values(&NewAudioFile {
+                    narrators_id: remap(&narrator_ids, f.narrator_id, "narrator")?,
+                    bundle_id: remap(&bundle_ids, f.bundle_id, "audio bundle")?,
+                    file_path: &f.file_name,
+                    mime: &f.mime,
+                    file_sha2: &hashes[&f.id][..],
+                })
+            .execute(&**conn)?;
+        fs::copy(tmp_audio.join(&f.file_name), staging_dir.join(&f.file_name))?;
+        summary.new_audio_files += 1;
+    }
+
+    for p in &manifest.skill_prerequisites {
+        let prerequisite = SkillPrerequisite {
+            skill_id: remap(&skill_ids, p.skill_id, "skill nugget")?,
+            prerequisite_id: remap(&skill_ids, p.prerequisite_id, "skill nugget")?,
+            required_level: p.required_level,
+        };
+        match skill::set_prerequisite(conn, &prerequisite) {
+            Err(Error(ErrorKind::InvalidInput, _)) => {
+                warn!("The prerequisite {:?} would form a cycle with the existing ones. \
+                       Skipping it.",
+                      prerequisite)
+            }
+            r => {
+                r?;
+            }
+        }
+    }
+
+    let mut word_ids = HashMap::new();
+    for w in &manifest.words {
+        let reading = kana::reading(&w.word);
+        let word: Word = diesel::insert_into(words::table)
+            .values((words::word.eq(&w.word),
+                     words::explanation.eq(&w.explanation),
+                     words::audio_bundle.eq(remap(&bundle_ids, w.audio_bundle, "audio bundle")?),
+                     words::skill_nugget.eq(remap(&skill_ids, w.skill_nugget, "skill nugget")?),
+                     words::published.eq(w.published),
+                     words::skill_level.eq(w.skill_level),
+                     words::priority.eq(w.priority),
+                     words::romaji.eq(kana::to_romaji(&reading)),
+                     words::reading.eq(&reading)))
+            .get_result(&**conn)?;
+        word_ids.insert(w.id, word.id);
+        summary.words += 1;
+    }
+
+    for q in &manifest.questions {
+        let question: QuizQuestion = diesel::insert_into(quiz_questions::table)
+            .values((quiz_questions::skill_id.eq(remap(&skill_ids, q.skill_id, "skill nugget")?),
+                     quiz_questions::q_name.eq(&q.q_name),
+                     quiz_questions::q_explanation.eq(&q.q_explanation),
+                     quiz_questions::question_text.eq(&q.question_text),
+                     quiz_questions::published.eq(q.published),
+                     quiz_questions::skill_level.eq(q.skill_level)))
+            .get_result(&**conn)?;
+
+        for a in &q.answers {
+            let a_audio_bundle = match a.a_audio_bundle {
+                Some(id) => Some(remap(&bundle_ids, id, "audio bundle")?),
+                None => None,
+            };
+            diesel::insert_into(question_answers::table).values(&NewAnswer {
+                        question_id: question.id,
+                        a_audio_bundle,
+                        q_audio_bundle: remap(&bundle_ids, a.q_audio_bundle, "audio bundle")?,
+                        answer_text: &a.answer_text,
+                    })
+                .execute(&**conn)?;
+        }
+        summary.questions += 1;
+    }
+
+    for e in &manifest.exercises {
+        let exercise: Exercise = diesel::insert_into(exercises::table)
+            .values((exercises::skill_id.eq(remap(&skill_ids, e.skill_id, "skill nugget")?),
+                     exercises::published.eq(e.published),
+                     exercises::skill_level.eq(e.skill_level)))
+            .get_result(&**conn)?;
+
+        for &word_id in &e.variants {
+            diesel::insert_into(exercise_variants::table).values(&ExerciseVariant {
+                        id: remap(&word_ids, word_id, "word")?,
+                        exercise_id: exercise.id,
+                    })
+                .execute(&**conn)?;
+        }
+        summary.exercises += 1;
+    }
+
+    for p in &manifest.comparison_pairs {
+        diesel::insert_into(comparison_pairs::table)
+            .values((comparison_pairs::skill_id.eq(remap(&skill_ids, p.skill_id, "skill nugget")?),
+                     comparison_pairs::word_a.eq(remap(&word_ids, p.word_a, "word")?),
+                     comparison_pairs::word_b.eq(remap(&word_ids, p.word_b, "word")?),
+                     comparison_pairs::published.eq(p.published),
+                     comparison_pairs::skill_level.eq(p.skill_level)))
+            .execute(&**conn)?;
+        summary.comparison_pairs += 1;
+    }
+
+    for e in &manifest.events {
+        let existing: Option<Event> = events::table.filter(events::name.eq(&e.name))
+            .get_result(&**conn)
+            .optional()?;
+        if existing.is_some() {
+            continue;
+        }
+
+        let required_group: Option<i32> = match e.required_group {
+            Some(ref group_name) => {
+                let group: Option<UserGroup> =
+                    user_groups::table.filter(user_groups::group_name.eq(group_name))
+                        .get_result(&**conn)
+                        .optional()?;
+                if group.is_none() {
+                    warn!("The group {:?} required by the event {:?} doesn't exist. \
+                           Importing the event without a required group.",
+                          group_name,
+                          e.name);
+                }
+                group.map(|g| g.id)
+            }
+            None => None,
+        };
+
+        diesel::insert_into(events::table)
+            .values((events::name.eq(&e.name),
+                     events::published.eq(e.published),
+                     events::required_group.eq(required_group),
+                     events::priority.eq(e.priority)))
+            .execute(&**conn)?;
+        summary.events += 1;
+    }
+
+    Ok(summary)
+}
+
+#[test]
+fn test_archive_entries() {
+    let mut archive = vec![];
+    write_entry(&mut archive, MANIFEST_NAME, b"{}").unwrap();
+    write_entry(&mut archive, "audio/2017-01-01T00-00-00Zabc.mp3", &[1_u8; 513]).unwrap();
+    write_end(&mut archive).unwrap();
+    assert_eq!(archive.len(), 512 + 512 + 512 + 1024 + 1024);
+
+    let mut input = &archive[..];
+    assert_eq!(read_entry(&mut input).unwrap(),
+               Some((MANIFEST_NAME.to_owned(), b"{}".to_vec())));
+    assert_eq!(read_entry(&mut input).unwrap(),
+               Some(("audio/2017-01-01T00-00-00Zabc.mp3".to_owned(), vec![1_u8; 513])));
+    assert_eq!(read_entry(&mut input).unwrap(), None);
+
+    let links: Vec<&str> =
+        image_links(r#"<img src="/api/images/2017-01-01T00-00-00Zabc.png"> /api/images/x.jpg"#)
+            .collect();
+    assert_eq!(links, vec!["2017-01-01T00-00-00Zabc.png", "x.jpg"]);
+    assert!(FILE_NAME.is_match("2017-01-01T00-00-00Zabc.png"));
+    assert!(!FILE_NAME.is_match("../passwords"));
+    assert!(!FILE_NAME.is_match("a/b"));
+}
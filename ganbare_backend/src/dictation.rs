@@ -0,0 +1,81 @@
+//! Grading of the dictation quizzes, where the learner types in kana what they heard.
+//!
+//! Both the expected answer and the typed one are normalized before comparing: the width
+//! is normalized, katakana are folded to hiragana, and the accent marks, punctuation and
+//! whitespace are removed. Answers typed in romaji (without an IME) are converted to kana.
+//! The answer is graded by its edit distance to the expected answer, so a single typo in a
+//! long sentence costs less than a single typo in a short word.
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::kana;
+use crate::accent::is_kana;
+
+/// Answers at or above this level count as correct in the scheduling.
+pub const PASSING_LEVEL: i32 = 80;
+
+/// Normalizes a dictation answer, or the expected answer, for comparing.
+pub fn normalize(text: &str) -> String {
+    let text: String = text.nfkc().collect();
+    let text = if text.chars().any(|c| c.is_ascii_alphabetic()) {
+        kana::from_romaji(&text).unwrap_or(text)
+    } else {
+        text
+    };
+    kana::reading(&text)
+        .chars()
+        .filter(|&c| is_kana(c) || c.is_alphanumeric())
+        .collect()
+}
+
+/// Whether the word can be asked as a dictation: it must consist of kana only.
+pub fn is_dictatable(word: &str) -> bool {
+    let normalized = normalize(word);
+    !normalized.is_empty() && normalized.chars().all(is_kana)
+}
+
+/// The Levenshtein distance in characters.
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+
+    for (i, x) in a.iter().enumerate() {
+        curr[0] = i + 1;
+        for (j, y) in b.iter().enumerate() {
+            let substitution = prev[j] + if x == y { 0 } else { 1 };
+            curr[j + 1] = substitution.min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    prev[b.len()]
+}
+
+/// Grades the answer from 0 to 100. `expected_kana` must be normalized already.
+pub fn grade(expected_kana: &str, answered_text: &str) -> i32 {
+    let expected: Vec<char> = expected_kana.chars().collect();
+    let answered: Vec<char> = normalize(answered_text).chars().collect();
+
+    let longer = expected.len().max(answered.len());
+    if longer == 0 {
+        return 100;
+    }
+    let distance = edit_distance(&expected, &answered);
+
+    (100.0 * (1.0 - distance as f32 / longer as f32)).round() as i32
+}
+
+#[test]
+fn test_grade() {
+    let expected = normalize("これは／はし・です。");
+    assert_eq!(expected, "これははしです");
+    assert_eq!(grade(&expected, "これは はしです"), 100);
+    assert_eq!(grade(&expected, "コレハハシデス"), 100);
+    assert_eq!(grade(&expected, "korehahashidesu"), 100);
+    assert_eq!(grade(&expected, "これははしだす"), 86);
+    assert_eq!(grade(&expected, ""), 0);
+    assert_eq!(grade(&normalize("きょ・うと"), "きよと"), 50);
+
+    assert!(is_dictatable("ｷｮｳﾄ"));
+    assert!(!is_dictatable("京都"));
+}
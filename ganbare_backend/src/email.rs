@@ -189,7 +189,7 @@ pub fn complete_pending_email_confirm(conn: &Connection,
 }
 
 pub fn clean_old_pendings(conn: &Connection, duration: chrono::Duration) -> Result<usize> {
-    let deadline = chrono::offset::Utc::now() - duration;
+    let deadline = clock::now() - duration;
     diesel::delete(pending_email_confirms::table.filter(pending_email_confirms::added.lt(deadline)))
         .execute(&**conn)
         .chain_err(|| "Couldn't delete the old pending requests.")
@@ -225,7 +225,7 @@ pub fn send_nag_emails(queue: &RwLock<VecDeque<Email>>,
 
         let last_nag = stats.last_nag_email.unwrap_or_else(|| chrono::MIN_DATE.and_hms(0, 0, 0));
 
-        if last_nag > chrono::offset::Utc::now() - nag_grace_period {
+        if last_nag > clock::now() - nag_grace_period {
             continue; // We have sent a nag email recently
         }
 
@@ -249,7 +249,7 @@ pub fn send_nag_emails(queue: &RwLock<VecDeque<Email>>,
 
         enqueue_mail(email, queue)?;
 
-        stats.last_nag_email = Some(chrono::offset::Utc::now());
+        stats.last_nag_email = Some(clock::now());
         let _: UserStats = stats.save_changes(&**conn)?;
 
         info!("Sent slacker heatening email to {}!", email_addr);
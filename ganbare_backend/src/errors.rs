@@ -81,6 +81,26 @@ error_chain::error_chain! {
                 description("RateLimit exceeded")
                 display("RateLimit exceeded")
             }
+            InvalidAccent(reason: String) {
+                description("Invalid accent markup")
+                display("Invalid accent markup: {}", reason)
+            }
+            InvalidWordList(reason: String) {
+                description("Invalid word list")
+                display("Invalid word list: {}", reason)
+            }
+            InvalidCourseArchive(reason: String) {
+                description("Invalid course archive")
+                display("Invalid course archive: {}", reason)
+            }
+            InvalidImportArchive(reason: String) {
+                description("Invalid import archive")
+                display("Invalid import archive: {}", reason)
+            }
+            AudioDecodingFailed(reason: String) {
+                description("Can't decode the audio file!")
+                display("Can't decode the audio file! {}", reason)
+            }
             FileAlreadyExists(hash: Vec<u8>) {
                 description("File already exists!")
                 display("File already exists!")
@@ -49,7 +49,9 @@ pub fn dispatch_event(conn: &Connection, user_id: i32) -> Result<Option<Event>>
         .filter(event_experiences::event_finish.is_not_null())
         .select(event_experiences::event_id);
 
-    let event = events::table.filter(events::published.eq(true))
+    let scheduled = publishing::scheduled_for_user(conn, "event", user_id)?;
+
+    let event = events::table.filter(events::published.eq(true).or(events::id.eq_any(scheduled)))
         .filter(events::required_group.eq(any(groups).nullable()).or(events::required_group.is_null()))
         .filter(events::id.ne(all(finished_events)))
         .order(events::priority.asc())
@@ -101,13 +103,19 @@ pub fn is_workable_or_done_by_event_id(conn: &Connection,
     }
 }
 
+/// The event and the user's experience of it. An event that is scheduled to be published
+/// for the user's groups is returned as published.
 pub fn state(conn: &Connection,
              event_name: &str,
              user_id: i32)
              -> Result<Option<(Event, EventExperience)>> {
     use schema::{event_experiences, events};
 
-    let event: Event = events::table.filter(events::name.eq(event_name)).get_result(&**conn)?;
+    let mut event: Event = events::table.filter(events::name.eq(event_name)).get_result(&**conn)?;
+
+    if !event.published {
+        event.published = publishing::is_scheduled_for_user(conn, "event", event.id, user_id)?;
+    }
 
     let ok = event_experiences::table.filter(event_experiences::user_id.eq(user_id))
         .filter(event_experiences::event_id.eq(event.id))
@@ -240,7 +248,7 @@ pub fn set_done(conn: &Connection,
     use schema::event_experiences;
 
     if let Some((ev, mut exp)) = state(conn, event_name, user_id)? {
-        exp.event_finish = Some(chrono::offset::Utc::now());
+        exp.event_finish = Some(clock::now());
         diesel::update(event_experiences::table.filter(event_experiences::event_id.eq(ev.id))
                            .filter(event_experiences::user_id.eq(user_id))).set(&exp)
                 .execute(&**conn)?;
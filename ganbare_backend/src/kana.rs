@@ -0,0 +1,240 @@
+//! Conversions between hiragana, katakana and romaji, for storing the readings of the words
+//! and for searching them.
+//!
+//! The romaji is modified Hepburn, but the long vowels are written the way they are spelled
+//! in kana (こうこう is "koukou", ケーキ is "keeki"), since that's how people type them.
+//! When converting from romaji, the common Kunrei and IME spellings (si, tu, hu, zya…)
+//! and vowels with macrons are accepted too.
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::accent;
+
+/// Hiragana and their romaji. The digraphs come first so that they take precedence.
+/// When converting from romaji, the first matching kana is used.
+const ROMAJI: &[(&str, &str)] = &[
+    ("きゃ", "kya"), ("きゅ", "kyu"), ("きょ", "kyo"),
+    ("ぎゃ", "gya"), ("ぎゅ", "gyu"), ("ぎょ", "gyo"),
+    ("しゃ", "sha"), ("しゅ", "shu"), ("しょ", "sho"), ("しぇ", "she"),
+    ("じゃ", "ja"), ("じゅ", "ju"), ("じょ", "jo"), ("じぇ", "je"),
+    ("ちゃ", "cha"), ("ちゅ", "chu"), ("ちょ", "cho"), ("ちぇ", "che"),
+    ("ぢゃ", "ja"), ("ぢゅ", "ju"), ("ぢょ", "jo"),
+    ("にゃ", "nya"), ("にゅ", "nyu"), ("にょ", "nyo"),
+    ("ひゃ", "hya"), ("ひゅ", "hyu"), ("ひょ", "hyo"),
+    ("びゃ", "bya"), ("びゅ", "byu"), ("びょ", "byo"),
+    ("ぴゃ", "pya"), ("ぴゅ", "pyu"), ("ぴょ", "pyo"),
+    ("みゃ", "mya"), ("みゅ", "myu"), ("みょ", "myo"),
+    ("りゃ", "rya"), ("りゅ", "ryu"), ("りょ", "ryo"),
+    ("ふぁ", "fa"), ("ふぃ", "fi"), ("ふぇ", "fe"), ("ふぉ", "fo"),
+    ("ゔぁ", "va"), ("ゔぃ", "vi"), ("ゔぇ", "ve"), ("ゔぉ", "vo"),
+    ("うぃ", "wi"), ("うぇ", "we"), ("うぉ", "wo"), ("いぇ", "ye"),
+    ("てぃ", "ti"), ("でぃ", "di"), ("とぅ", "tu"), ("どぅ", "du"), ("つぁ", "tsa"),
+    ("あ", "a"), ("い", "i"), ("う", "u"), ("え", "e"), ("お", "o"),
+    ("か", "ka"), ("き", "ki"), ("く", "ku"), ("け", "ke"), ("こ", "ko"),
+    ("が", "ga"), ("ぎ", "gi"), ("ぐ", "gu"), ("げ", "ge"), ("ご", "go"),
+    ("さ", "sa"), ("し", "shi"), ("す", "su"), ("せ", "se"), ("そ", "so"),
+    ("ざ", "za"), ("じ", "ji"), ("ず", "zu"), ("ぜ", "ze"), ("ぞ", "zo"),
+    ("た", "ta"), ("ち", "chi"), ("つ", "tsu"), ("て", "te"), ("と", "to"),
+    ("だ", "da"), ("ぢ", "ji"), ("づ", "zu"), ("で", "de"), ("ど", "do"),
+    ("な", "na"), ("に", "ni"), ("ぬ", "nu"), ("ね", "ne"), ("の", "no"),
+    ("は", "ha"), ("ひ", "hi"), ("ふ", "fu"), ("へ", "he"), ("ほ", "ho"),
+    ("ば", "ba"), ("び", "bi"), ("ぶ", "bu"), ("べ", "be"), ("ぼ", "bo"),
+    ("ぱ", "pa"), ("ぴ", "pi"), ("ぷ", "pu"), ("ぺ", "pe"), ("ぽ", "po"),
+    ("ま", "ma"), ("み", "mi"), ("む", "mu"), ("め", "me"), ("も", "mo"),
+    ("や", "ya"), ("ゆ", "yu"), ("よ", "yo"),
+    ("ら", "ra"), ("り", "ri"), ("る", "ru"), ("れ", "re"), ("ろ", "ro"),
+    ("わ", "wa"), ("ゐ", "wi"), ("ゑ", "we"), ("を", "wo"), ("ゔ", "vu"),
+    ("ぁ", "a"), ("ぃ", "i"), ("ぅ", "u"), ("ぇ", "e"), ("ぉ", "o"),
+    ("ゃ", "ya"), ("ゅ", "yu"), ("ょ", "yo"), ("ゎ", "wa"),
+];
+
+/// Alternative spellings that are only accepted when converting from romaji.
+/// These take precedence over `ROMAJI`, so "ti" is ち like in an IME, not てぃ.
+const ROMAJI_VARIANTS: &[(&str, &str)] = &[
+    ("sya", "しゃ"), ("syu", "しゅ"), ("syo", "しょ"),
+    ("zya", "じゃ"), ("zyu", "じゅ"), ("zyo", "じょ"),
+    ("jya", "じゃ"), ("jyu", "じゅ"), ("jyo", "じょ"),
+    ("tya", "ちゃ"), ("tyu", "ちゅ"), ("tyo", "ちょ"),
+    ("cya", "ちゃ"), ("cyu", "ちゅ"), ("cyo", "ちょ"),
+    ("si", "し"), ("zi", "じ"), ("ti", "ち"), ("tu", "つ"), ("di", "ぢ"), ("du", "づ"),
+    ("hu", "ふ"), ("la", "ら"), ("li", "り"), ("lu", "る"), ("le", "れ"), ("lo", "ろ"),
+];
+
+fn is_vowel(c: char) -> bool {
+    match c {
+        'a' | 'i' | 'u' | 'e' | 'o' => true,
+        _ => false,
+    }
+}
+
+/// Converts katakana to hiragana. Everything else is kept as it is.
+pub fn to_hiragana(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\u{30A1}'..='\u{30F6}' | '\u{30FD}'..='\u{30FE}' => {
+                std::char::from_u32(c as u32 - 0x60).expect("Hiragana are valid chars")
+            }
+            c => c,
+        })
+        .collect()
+}
+
+/// The reading of a word in `words.word`: width-normalized hiragana without the accent marks.
+pub fn reading(word: &str) -> String {
+    to_hiragana(&accent::strip_marks(word))
+}
+
+/// Converts kana to romaji. Anything that isn't kana is kept as it is.
+pub fn to_romaji(kana: &str) -> String {
+    let kana: Vec<char> = to_hiragana(kana).chars().collect();
+    let mut out = String::new();
+    let mut sokuon = false;
+    let mut i = 0;
+
+    while i < kana.len() {
+        let c = kana[i];
+        if c == 'っ' {
+            sokuon = true;
+            i += 1;
+            continue;
+        }
+        if c == 'ー' {
+            if let Some(vowel) = out.chars().last().filter(|&v| is_vowel(v)) {
+                out.push(vowel);
+            }
+            i += 1;
+            continue;
+        }
+        if c == 'ん' {
+            out.push('n');
+            i += 1;
+            continue;
+        }
+
+        let two: String = kana[i..].iter().take(2).collect();
+        let one = c.to_string();
+        let syllable = ROMAJI.iter()
+            .find(|&&(k, _)| k == two && two.chars().count() == 2)
+            .or_else(|| ROMAJI.iter().find(|&&(k, _)| k == one));
+
+        match syllable {
+            Some(&(k, romaji)) => {
+                if sokuon {
+                    let first = romaji.chars().next().expect("Romaji are never empty");
+                    out.push(if romaji.starts_with("ch") { 't' } else { first });
+                }
+                out.push_str(romaji);
+                i += k.chars().count();
+            }
+            None => {
+                out.push(c);
+                i += 1;
+            }
+        }
+        sokuon = false;
+    }
+    out
+}
+
+/// Converts romaji to hiragana. Returns `None` if the text isn't romaji.
+pub fn from_romaji(romaji: &str) -> Option<String> {
+    let romaji: String = romaji.nfkc()
+        .flat_map(|c| {
+            let expanded: &[char] = match c {
+                'ā' | 'Ā' | 'â' | 'Â' => &['a', 'a'],
+                'ī' | 'Ī' | 'î' | 'Î' => &['i', 'i'],
+                'ū' | 'Ū' | 'û' | 'Û' => &['u', 'u'],
+                'ē' | 'Ē' | 'ê' | 'Ê' => &['e', 'e'],
+                'ō' | 'Ō' | 'ô' | 'Ô' => &['o', 'u'],
+                _ => return c.to_lowercase().collect::<Vec<_>>(),
+            };
+            expanded.to_vec()
+        })
+        .collect();
+    let chars: Vec<char> = romaji.chars().collect();
+    let mut out = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).cloned();
+
+        if c == '-' {
+            out.push('ー');
+            i += 1;
+            continue;
+        }
+        if c == '\'' {
+            i += 1;
+            continue;
+        }
+        if c == 'n' && next == Some('n') &&
+           chars.get(i + 2).map(|&c| !is_vowel(c) && c != 'y').unwrap_or(true) {
+            out.push('ん');
+            i += 2;
+            continue;
+        }
+        if c == 'n' && next.map(|n| !is_vowel(n) && n != 'y').unwrap_or(true) {
+            out.push('ん');
+            i += 1;
+            continue;
+        }
+        if c.is_ascii_alphabetic() && !is_vowel(c) && next == Some(c) {
+            out.push('っ');
+            i += 1;
+            continue;
+        }
+        if c == 't' && next == Some('c') {
+            out.push('っ');
+            i += 1;
+            continue;
+        }
+
+        let syllable = (1..=3).rev().filter_map(|len| {
+            if i + len > chars.len() {
+                return None;
+            }
+            let candidate: String = chars[i..i + len].iter().collect();
+            ROMAJI_VARIANTS.iter()
+                .find(|&&(r, _)| r == candidate)
+                .map(|&(_, k)| k)
+                .or_else(|| ROMAJI.iter().find(|&&(_, r)| r == candidate).map(|&(k, _)| k))
+                .map(|k| (k, len))
+        }).next();
+
+        match syllable {
+            Some((kana, len)) => {
+                out.push_str(kana);
+                i += len;
+            }
+            None if c.is_whitespace() => {
+                out.push(c);
+                i += 1;
+            }
+            None => return None,
+        }
+    }
+    Some(out)
+}
+
+#[test]
+fn test_romaji() {
+    assert_eq!(reading("きょ・うと"), "きょうと");
+    assert_eq!(reading("ｶﾞｯｺｳ＝"), "がっこう");
+    assert_eq!(to_romaji("キョウト"), "kyouto");
+    assert_eq!(to_romaji("がっこう"), "gakkou");
+    assert_eq!(to_romaji("まっちゃ"), "matcha");
+    assert_eq!(to_romaji("ケーキ"), "keeki");
+    assert_eq!(to_romaji("しんぶん"), "shinbun");
+
+    assert_eq!(from_romaji("kyouto").unwrap(), "きょうと");
+    assert_eq!(from_romaji("Kyōto").unwrap(), "きょうと");
+    assert_eq!(from_romaji("gakkou").unwrap(), "がっこう");
+    assert_eq!(from_romaji("matcha").unwrap(), "まっちゃ");
+    assert_eq!(from_romaji("konnichiha").unwrap(), "こんにちは");
+    assert_eq!(from_romaji("konna").unwrap(), "こんな");
+    assert_eq!(from_romaji("kon'ya").unwrap(), "こんや");
+    assert_eq!(from_romaji("sinbun").unwrap(), "しんぶん");
+    assert_eq!(from_romaji("tukue").unwrap(), "つくえ");
+    assert!(from_romaji("xyz").is_none());
+}
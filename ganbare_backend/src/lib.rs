@@ -1,3 +1,5 @@
+#![recursion_limit = "256"]
+
 #[macro_use]
 pub extern crate diesel;
 
@@ -77,10 +79,23 @@ pub mod errors;
 pub mod user;
 pub mod session;
 pub mod audio;
+pub mod pitch;
+pub mod pronunciation;
 pub mod quiz;
+pub mod accent;
+pub mod kana;
+pub mod dictation;
 pub mod manage;
+pub mod search;
+pub mod word_lists;
+pub mod course;
+pub mod revision;
+pub mod trash;
+pub mod publishing;
+pub mod word_import;
 pub mod test;
 pub mod helpers;
+pub mod clock;
 
 
 pub use models::*;
@@ -103,6 +118,10 @@ pub mod db {
 
     pub fn check(conn: &Connection) -> Result<bool> {
         run_migrations(conn).chain_err(|| "Couldn't run the migrations.")?;
+        let filled = manage::fill_missing_readings(conn)?;
+        if filled > 0 {
+            info!("Filled in the readings of {} words.", filled);
+        }
         init_check_is_installed(conn)?;
         Ok(is_installed())
     }
@@ -158,6 +177,7 @@ pub mod db {
 
 pub mod skill {
     use super::*;
+    use error_chain::bail;
 
     pub fn get_create_by_name(conn: &Connection, skill_summary: &str) -> Result<SkillNugget> {
         use schema::skill_nuggets;
@@ -195,9 +215,13 @@ pub mod skill {
                            user_id: i32)
                            -> Result<(Vec<(DueItem, QuestionData, QuizQuestion)>,
                                       Vec<(DueItem, ExerciseData, Exercise)>,
-                                      Vec<(PendingItem, WAskedData, Word)>)> {
+                                      Vec<(PendingItem, WAskedData, Word)>,
+                                      Vec<(DueItem, ComparisonData, ComparisonPair)>,
+                                      Vec<(DueItem, AccentData, Word)>,
+                                      Vec<(DueItem, DictationData, Word)>)> {
         use schema::{due_items, question_data, exercise_data, quiz_questions, exercises, words,
-                     pending_items, w_asked_data};
+                     pending_items, w_asked_data, comparison_data, comparison_pairs, accent_data,
+                     dictation_data};
 
         let data_q: Vec<(DueItem, QuestionData)> =
             due_items::table.inner_join(question_data::table)
@@ -224,9 +248,34 @@ pub mod skill {
                                .get_result(&**conn)?);
         }
 
+        let c: Vec<(DueItem, ComparisonData, ComparisonPair)> =
+            due_items::table.inner_join(comparison_data::table.inner_join(comparison_pairs::table))
+                .filter(due_items::user_id.eq(user_id))
+                .get_results::<(DueItem, (ComparisonData, ComparisonPair))>(&**conn)?
+                .into_iter()
+                .map(|(a, (b, c))| (a, b, c))
+                .collect();
+
+        let a: Vec<(DueItem, AccentData, Word)> =
+            due_items::table.inner_join(accent_data::table.inner_join(words::table))
+                .filter(due_items::user_id.eq(user_id))
+                .get_results::<(DueItem, (AccentData, Word))>(&**conn)?
+                .into_iter()
+                .map(|(a, (b, c))| (a, b, c))
+                .collect();
+
+        let d: Vec<(DueItem, DictationData, Word)> =
+            due_items::table.inner_join(dictation_data::table.inner_join(words::table))
+                .filter(due_items::user_id.eq(user_id))
+                .get_results::<(DueItem, (DictationData, Word))>(&**conn)?
+                .into_iter()
+                .map(|(a, (b, c))| (a, b, c))
+                .collect();
+
         let data_w: Vec<(PendingItem, WAskedData)> =
             pending_items::table.inner_join(w_asked_data::table)
                 .filter(pending_items::user_id.eq(user_id).and(pending_items::test_item.eq(false)))
+                .filter(pending_items::practice.eq(false))
                 .get_results(&**conn)?;
 
         let mut words: Vec<Word> = vec![];
@@ -248,7 +297,7 @@ pub mod skill {
             .map(|((a, b), c)| (a, b, c))
             .collect();
 
-        Ok((q, e, w))
+        Ok((q, e, w, c, a, d))
     }
 
     pub fn get_skill_nuggets(conn: &Connection)
@@ -263,11 +312,14 @@ pub mod skill {
             skill_nuggets::table.order(skill_nuggets::skill_summary.asc()).get_results(&**conn)?;
 
         let words = Word::belonging_to(&nuggets)
+            .filter(words::id.ne_all(trash::trashed_ids(conn, "word")?))
             .order(words::id.asc())
             .load::<Word>(&**conn)?
             .grouped_by(&nuggets);
 
-        let questions = QuizQuestion::belonging_to(&nuggets).order(quiz_questions::id.asc())
+        let questions = QuizQuestion::belonging_to(&nuggets)
+            .filter(quiz_questions::id.ne_all(trash::trashed_ids(conn, "question")?))
+            .order(quiz_questions::id.asc())
             .load::<QuizQuestion>(&**conn)?;
 
         let q_answers = Answer::belonging_to(&questions)
@@ -280,7 +332,9 @@ pub mod skill {
             .collect::<Vec<_>>()
             .grouped_by(&nuggets);
 
-        let exercises = Exercise::belonging_to(&nuggets).order(exercises::id.asc())
+        let exercises = Exercise::belonging_to(&nuggets)
+            .filter(exercises::id.ne_all(trash::trashed_ids(conn, "exercise")?))
+            .order(exercises::id.asc())
             .load::<Exercise>(&**conn)?;
 
         let e_answers = ExerciseVariant::belonging_to(&exercises)
@@ -329,6 +383,81 @@ pub mod skill {
 
     }
 
+    pub fn get_prerequisites(conn: &Connection) -> Result<Vec<SkillPrerequisite>> {
+        use schema::skill_prerequisites;
+
+        Ok(skill_prerequisites::table.order((skill_prerequisites::skill_id.asc(),
+                                             skill_prerequisites::prerequisite_id.asc()))
+               .get_results(&**conn)?)
+    }
+
+    /// Whether `skill_id` is a direct or an indirect prerequisite of `of_skill_id`.
+    fn is_prerequisite(conn: &Connection, skill_id: i32, of_skill_id: i32) -> Result<bool> {
+        use schema::skill_prerequisites;
+
+        let mut seen = vec![of_skill_id];
+        let mut unvisited = vec![of_skill_id];
+
+        while let Some(id) = unvisited.pop() {
+            let prerequisites: Vec<i32> =
+                skill_prerequisites::table.filter(skill_prerequisites::skill_id.eq(id))
+                    .select(skill_prerequisites::prerequisite_id)
+                    .get_results(&**conn)?;
+
+            for p in prerequisites {
+                if p == skill_id {
+                    return Ok(true);
+                }
+                if !seen.contains(&p) {
+                    seen.push(p);
+                    unvisited.push(p);
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    /// Adds a prerequisite or updates its required level.
+    /// Fails with `InvalidInput` if the prerequisite would make the graph cyclic.
+    pub fn set_prerequisite(conn: &Connection,
+                            prerequisite: &SkillPrerequisite)
+                            -> Result<SkillPrerequisite> {
+        use schema::skill_prerequisites;
+
+        if prerequisite.skill_id == prerequisite.prerequisite_id ||
+           prerequisite.required_level <= 0 ||
+           is_prerequisite(conn, prerequisite.skill_id, prerequisite.prerequisite_id)? {
+            bail!(ErrorKind::InvalidInput);
+        }
+
+        let existing: Option<SkillPrerequisite> =
+            skill_prerequisites::table.filter(skill_prerequisites::skill_id
+                                                  .eq(prerequisite.skill_id))
+                .filter(skill_prerequisites::prerequisite_id.eq(prerequisite.prerequisite_id))
+                .get_result(&**conn)
+                .optional()?;
+
+        Ok(if existing.is_some() {
+               prerequisite.save_changes(&**conn)?
+           } else {
+               diesel::insert_into(skill_prerequisites::table).values(prerequisite)
+                   .get_result(&**conn)?
+           })
+    }
+
+    pub fn remove_prerequisite(conn: &Connection,
+                               skill_id: i32,
+                               prerequisite_id: i32)
+                               -> Result<Option<SkillPrerequisite>> {
+        use schema::skill_prerequisites;
+
+        Ok(diesel::delete(skill_prerequisites::table
+                              .filter(skill_prerequisites::skill_id.eq(skill_id))
+                              .filter(skill_prerequisites::prerequisite_id.eq(prerequisite_id)))
+               .get_result(&**conn)
+               .optional()?)
+    }
+
     pub fn remove(conn: &Connection, id: i32) -> Result<Option<SkillNugget>> {
         use schema::skill_nuggets;
 
@@ -138,6 +138,8 @@ pub fn create_or_update_word(conn: &Connection,
 
     info!("Create word {:?}", w);
 
+    accent::validate(&w.word)?;
+
     let nugget = skill::get_create_by_name(&*conn, &w.nugget)?;
 
     let mut audio_file = None;
@@ -171,6 +173,7 @@ pub fn create_or_update_word(conn: &Connection,
         info!("The word existed already. Returning.");
         return Ok(word);
     } else {
+        let reading = kana::reading(&w.word);
         let new_word = NewWord {
             word: &w.word,
             explanation: &w.explanation,
@@ -178,6 +181,8 @@ pub fn create_or_update_word(conn: &Connection,
             skill_nugget: nugget.id,
             skill_level: w.skill_level,
             priority: w.priority,
+            reading: &reading,
+            romaji: &kana::to_romaji(&reading),
         };
 
         let word = diesel::insert_into(words::table).values(&new_word).get_result(&**conn)?;
@@ -204,6 +209,38 @@ pub fn get_exercise(conn: &Connection,
     }
 }
 
+pub fn get_comparison(conn: &Connection, id: i32) -> Result<Option<ComparisonPair>> {
+    Ok(schema::comparison_pairs::table.filter(schema::comparison_pairs::id.eq(id))
+           .get_result(&**conn)
+           .optional()?)
+}
+
+pub fn get_comparisons(conn: &Connection) -> Result<Vec<ComparisonPair>> {
+    use schema::comparison_pairs;
+
+    let trashed = trash::trashed_ids(conn, "comparison")?;
+
+    Ok(comparison_pairs::table.filter(comparison_pairs::id.ne_all(trashed))
+           .order(comparison_pairs::id.asc())
+           .get_results(&**conn)?)
+}
+
+/// Fills in the readings of the words that don't have them yet.
+pub fn fill_missing_readings(conn: &Connection) -> Result<usize> {
+    use schema::words;
+
+    let words: Vec<Word> = words::table.filter(words::reading.eq("")).get_results(&**conn)?;
+    let count = words.len();
+
+    for mut w in words {
+        w.reading = kana::reading(&w.word);
+        w.romaji = kana::to_romaji(&w.reading);
+        let _: Word = w.save_changes(&**conn)?;
+    }
+
+    Ok(count)
+}
+
 pub fn get_word(conn: &Connection, id: i32) -> Result<Option<Word>> {
     Ok(schema::words::table.filter(schema::words::id.eq(id))
            .get_result(&**conn)
@@ -228,6 +265,15 @@ pub fn publish_exercise(conn: &Connection, id: i32, published: bool) -> Result<(
     Ok(())
 }
 
+pub fn publish_comparison(conn: &Connection, id: i32, published: bool) -> Result<()> {
+    use schema::comparison_pairs;
+    diesel::update(comparison_pairs::table
+        .filter(comparison_pairs::id.eq(id)))
+        .set(comparison_pairs::published.eq(published))
+        .execute(&**conn)?;
+    Ok(())
+}
+
 pub fn publish_word(conn: &Connection, id: i32, published: bool) -> Result<()> {
     use schema::words;
     diesel::update(words::table.filter(words::id.eq(id))).set(words::published.eq(published))
@@ -242,7 +288,12 @@ pub fn update_word(conn: &Connection,
                    -> Result<Option<Word>> {
     use schema::words;
 
+    if let Some(ref word) = item.word {
+        accent::validate(word)?;
+    }
     item.explanation = item.explanation.try_map(|s| sanitize_links(&s, image_dir))?;
+    item.reading = item.word.as_ref().map(|w| kana::reading(w));
+    item.romaji = item.reading.as_ref().map(|r| kana::to_romaji(r));
 
     let item = diesel::update(words::table.filter(words::id.eq(id))).set(&item)
         .get_result(&**conn)
@@ -274,6 +325,26 @@ pub fn update_question(conn: &Connection,
 }
 
 
+pub fn update_comparison(conn: &Connection,
+                         id: i32,
+                         item: UpdateComparisonPair)
+                         -> Result<Option<ComparisonPair>> {
+    use schema::comparison_pairs;
+
+    if item.word_a.is_some() || item.word_b.is_some() {
+        let pair = try_or!(get_comparison(conn, id)?, else return Ok(None));
+        check_comparison(conn,
+                         item.word_a.unwrap_or(pair.word_a),
+                         item.word_b.unwrap_or(pair.word_b))?;
+    }
+
+    let item = diesel::update(comparison_pairs::table.filter(comparison_pairs::id.eq(id))).set(&item)
+        .get_result(&**conn)
+        .optional()?;
+    Ok(item)
+}
+
+
 pub fn update_answer(conn: &Connection,
                      id: i32,
                      mut item: UpdateAnswer,
@@ -334,6 +405,65 @@ pub fn remove_exercise(conn: &Connection, id: i32) -> Result<bool> {
     Ok(count == 1)
 }
 
+pub fn remove_comparison(conn: &Connection, id: i32) -> Result<bool> {
+    use schema::comparison_pairs;
+
+    let count = diesel::delete(comparison_pairs::table.filter(comparison_pairs::id.eq(id)))
+        .execute(&**conn)?;
+
+    Ok(count == 1)
+}
+
+/// The words of a comparison pair must read the same and differ only by their accent.
+/// Returns `InvalidInput` if the words are the same, missing or read differently,
+/// and `InvalidAccent` if their accents can't be parsed or don't differ.
+fn check_comparison(conn: &Connection, word_a: i32, word_b: i32) -> Result<()> {
+    use schema::words;
+
+    if word_a == word_b {
+        return Err(ErrorKind::InvalidInput.into());
+    }
+
+    let word_a: Option<String> = words::table.filter(words::id.eq(word_a))
+        .select(words::word)
+        .get_result(&**conn)
+        .optional()?;
+    let word_b: Option<String> = words::table.filter(words::id.eq(word_b))
+        .select(words::word)
+        .get_result(&**conn)
+        .optional()?;
+    let (word_a, word_b) = match (word_a, word_b) {
+        (Some(a), Some(b)) => (a, b),
+        _ => return Err(ErrorKind::InvalidInput.into()),
+    };
+
+    if kana::reading(&word_a) != kana::reading(&word_b) {
+        return Err(ErrorKind::InvalidInput.into());
+    }
+
+    if accent::parse(&word_a)?.nucleus == accent::parse(&word_b)?.nucleus {
+        return Err(ErrorKind::InvalidAccent(format!("{:?} and {:?} have the same accent",
+                                                    word_a,
+                                                    word_b))
+                           .into());
+    }
+
+    Ok(())
+}
+
+pub fn post_comparison(conn: &Connection, pair: NewComparisonPair) -> Result<i32> {
+    use schema::comparison_pairs;
+
+    debug!("Post comparison pair: {:?}", pair);
+
+    check_comparison(conn, pair.word_a, pair.word_b)?;
+
+    let c: ComparisonPair =
+        diesel::insert_into(comparison_pairs::table).values(&pair).get_result(&**conn)?;
+
+    Ok(c.id)
+}
+
 pub fn post_question(conn: &Connection,
                      question: NewQuizQuestion,
                      mut answers: Vec<NewAnswer>)
@@ -374,8 +504,10 @@ pub fn post_exercise(conn: &Connection,
 }
 
 pub fn del_due_and_pending_items(conn: &Connection, user_id: i32) -> Result<()> {
-    use schema::{due_items, pending_items, question_data, exercise_data, e_asked_data, q_asked_data,
-                 e_answered_data, q_answered_data};
+    use schema::{due_items, pending_items, question_data, exercise_data, comparison_data,
+                 accent_data, dictation_data, e_asked_data, q_asked_data, c_asked_data,
+                 a_asked_data, d_asked_data, e_answered_data, q_answered_data, c_answered_data,
+                 a_answered_data, d_answered_data, pronunciation_scores};
     use diesel::expression::dsl::any;
 
     let p = diesel::update(
@@ -396,6 +528,15 @@ pub fn del_due_and_pending_items(conn: &Connection, user_id: i32) -> Result<()>
     let e = diesel::delete(exercise_data::table.filter(exercise_data::due.eq(any(due_items))))
         .execute(&**conn)?;
 
+    let c = diesel::delete(comparison_data::table.filter(comparison_data::due.eq(any(due_items))))
+        .execute(&**conn)?;
+
+    let a = diesel::delete(accent_data::table.filter(accent_data::due.eq(any(due_items))))
+        .execute(&**conn)?;
+
+    let di = diesel::delete(dictation_data::table.filter(dictation_data::due.eq(any(due_items))))
+        .execute(&**conn)?;
+
     let d =
         diesel::delete(due_items::table.filter(due_items::user_id.eq(user_id))).execute(&**conn)?;
 
@@ -410,26 +551,64 @@ pub fn del_due_and_pending_items(conn: &Connection, user_id: i32) -> Result<()>
         answers += diesel::delete(q_answered_data::table.filter(q_answered_data::id.eq(p.id)))
             .execute(&**conn)?;
 
+        answers += diesel::delete(c_answered_data::table.filter(c_answered_data::id.eq(p.id)))
+            .execute(&**conn)?;
+
+        answers += diesel::delete(a_answered_data::table.filter(a_answered_data::id.eq(p.id)))
+            .execute(&**conn)?;
+
+        answers += diesel::delete(d_answered_data::table.filter(d_answered_data::id.eq(p.id)))
+            .execute(&**conn)?;
+
+        diesel::delete(pronunciation_scores::table.filter(pronunciation_scores::id.eq(p.id)))
+            .execute(&**conn)?;
+
         asks +=
             diesel::delete(e_asked_data::table.filter(e_asked_data::id.eq(p.id))).execute(&**conn)?;
 
         asks +=
             diesel::delete(q_asked_data::table.filter(q_asked_data::id.eq(p.id))).execute(&**conn)?;
 
+        asks +=
+            diesel::delete(c_asked_data::table.filter(c_asked_data::id.eq(p.id))).execute(&**conn)?;
+
+        asks +=
+            diesel::delete(a_asked_data::table.filter(a_asked_data::id.eq(p.id))).execute(&**conn)?;
+
+        asks +=
+            diesel::delete(d_asked_data::table.filter(d_asked_data::id.eq(p.id))).execute(&**conn)?;
+
     }
 
     debug!("Deactivated {} pending items and deleted {} due items. ({} questions, {} exercises, \
-            {} asks, {} answers)",
+            {} comparisons, {} accents, {} dictations, {} asks, {} answers)",
            p,
            d,
            q,
            e,
+           c,
+           a,
+           di,
            asks,
            answers);
 
     Ok(())
 }
 
+/// Lets a suspended leech be asked again. The lapses are zeroed, so that the item isn't
+/// suspended again on the next miss.
+pub fn unsuspend_due_item(conn: &Connection, user_id: i32, due_item_id: i32) -> Result<Option<DueItem>> {
+    use schema::due_items;
+
+    let due_item = diesel::update(due_items::table.filter(due_items::id.eq(due_item_id))
+            .filter(due_items::user_id.eq(user_id)))
+        .set((due_items::suspended.eq(false), due_items::lapses.eq(0)))
+        .get_result(&**conn)
+        .optional()?;
+
+    Ok(due_item)
+}
+
 pub fn replace_audio_bundle(conn: &Connection, bundle_id: i32, new_bundle_id: i32) -> Result<()> {
     use schema::{words, question_answers};
 
@@ -547,7 +726,7 @@ pub fn sanitize_links(text: &str, image_dir: &Path) -> Result<String> {
             let mut filename = "%FT%H-%M-%SZ".to_string();
             filename.extend(thread_rng().sample_iter(Alphanumeric).take(10));
             filename.push_str(extension);
-            filename = format!("{}", chrono::offset::Utc::now().format(&filename));
+            filename = format!("{}", clock::now().format(&filename));
             new_path.push(&filename);
 
             let mut file = fs::File::create(new_path)?;
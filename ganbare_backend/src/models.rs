@@ -194,6 +194,21 @@ pub struct UpdateAudioFile {
     pub file_sha2: Option<Option<Vec<u8>>>,
 }
 
+#[derive(Insertable)]
+#[table_name="audio_pitch"]
+pub struct NewAudioPitch<'a> {
+    pub audio_file_id: i32,
+    pub contour: &'a str,
+}
+
+#[derive(Insertable)]
+#[table_name="user_audio_pitch"]
+pub struct NewUserAudioPitch<'a> {
+    pub user_id: i32,
+    pub file_name: &'a str,
+    pub contour: &'a str,
+}
+
 #[derive(Insertable, Queryable, Associations, Identifiable,
     Debug, AsChangeset, Serialize, Deserialize)]
 #[table_name="audio_bundles"]
@@ -218,7 +233,8 @@ pub struct NewQuizQuestion<'a> {
     pub skill_level: i32,
 }
 
-#[derive(Insertable, Queryable, Associations, Identifiable, AsChangeset, Debug, Serialize)]
+#[derive(Insertable, Queryable, Associations, Identifiable, AsChangeset, Debug, Serialize,
+Deserialize)]
 #[belongs_to(SkillNugget, foreign_key = "skill_id")]
 
 
@@ -255,7 +271,8 @@ pub struct NewAnswer<'a> {
     pub answer_text: &'a str,
 }
 
-#[derive(Insertable, Queryable, Associations, Identifiable, Debug, Serialize, AsChangeset)]
+#[derive(Insertable, Queryable, Associations, Identifiable, Debug, Serialize, Deserialize,
+AsChangeset)]
 #[belongs_to(QuizQuestion, foreign_key = "question_id")]
 #[belongs_to(AudioBundle, foreign_key = "q_audio_bundle")]
 #[table_name="question_answers"]
@@ -309,7 +326,8 @@ pub struct NewExercise {
     pub skill_level: i32,
 }
 
-#[derive(Insertable, Identifiable, Queryable, Associations, Debug, Serialize, Deserialize)]
+#[derive(Insertable, Identifiable, Queryable, Associations, Debug, Serialize, Deserialize,
+AsChangeset)]
 #[belongs_to(Exercise, foreign_key = "exercise_id")]
 #[belongs_to(Word, foreign_key = "id")]
 #[table_name="exercise_variants"]
@@ -326,6 +344,39 @@ pub struct UpdateExerciseVariant {
     pub exercise_id: Option<i32>,
 }
 
+#[derive(Insertable, Debug)]
+#[table_name="comparison_pairs"]
+pub struct NewComparisonPair {
+    pub skill_id: i32,
+    pub word_a: i32,
+    pub word_b: i32,
+    pub skill_level: i32,
+}
+
+#[derive(Insertable, Queryable, Associations, Identifiable,
+    Debug, Serialize, Deserialize, AsChangeset)]
+#[belongs_to(SkillNugget, foreign_key = "skill_id")]
+#[table_name="comparison_pairs"]
+pub struct ComparisonPair {
+    pub id: i32,
+    pub skill_id: i32,
+    pub word_a: i32,
+    pub word_b: i32,
+    pub published: bool,
+    pub skill_level: i32,
+}
+
+#[derive(Queryable, Debug, AsChangeset, Serialize, Deserialize, Default)]
+#[table_name="comparison_pairs"]
+#[serde(default)]
+pub struct UpdateComparisonPair {
+    pub skill_id: Option<i32>,
+    pub word_a: Option<i32>,
+    pub word_b: Option<i32>,
+    pub published: Option<bool>,
+    pub skill_level: Option<i32>,
+}
+
 #[derive(Insertable)]
 #[table_name="words"]
 pub struct NewWord<'a> {
@@ -335,9 +386,12 @@ pub struct NewWord<'a> {
     pub skill_nugget: i32,
     pub skill_level: i32,
     pub priority: i32,
+    pub reading: &'a str,
+    pub romaji: &'a str,
 }
 
-#[derive(Insertable, Queryable, Associations, Identifiable, Debug, Serialize, AsChangeset)]
+#[derive(Insertable, Queryable, Associations, Identifiable, Debug, Serialize, Deserialize,
+AsChangeset)]
 #[table_name="words"]
 #[belongs_to(SkillNugget, foreign_key = "skill_nugget")]
 #[belongs_to(AudioBundle, foreign_key = "audio_bundle")]
@@ -352,6 +406,9 @@ pub struct Word {
     pub published: bool,
     pub skill_level: i32,
     pub priority: i32,
+    /// The word in hiragana, without the accent marks.
+    pub reading: String,
+    pub romaji: String,
 }
 
 #[derive(Queryable, AsChangeset, Debug, Serialize, Deserialize, Default)]
@@ -365,11 +422,17 @@ pub struct UpdateWord {
     pub published: Option<bool>,
     pub skill_level: Option<i32>,
     pub priority: Option<i32>,
+    /// Derived from `word` by `manage::update_word`.
+    #[serde(skip)]
+    pub reading: Option<String>,
+    #[serde(skip)]
+    pub romaji: Option<String>,
 }
 
-#[derive(Insertable, Identifiable, Queryable, Associations, Debug,
+#[derive(Insertable, Identifiable, Queryable, Associations, Debug, Clone,
 AsChangeset, Serialize)]
 #[table_name="due_items"]
+#[changeset_options(treat_none_as_null = "true")]
 #[belongs_to(User, foreign_key = "user_id")]
 
 
@@ -382,6 +445,14 @@ pub struct DueItem {
     pub correct_streak_overall: i32,
     pub correct_streak_this_time: i32,
     pub item_type: String,
+    pub ease_factor: f64,
+    pub difficulty: f64,
+    pub stability: f64,
+    pub lapses: i32,
+    pub suspended: bool,
+    pub parked: bool,
+    /// When the latest lapse was counted. Cleared when the item is reviewed.
+    pub lapsed_date: Option<DateTime<Utc>>,
 }
 
 #[derive(Insertable)]
@@ -396,6 +467,129 @@ pub struct NewDueItem<'a> {
     pub item_type: &'a str,
 }
 
+#[derive(Queryable, Associations, Identifiable, Debug, Serialize)]
+#[table_name="review_log"]
+#[belongs_to(User, foreign_key = "user_id")]
+pub struct ReviewLog {
+    pub id: i32,
+    pub user_id: i32,
+    pub asked_id: i32,
+    pub item_type: String,
+    pub item_id: i32,
+    pub due_item_id: Option<i32>,
+    pub answered_date: DateTime<Utc>,
+    pub correct: bool,
+    pub answer_level: Option<i32>,
+    pub active_answer_time_ms: i32,
+    pub full_answer_time_ms: Option<i32>,
+    pub full_spent_time_ms: i32,
+    pub prev_due_delay: Option<i32>,
+    pub new_due_delay: Option<i32>,
+    pub prev_correct_streak_this_time: Option<i32>,
+    pub new_correct_streak_this_time: Option<i32>,
+    pub prev_correct_streak_overall: Option<i32>,
+    pub new_correct_streak_overall: Option<i32>,
+    pub new_due_date: Option<DateTime<Utc>>,
+    pub scheduler: Option<String>,
+    pub reconstructed: bool,
+    pub recall: Option<String>,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[table_name="review_log"]
+pub struct NewReviewLog<'a> {
+    pub user_id: i32,
+    pub asked_id: i32,
+    pub item_type: &'a str,
+    pub item_id: i32,
+    pub due_item_id: Option<i32>,
+    pub answered_date: DateTime<Utc>,
+    pub correct: bool,
+    pub answer_level: Option<i32>,
+    pub active_answer_time_ms: i32,
+    pub full_answer_time_ms: Option<i32>,
+    pub full_spent_time_ms: i32,
+    pub prev_due_delay: Option<i32>,
+    pub new_due_delay: Option<i32>,
+    pub prev_correct_streak_this_time: Option<i32>,
+    pub new_correct_streak_this_time: Option<i32>,
+    pub prev_correct_streak_overall: Option<i32>,
+    pub new_correct_streak_overall: Option<i32>,
+    pub new_due_date: Option<DateTime<Utc>>,
+    pub scheduler: Option<&'a str>,
+    pub reconstructed: bool,
+    pub recall: Option<&'a str>,
+}
+
+#[derive(Queryable, Associations, Identifiable, Debug, Serialize)]
+#[table_name="revisions"]
+#[belongs_to(User, foreign_key = "editor_id")]
+pub struct Revision {
+    pub id: i32,
+    pub item_type: String,
+    pub item_id: i32,
+    pub editor_id: Option<i32>,
+    pub action: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+    pub edited: DateTime<Utc>,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name="revisions"]
+pub struct NewRevision<'a> {
+    pub item_type: &'a str,
+    pub item_id: i32,
+    pub editor_id: Option<i32>,
+    pub action: &'a str,
+    pub before: Option<&'a str>,
+    pub after: Option<&'a str>,
+}
+
+#[derive(Queryable, Associations, Identifiable, Debug, Serialize)]
+#[table_name="publish_schedules"]
+#[belongs_to(UserGroup, foreign_key = "group_id")]
+pub struct PublishSchedule {
+    pub id: i32,
+    pub item_type: String,
+    pub item_id: i32,
+    pub publish_at: Option<DateTime<Utc>>,
+    pub unpublish_at: Option<DateTime<Utc>>,
+    pub group_id: Option<i32>,
+}
+
+#[derive(Insertable, AsChangeset, Debug)]
+#[table_name="publish_schedules"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct NewPublishSchedule<'a> {
+    pub item_type: &'a str,
+    pub item_id: i32,
+    pub publish_at: Option<DateTime<Utc>>,
+    pub unpublish_at: Option<DateTime<Utc>>,
+    pub group_id: Option<i32>,
+}
+
+#[derive(Queryable, Associations, Identifiable, Debug, Serialize)]
+#[table_name="trash"]
+#[belongs_to(User, foreign_key = "trashed_by")]
+pub struct TrashedItem {
+    pub id: i32,
+    pub item_type: String,
+    pub item_id: i32,
+    pub trashed_by: Option<i32>,
+    pub trashed: DateTime<Utc>,
+    pub cascaded_from: Option<i32>,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name="trash"]
+pub struct NewTrashedItem<'a> {
+    pub item_type: &'a str,
+    pub item_id: i32,
+    pub trashed_by: i32,
+    pub cascaded_from: Option<i32>,
+}
+
 #[derive(Insertable, Queryable, Associations, Identifiable, Debug,
 AsChangeset, Serialize, Deserialize)]
 #[table_name="pending_items"]
@@ -412,6 +606,7 @@ pub struct PendingItem {
     pub pending: bool,
     pub item_type: String,
     pub test_item: bool,
+    pub practice: bool,
 }
 
 #[derive(Insertable, Associations, Debug, AsChangeset)]
@@ -421,6 +616,25 @@ pub struct NewPendingItem<'a> {
     pub audio_file_id: i32,
     pub item_type: &'a str,
     pub test_item: bool,
+    pub practice: bool,
+}
+
+#[derive(Insertable, Queryable, Associations, Identifiable, Debug, AsChangeset)]
+#[table_name="answer_submissions"]
+#[primary_key(user_id, idempotency_key)]
+#[belongs_to(User, foreign_key = "user_id")]
+pub struct AnswerSubmission {
+    pub user_id: i32,
+    pub idempotency_key: String,
+    pub next_pending_item: Option<i32>,
+    pub submitted: DateTime<Utc>,
+}
+
+#[derive(Insertable)]
+#[table_name="answer_submissions"]
+pub struct NewAnswerSubmission<'a> {
+    pub user_id: i32,
+    pub idempotency_key: &'a str,
 }
 
 #[derive(Insertable, Queryable, Associations, Identifiable, Debug, Clone, AsChangeset)]
@@ -435,7 +649,7 @@ pub struct QAskedData {
     pub correct_qa_id: i32,
 }
 
-#[derive(Insertable, Queryable, Associations, Identifiable, Debug, Clone, Serialize)]
+#[derive(Insertable, Queryable, Associations, Identifiable, Debug, Clone, Serialize, Deserialize)]
 #[table_name="q_answered_data"]
 #[belongs_to(QAskedData, foreign_key = "id")]
 pub struct QAnsweredData {
@@ -445,6 +659,9 @@ pub struct QAnsweredData {
     pub active_answer_time_ms: i32,
     pub full_answer_time_ms: i32,
     pub full_spent_time_ms: i32,
+    /// Answered in cram mode. Set by the server.
+    #[serde(skip_deserializing)]
+    pub practice: bool,
 }
 
 #[derive(Insertable, Queryable, Associations, Identifiable, Debug, Clone, AsChangeset)]
@@ -460,7 +677,7 @@ pub struct EAskedData {
 }
 
 #[derive(Insertable, Queryable, Associations, Identifiable,
-    Debug, Clone, AsChangeset, Serialize)]
+    Debug, Clone, AsChangeset, Serialize, Deserialize)]
 #[table_name="e_answered_data"]
 #[belongs_to(EAskedData, foreign_key = "id")]
 pub struct EAnsweredData {
@@ -472,6 +689,9 @@ pub struct EAnsweredData {
     pub answer_level: i32,
     pub full_spent_time_ms: i32,
     pub reflected_time_ms: i32,
+    /// Answered in cram mode. Set by the server.
+    #[serde(skip_deserializing)]
+    pub practice: bool,
 }
 
 #[derive(Identifiable, Insertable, Queryable, Associations, Debug, Clone,
@@ -487,7 +707,7 @@ pub struct WAskedData {
 }
 
 #[derive(Identifiable, Insertable, Queryable, Associations,
-    Debug, Clone, AsChangeset, Serialize)]
+    Debug, Clone, AsChangeset, Serialize, Deserialize)]
 #[table_name="w_answered_data"]
 #[belongs_to(WAskedData, foreign_key = "id")]
 pub struct WAnsweredData {
@@ -496,6 +716,133 @@ pub struct WAnsweredData {
     pub audio_times: i32,
     pub checked_date: DateTime<Utc>,
     pub active_answer_time_ms: i32,
+    /// Answered in cram mode. Set by the server.
+    #[serde(skip_deserializing)]
+    pub practice: bool,
+}
+
+#[derive(Insertable, Queryable, Associations, Identifiable, Debug, Clone, AsChangeset)]
+#[table_name="c_asked_data"]
+#[belongs_to(PendingItem, foreign_key = "id")]
+#[belongs_to(ComparisonPair, foreign_key = "pair_id")]
+#[belongs_to(Word, foreign_key = "word_id")]
+
+pub struct CAskedData {
+    pub id: i32,
+    pub pair_id: i32,
+    pub word_id: i32,
+    pub first_audio_id: i32,
+    pub second_audio_id: i32,
+    pub right_choice: i32,
+}
+
+#[derive(Insertable, Queryable, Associations, Identifiable, Debug, Clone, Serialize, Deserialize)]
+#[table_name="c_answered_data"]
+#[belongs_to(CAskedData, foreign_key = "id")]
+pub struct CAnsweredData {
+    pub id: i32,
+    pub answered_choice: Option<i32>,
+    pub answered_date: DateTime<Utc>,
+    pub active_answer_time_ms: i32,
+    pub full_answer_time_ms: i32,
+    pub full_spent_time_ms: i32,
+    /// Answered in cram mode. Set by the server.
+    #[serde(skip_deserializing)]
+    pub practice: bool,
+}
+
+#[derive(Insertable, Queryable, Associations, Identifiable, Debug, Clone, AsChangeset)]
+#[table_name="a_asked_data"]
+#[belongs_to(PendingItem, foreign_key = "id")]
+#[belongs_to(Word, foreign_key = "word_id")]
+
+pub struct AAskedData {
+    pub id: i32,
+    pub word_id: i32,
+    pub right_nucleus: Option<i32>,
+}
+
+#[derive(Insertable, Queryable, Associations, Identifiable, Debug, Clone, Serialize, Deserialize)]
+#[table_name="a_answered_data"]
+#[belongs_to(AAskedData, foreign_key = "id")]
+pub struct AAnsweredData {
+    pub id: i32,
+    pub answered_nucleus: Option<i32>,
+    pub answered_date: DateTime<Utc>,
+    pub active_answer_time_ms: i32,
+    pub full_answer_time_ms: i32,
+    pub full_spent_time_ms: i32,
+    /// Answered in cram mode. Set by the server.
+    #[serde(skip_deserializing)]
+    pub practice: bool,
+}
+
+#[derive(Insertable, Queryable, Associations, Identifiable, Debug, Clone, Serialize, Deserialize)]
+#[table_name="pronunciation_scores"]
+#[belongs_to(EAskedData, foreign_key = "id")]
+pub struct PronunciationScore {
+    pub id: i32,
+    pub file_name: String,
+    /// From 0 to 100. `None` if not scored yet, or if the recording couldn't be scored.
+    pub score: Option<f32>,
+    pub scored: Option<DateTime<Utc>>,
+}
+
+#[derive(Insertable)]
+#[table_name="word_lists"]
+pub struct NewWordList<'a> {
+    pub list_name: &'a str,
+    pub list_type: &'a str,
+}
+
+#[derive(Identifiable, Queryable, Debug, Serialize)]
+#[table_name="word_lists"]
+pub struct WordList {
+    pub id: i32,
+    pub list_name: String,
+    /// Either "frequency" or "jlpt".
+    pub list_type: String,
+    pub imported: DateTime<Utc>,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name="word_list_entries"]
+pub struct NewWordListEntry {
+    pub list_id: i32,
+    pub word: String,
+    pub reading: String,
+    /// The position in a frequency list, or the JLPT level (5 for N5).
+    pub rank: i32,
+}
+
+#[derive(Insertable, Queryable, Associations, Identifiable, Debug, Clone, AsChangeset)]
+#[table_name="d_asked_data"]
+#[belongs_to(PendingItem, foreign_key = "id")]
+#[belongs_to(Word, foreign_key = "word_id")]
+
+pub struct DAskedData {
+    pub id: i32,
+    pub word_id: i32,
+    pub expected_kana: String,
+}
+
+#[derive(Insertable, Queryable, Associations, Identifiable, Debug, Clone, Serialize, Deserialize)]
+#[table_name="d_answered_data"]
+#[belongs_to(DAskedData, foreign_key = "id")]
+pub struct DAnsweredData {
+    pub id: i32,
+    pub answered_text: String,
+    /// The similarity of the answer to the expected kana, from 0 to 100. Graded by the server.
+    #[serde(default)]
+    pub answer_level: i32,
+    pub answered_date: DateTime<Utc>,
+    pub active_answer_time_ms: i32,
+    pub full_answer_time_ms: i32,
+    pub full_spent_time_ms: i32,
+    pub audio_times: i32,
+    /// Answered in cram mode. Set by the server.
+    #[serde(skip_deserializing)]
+    pub practice: bool,
 }
 
 #[derive(Insertable, Queryable, Associations, Debug,
@@ -517,6 +864,36 @@ pub struct ExerciseData {
     pub due: i32,
 }
 
+#[derive(Insertable, Queryable, Associations, Debug,
+AsChangeset, Serialize, Deserialize)]
+#[table_name="comparison_data"]
+#[belongs_to(DueItem, foreign_key = "due")]
+#[belongs_to(ComparisonPair, foreign_key = "pair_id")]
+pub struct ComparisonData {
+    pub pair_id: i32,
+    pub due: i32,
+}
+
+#[derive(Insertable, Queryable, Associations, Debug,
+AsChangeset, Serialize, Deserialize)]
+#[table_name="accent_data"]
+#[belongs_to(DueItem, foreign_key = "due")]
+#[belongs_to(Word, foreign_key = "word_id")]
+pub struct AccentData {
+    pub word_id: i32,
+    pub due: i32,
+}
+
+#[derive(Insertable, Queryable, Associations, Debug,
+AsChangeset, Serialize, Deserialize)]
+#[table_name="dictation_data"]
+#[belongs_to(DueItem, foreign_key = "due")]
+#[belongs_to(Word, foreign_key = "word_id")]
+pub struct DictationData {
+    pub word_id: i32,
+    pub due: i32,
+}
+
 #[derive(Insertable)]
 #[table_name="skill_data"]
 pub struct NewSkillData {
@@ -537,7 +914,20 @@ pub struct SkillData {
     pub skill_level: i32,
 }
 
-#[derive(Insertable, Queryable, Associations, Debug, AsChangeset, Identifiable, Serialize)]
+/// New content of `skill_id` is introduced only after the user has reached
+/// `required_level` on `prerequisite_id`.
+#[derive(Identifiable, Insertable, Queryable, Associations,
+Debug, Clone, AsChangeset, Serialize, Deserialize)]
+#[table_name="skill_prerequisites"]
+#[primary_key(skill_id, prerequisite_id)]
+#[belongs_to(SkillNugget, foreign_key = "skill_id")]
+pub struct SkillPrerequisite {
+    pub skill_id: i32,
+    pub prerequisite_id: i32,
+    pub required_level: i32,
+}
+
+#[derive(Insertable, Queryable, Associations, Debug, Clone, AsChangeset, Identifiable, Serialize)]
 #[belongs_to(User, foreign_key = "id")]
 #[table_name="user_metrics"]
 pub struct UserMetrics {
@@ -559,6 +949,14 @@ pub struct UserMetrics {
     pub streak_limit: i32,
     pub cooldown_delay: i32,
     pub streak_skill_bump_criteria: i32,
+    pub timezone: String,
+    pub day_rollover_hour: i32,
+    pub leech_threshold: i32,
+    pub slow_answer_percent: i32,
+    pub fast_answer_percent: i32,
+    pub max_good_audio_times: i32,
+    pub hard_interval_percent: i32,
+    pub easy_interval_percent: i32,
 }
 
 #[derive(Debug, AsChangeset, Identifiable, Deserialize, Default)]
@@ -583,6 +981,14 @@ pub struct UpdateUserMetrics {
     pub streak_limit: Option<i32>,
     pub cooldown_delay: Option<i32>,
     pub streak_skill_bump_criteria: Option<i32>,
+    pub timezone: Option<String>,
+    pub day_rollover_hour: Option<i32>,
+    pub leech_threshold: Option<i32>,
+    pub slow_answer_percent: Option<i32>,
+    pub fast_answer_percent: Option<i32>,
+    pub max_good_audio_times: Option<i32>,
+    pub hard_interval_percent: Option<i32>,
+    pub easy_interval_percent: Option<i32>,
 }
 
 #[derive(Insertable)]
@@ -0,0 +1,279 @@
+//! Extraction of pitch (F0) contours from the narrator audio and the recordings of the learners.
+//!
+//! The audio is decoded with ffmpeg, which handles both the mp3 files of `audio_files` and the
+//! ogg/opus recordings in the user audio directory. The F0 is estimated with the YIN algorithm
+//! (de Cheveigné & Kawahara, 2002). Extracting is slowish, so the contours are cached in
+//! the database.
+
+use super::*;
+use std::path::Path;
+use std::process::Command;
+use serde::{Serialize, Deserialize};
+
+lazy_static! {
+    static ref FFMPEG : String = {
+        dotenv::dotenv().ok();
+        std::env::var("GANBARE_FFMPEG").unwrap_or_else(|_| "ffmpeg".into())
+    };
+}
+
+/// The audio is resampled to this rate before the analysis. Plenty for speech.
+const SAMPLE_RATE: u32 = 16_000;
+const HOP_MS: u32 = 10;
+const WINDOW_MS: u32 = 25;
+const MIN_F0: f32 = 60.0;
+const MAX_F0: f32 = 500.0;
+const YIN_THRESHOLD: f32 = 0.15;
+/// Frames quieter than this compared to the loudest frame are considered unvoiced.
+const VOICING_RMS_RATIO: f32 = 0.05;
+const MIN_RMS: f32 = 0.001;
+/// Voiced runs shorter than this many frames are considered noise.
+const MIN_VOICED_RUN: usize = 3;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PitchContour {
+    /// The time between two consecutive frames.
+    pub hop_ms: u32,
+    /// The fundamental frequency of each frame in Hz. `None` if the frame is unvoiced.
+    pub f0: Vec<Option<f32>>,
+}
+
+/// The contour of the narrator together with the contour of a recording of the learner,
+/// for overlaying the two.
+#[derive(Serialize, Debug, Clone)]
+pub struct PitchOverlay {
+    pub audio: PitchContour,
+    pub recording: Option<PitchContour>,
+}
+
+/// Decodes an audio file into mono samples at `SAMPLE_RATE`.
+pub fn decode(path: &Path) -> Result<Vec<f32>> {
+    let output = Command::new(&*FFMPEG).args(&["-v", "error", "-i"])
+        .arg(path)
+        .args(&["-f", "f32le", "-ac", "1", "-ar", &SAMPLE_RATE.to_string(), "-"])
+        .output()
+        .chain_err(|| "Couldn't run ffmpeg!")?;
+
+    if !output.status.success() {
+        return Err(ErrorKind::AudioDecodingFailed(String::from_utf8_lossy(&output.stderr)
+                                                      .into_owned())
+                       .into());
+    }
+
+    Ok(output.stdout
+           .chunks_exact(4)
+           .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+           .collect())
+}
+
+/// Refines the position of a minimum by fitting a parabola through it and its neighbours.
+fn parabolic_interpolation(y: &[f32], x: usize) -> f32 {
+    if x < 1 || x + 1 >= y.len() {
+        return x as f32;
+    }
+    let (a, b, c) = (y[x - 1], y[x], y[x + 1]);
+    let denominator = a - 2.0 * b + c;
+    if denominator.abs() < std::f32::EPSILON {
+        x as f32
+    } else {
+        x as f32 + 0.5 * (a - c) / denominator
+    }
+}
+
+/// Returns the period of the frame in samples, or `None` if there's no clear periodicity.
+/// The frame must be `window + max_tau` samples long.
+fn yin_period(frame: &[f32], window: usize, min_tau: usize, max_tau: usize) -> Option<f32> {
+    let mut cmnd = vec![1.0_f32; max_tau + 1];
+    let mut running_sum = 0.0;
+    for tau in 1..=max_tau {
+        let diff: f32 = frame[..window]
+            .iter()
+            .zip(&frame[tau..tau + window])
+            .map(|(a, b)| (a - b) * (a - b))
+            .sum();
+        running_sum += diff;
+        if running_sum > 0.0 {
+            cmnd[tau] = diff * tau as f32 / running_sum;
+        }
+    }
+
+    let mut tau = min_tau.max(2);
+    while tau < max_tau {
+        if cmnd[tau] < YIN_THRESHOLD {
+            while tau + 1 < max_tau && cmnd[tau + 1] < cmnd[tau] {
+                tau += 1;
+            }
+            return Some(parabolic_interpolation(&cmnd, tau));
+        }
+        tau += 1;
+    }
+    None
+}
+
+fn rms(samples: &[f32]) -> f32 {
+    (samples.iter().map(|s| s * s).sum::<f32>() / samples.len().max(1) as f32).sqrt()
+}
+
+/// Extracts the F0 contour from mono samples.
+pub fn extract(samples: &[f32], sample_rate: u32) -> PitchContour {
+    let hop = (sample_rate * HOP_MS / 1000) as usize;
+    let window = (sample_rate * WINDOW_MS / 1000) as usize;
+    let min_tau = (sample_rate as f32 / MAX_F0).floor() as usize;
+    let max_tau = (sample_rate as f32 / MIN_F0).ceil() as usize;
+    let frame_len = window + max_tau;
+
+    if samples.len() < frame_len {
+        return PitchContour {
+            hop_ms: HOP_MS,
+            f0: vec![],
+        };
+    }
+
+    let frames: Vec<&[f32]> = (0..(samples.len() - frame_len) / hop + 1)
+        .map(|i| &samples[i * hop..i * hop + frame_len])
+        .collect();
+    let loudness: Vec<f32> = frames.iter().map(|f| rms(&f[..window])).collect();
+    let loudest = loudness.iter().cloned().fold(0.0, f32::max);
+
+    let mut f0: Vec<Option<f32>> = frames.iter()
+        .zip(&loudness)
+        .map(|(frame, &loudness)| {
+            if loudness < MIN_RMS || loudness < loudest * VOICING_RMS_RATIO {
+                return None;
+            }
+            yin_period(frame, window, min_tau, max_tau).map(|period| sample_rate as f32 / period)
+        })
+        .collect();
+
+    // Clear the voiced runs that are too short to be speech.
+    let mut run_start = 0;
+    for i in 0..=f0.len() {
+        if i < f0.len() && f0[i].is_some() {
+            continue;
+        }
+        if i - run_start < MIN_VOICED_RUN {
+            for frame in &mut f0[run_start..i] {
+                *frame = None;
+            }
+        }
+        run_start = i + 1;
+    }
+
+    PitchContour {
+        hop_ms: HOP_MS,
+        f0: f0,
+    }
+}
+
+pub fn extract_file(path: &Path) -> Result<PitchContour> {
+    Ok(extract(&decode(path)?, SAMPLE_RATE))
+}
+
+fn parse_cached(contour: &str) -> Result<PitchContour> {
+    serde_json::from_str(contour)
+        .chain_err(|| ErrorKind::DatabaseOdd("A cached pitch contour isn't valid JSON!"))
+}
+
+fn serialize(contour: &PitchContour) -> Result<String> {
+    serde_json::to_string(contour).chain_err(|| "Couldn't serialize the pitch contour!")
+}
+
+/// Whether an audio file has been played to the user as an exercise. The learners get
+/// the pitch contours of those files only, so they can't make the server extract any file.
+pub fn is_exercise_audio_of(conn: &Connection, user_id: i32, audio_file_id: i32) -> Result<bool> {
+    use schema::pending_items;
+
+    let count: i64 = pending_items::table.filter(pending_items::user_id.eq(user_id))
+        .filter(pending_items::audio_file_id.eq(audio_file_id))
+        .filter(pending_items::item_type.eq("exercise"))
+        .count()
+        .get_result(&**conn)?;
+
+    Ok(count > 0)
+}
+
+/// The pitch contour of a file in `audio_files`. Extracted on the first request and cached.
+pub fn for_audio_file(conn: &Connection,
+                      audio_dir: &Path,
+                      audio_file_id: i32)
+                      -> Result<PitchContour> {
+    use schema::audio_pitch;
+
+    let cached: Option<String> =
+        audio_pitch::table.filter(audio_pitch::audio_file_id.eq(audio_file_id))
+            .select(audio_pitch::contour)
+            .get_result(&**conn)
+            .optional()?;
+
+    if let Some(cached) = cached {
+        return parse_cached(&cached);
+    }
+
+    let file = audio::get_audio_file_by_id(conn, audio_file_id)?;
+    let contour = extract_file(&audio_dir.join(&file.file_path))?;
+
+    diesel::insert_into(audio_pitch::table).values(&NewAudioPitch {
+                                                    audio_file_id: audio_file_id,
+                                                    contour: &serialize(&contour)?,
+                                                })
+        .on_conflict_do_nothing()
+        .execute(&**conn)?;
+
+    Ok(contour)
+}
+
+/// The pitch contour of a recording of the user. Extracted on the first request and cached.
+pub fn for_user_audio(conn: &Connection,
+                      user_audio_dir: &Path,
+                      user_id: i32,
+                      file_name: &str)
+                      -> Result<PitchContour> {
+    use schema::user_audio_pitch;
+
+    if file_name.contains('/') || file_name.contains('\\') || file_name.starts_with('.') {
+        return Err(ErrorKind::FileNotFound.into());
+    }
+
+    let cached: Option<String> =
+        user_audio_pitch::table.filter(user_audio_pitch::user_id.eq(user_id))
+            .filter(user_audio_pitch::file_name.eq(file_name))
+            .select(user_audio_pitch::contour)
+            .get_result(&**conn)
+            .optional()?;
+
+    if let Some(cached) = cached {
+        return parse_cached(&cached);
+    }
+
+    let path = user_audio_dir.join(file_name);
+    if !path.is_file() {
+        return Err(ErrorKind::FileNotFound.into());
+    }
+    let contour = extract_file(&path)?;
+
+    diesel::insert_into(user_audio_pitch::table).values(&NewUserAudioPitch {
+                                                         user_id: user_id,
+                                                         file_name: file_name,
+                                                         contour: &serialize(&contour)?,
+                                                     })
+        .on_conflict_do_nothing()
+        .execute(&**conn)?;
+
+    Ok(contour)
+}
+
+#[test]
+fn test_extract() {
+    let sine: Vec<f32> = (0..SAMPLE_RATE / 2)
+        .map(|i| 0.5 * (2.0 * std::f32::consts::PI * 200.0 * i as f32 / SAMPLE_RATE as f32).sin())
+        .collect();
+    let contour = extract(&sine, SAMPLE_RATE);
+    assert!(!contour.f0.is_empty());
+    for f0 in &contour.f0 {
+        let f0 = f0.expect("A pure tone is voiced throughout.");
+        assert!((f0 - 200.0).abs() < 2.0, "{}", f0);
+    }
+
+    let silence = vec![0.0; SAMPLE_RATE as usize / 2];
+    assert!(extract(&silence, SAMPLE_RATE).f0.iter().all(Option::is_none));
+}
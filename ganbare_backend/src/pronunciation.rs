@@ -0,0 +1,274 @@
+//! Automatic scoring of the pronunciation in the exercise recordings.
+//!
+//! The pitch contour of the recording of the learner is compared with the contour of the
+//! narrator audio that was asked. Both contours are converted to semitones around the median
+//! of the speaker, so that the register of the voice doesn't matter, and aligned with dynamic
+//! time warping (DTW), so that the tempo doesn't matter either. Only the voiced frames are
+//! compared, so pauses and silence before and after the word don't affect the score.
+//!
+//! The scoring is done offline, by the background thread of the server or by the
+//! `score_pronunciation` binary. For the users in the "pronunciation_scheduling" group,
+//! the recording is scored when the answer is logged, and a poor score fails the exercise
+//! even if the user assessed their answer as correct.
+
+use super::*;
+use std::path::Path;
+use pitch::PitchContour;
+use error_chain::bail;
+use serde::Serialize;
+
+/// Recordings that score under this fail the exercise for the users
+/// in the "pronunciation_scheduling" group.
+pub const PASSING_SCORE: f32 = 50.0;
+/// The mean deviation in semitones that drops the score from 100 by a factor of e.
+const SCORE_SCALE_SEMITONES: f32 = 4.0;
+/// Contours with fewer voiced frames than this are too short to be scored.
+const MIN_VOICED_FRAMES: usize = 10;
+
+/// The voiced frames of the contour, in semitones relative to their median.
+fn semitones(contour: &PitchContour) -> Vec<f32> {
+    let voiced: Vec<f32> = contour.f0.iter().filter_map(|&f| f).collect();
+    if voiced.is_empty() {
+        return voiced;
+    }
+    let mut sorted = voiced.clone();
+    sorted.sort_by(|a, b| a.partial_cmp(b).expect("F0 values are never NaN"));
+    let median = sorted[sorted.len() / 2];
+    voiced.iter().map(|f| 12.0 * (f / median).log2()).collect()
+}
+
+/// The mean absolute difference of the two sequences along their best alignment.
+/// The diagonal steps are weighted twice, so the cost is normalized by the summed length.
+pub fn dtw_distance(a: &[f32], b: &[f32]) -> f32 {
+    assert!(!a.is_empty() && !b.is_empty());
+
+    let mut prev = vec![std::f32::INFINITY; b.len() + 1];
+    let mut curr = vec![std::f32::INFINITY; b.len() + 1];
+    prev[0] = 0.0;
+
+    for x in a {
+        curr[0] = std::f32::INFINITY;
+        for (j, y) in b.iter().enumerate() {
+            let d = (x - y).abs();
+            curr[j + 1] = (prev[j] + 2.0 * d).min(prev[j + 1] + d).min(curr[j] + d);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    prev[b.len()] / (a.len() + b.len()) as f32
+}
+
+/// Scores the attempt against the reference from 0 to 100.
+/// Returns `None` if either of them has too little voiced speech to compare.
+pub fn score_contours(reference: &PitchContour, attempt: &PitchContour) -> Option<f32> {
+    let reference = semitones(reference);
+    let attempt = semitones(attempt);
+    if reference.len() < MIN_VOICED_FRAMES || attempt.len() < MIN_VOICED_FRAMES {
+        return None;
+    }
+    let distance = dtw_distance(&reference, &attempt);
+    Some(100.0 * (-distance / SCORE_SCALE_SEMITONES).exp())
+}
+
+/// Links the recording that the user made while answering an exercise with the exercise.
+pub fn register_recording(conn: &Connection,
+                          user_id: i32,
+                          asked_id: i32,
+                          file_name: &str)
+                          -> Result<()> {
+    use schema::{pending_items, e_asked_data, pronunciation_scores};
+
+    let asked: Option<EAskedData> = e_asked_data::table.inner_join(pending_items::table)
+        .filter(pending_items::id.eq(asked_id))
+        .filter(pending_items::user_id.eq(user_id))
+        .select(e_asked_data::all_columns)
+        .get_result(&**conn)
+        .optional()?;
+
+    if asked.is_none() {
+        bail!(ErrorKind::InvalidInput);
+    }
+
+    diesel::insert_into(pronunciation_scores::table).values(&PronunciationScore {
+                                                             id: asked_id,
+                                                             file_name: file_name.to_owned(),
+                                                             score: None,
+                                                             scored: None,
+                                                         })
+        .on_conflict_do_nothing()
+        .execute(&**conn)?;
+
+    Ok(())
+}
+
+/// A contour that can't be extracted because the audio is missing or can't be decoded
+/// is `None`; other errors are returned.
+fn contour_or_none(contour: Result<PitchContour>, file: &str) -> Result<Option<PitchContour>> {
+    match contour {
+        Ok(contour) => Ok(Some(contour)),
+        Err(e) => {
+            match *e.kind() {
+                ErrorKind::FileNotFound |
+                ErrorKind::AudioDecodingFailed(_) => {
+                    warn!("Couldn't score against {:?}: {}", file, e);
+                    Ok(None)
+                }
+                _ => Err(e),
+            }
+        }
+    }
+}
+
+/// Scores the recording linked with the exercise, unless it's scored already.
+/// If the recording or the narrator audio is missing or can't be decoded, the recording is
+/// marked as scored without a score.
+pub fn score(conn: &Connection,
+             audio_dir: &Path,
+             user_audio_dir: &Path,
+             asked_id: i32)
+             -> Result<Option<f32>> {
+    use schema::{pending_items, pronunciation_scores};
+
+    let recording: PronunciationScore =
+        pronunciation_scores::table.filter(pronunciation_scores::id.eq(asked_id))
+            .get_result(&**conn)?;
+
+    if recording.scored.is_some() {
+        return Ok(recording.score);
+    }
+
+    let pending_item: PendingItem = pending_items::table.filter(pending_items::id.eq(asked_id))
+        .get_result(&**conn)?;
+
+    let reference = contour_or_none(pitch::for_audio_file(conn,
+                                                          audio_dir,
+                                                          pending_item.audio_file_id),
+                                    &format!("audio file {}", pending_item.audio_file_id))?;
+    let attempt = contour_or_none(pitch::for_user_audio(conn,
+                                                        user_audio_dir,
+                                                        pending_item.user_id,
+                                                        &recording.file_name),
+                                  &recording.file_name)?;
+
+    let score = match (reference, attempt) {
+        (Some(reference), Some(attempt)) => score_contours(&reference, &attempt),
+        _ => None,
+    };
+
+    diesel::update(pronunciation_scores::table.filter(pronunciation_scores::id.eq(asked_id)))
+        .set((pronunciation_scores::score.eq(score),
+              pronunciation_scores::scored.eq(Some(clock::now()))))
+        .execute(&**conn)?;
+
+    Ok(score)
+}
+
+/// Scores all the recordings that aren't scored yet. Returns the number of recordings scored.
+/// A recording that fails to be scored is logged and left for the next time, so that it
+/// doesn't hold up the rest.
+pub fn score_unscored(conn: &Connection, audio_dir: &Path, user_audio_dir: &Path) -> Result<usize> {
+    use schema::pronunciation_scores;
+
+    let unscored: Vec<i32> =
+        pronunciation_scores::table.filter(pronunciation_scores::scored.is_null())
+            .select(pronunciation_scores::id)
+            .order(pronunciation_scores::id.asc())
+            .get_results(&**conn)?;
+
+    let mut count = 0;
+    for &asked_id in &unscored {
+        match score(conn, audio_dir, user_audio_dir, asked_id) {
+            Ok(_) => count += 1,
+            Err(e) => {
+                warn!("Couldn't score the recording of the exercise answer {}: {}",
+                      asked_id,
+                      e)
+            }
+        }
+    }
+
+    Ok(count)
+}
+
+/// Whether the pronunciation is good enough for the exercise answer to count as correct.
+/// Only the users in the "pronunciation_scheduling" group may fail by pronunciation,
+/// and answers without a score always pass.
+pub fn passes(conn: &Connection, user_id: i32, asked_id: i32) -> Result<bool> {
+    use schema::pronunciation_scores;
+
+    if !user::check_user_group(conn, user_id, "pronunciation_scheduling")? {
+        return Ok(true);
+    }
+
+    let score: Option<Option<f32>> =
+        pronunciation_scores::table.filter(pronunciation_scores::id.eq(asked_id))
+            .select(pronunciation_scores::score)
+            .get_result(&**conn)
+            .optional()?;
+
+    Ok(score.and_then(|s| s).map(|s| s >= PASSING_SCORE).unwrap_or(true))
+}
+
+#[derive(Serialize, Debug)]
+pub struct ScoredAnswer {
+    pub recording: PronunciationScore,
+    pub word_id: i32,
+    /// The self-assessment of the user.
+    pub answer_level: Option<i32>,
+    pub answered_date: chrono::DateTime<chrono::Utc>,
+}
+
+/// The scored exercise answers of the user, the latest first.
+pub fn get_scores(conn: &Connection, user_id: i32) -> Result<Vec<ScoredAnswer>> {
+    use schema::{pending_items, e_asked_data, e_answered_data, pronunciation_scores};
+
+    let scores: Vec<(PronunciationScore, i32, Option<i32>, chrono::DateTime<chrono::Utc>)> =
+        pronunciation_scores::table.inner_join(e_asked_data::table
+                                                   .inner_join(pending_items::table)
+                                                   .inner_join(e_answered_data::table))
+            .filter(pending_items::user_id.eq(user_id))
+            .select((pronunciation_scores::all_columns,
+                     e_asked_data::word_id,
+                     e_answered_data::answer_level,
+                     e_answered_data::answered_date))
+            .order(e_answered_data::answered_date.desc())
+            .get_results(&**conn)?;
+
+    Ok(scores.into_iter()
+           .map(|(recording, word_id, answer_level, answered_date)| {
+                    ScoredAnswer {
+                        recording,
+                        word_id,
+                        answer_level,
+                        answered_date,
+                    }
+                })
+           .collect())
+}
+
+#[test]
+fn test_score_contours() {
+    let rise_fall = |base: f32, len: usize| {
+        PitchContour {
+            hop_ms: 10,
+            f0: (0..len)
+                .map(|i| Some(base * (1.0 + 0.3 * (i as f32 / len as f32 * 3.14).sin())))
+                .collect(),
+        }
+    };
+    let flat = |base: f32, len: usize| {
+        PitchContour {
+            hop_ms: 10,
+            f0: vec![Some(base); len],
+        }
+    };
+
+    // A different voice and tempo with the same melody scores well
+    let same = score_contours(&rise_fall(120.0, 40), &rise_fall(220.0, 60)).unwrap();
+    assert!(same > 90.0, "{}", same);
+
+    let different = score_contours(&rise_fall(120.0, 40), &flat(220.0, 60)).unwrap();
+    assert!(different < same, "{} {}", different, same);
+
+    assert_eq!(score_contours(&rise_fall(120.0, 40), &flat(220.0, 5)), None);
+}
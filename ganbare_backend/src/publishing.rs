@@ -0,0 +1,189 @@
+//! Scheduled publishing of words, questions, exercises and events.
+//!
+//! A schedule without a group is applied by the background thread: the item is published
+//! at `publish_at` and unpublished at `unpublish_at`, and the schedule is removed when it has
+//! nothing left to apply. A schedule with a group leaves the item a draft for everyone else,
+//! but the members of the group see it as published between `publish_at` and `unpublish_at`.
+
+use super::*;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use error_chain::bail;
+
+pub const ITEM_TYPES: &[&str] = &["word", "question", "exercise", "event"];
+
+#[derive(Deserialize, Debug)]
+pub struct Schedule {
+    pub publish_at: Option<DateTime<Utc>>,
+    pub unpublish_at: Option<DateTime<Utc>>,
+    pub group_id: Option<i32>,
+}
+
+/// An SQL condition for the raw queries that choose new content: the item with the id in
+/// `id_column` has a schedule that publishes it for the user at the moment.
+pub fn sql_scheduled_for_user(item_type: &str, id_column: &str, user_id: i32) -> String {
+    format!(r###"EXISTS (
+        SELECT 1
+            FROM publish_schedules AS ps
+            JOIN group_memberships AS gm
+            ON gm.group_id=ps.group_id
+            WHERE ps.item_type='{}' AND ps.item_id={} AND gm.user_id={} AND
+                COALESCE(ps.publish_at <= '{now}', true) AND
+                COALESCE(ps.unpublish_at > '{now}', true)
+    )"###,
+            item_type,
+            id_column,
+            user_id,
+            now = clock::now().to_rfc3339())
+}
+
+/// The ids of the items of a type that have a schedule that publishes them for the user
+/// at the moment.
+pub fn scheduled_for_user(conn: &Connection, item_type: &str, user_id: i32) -> Result<Vec<i32>> {
+    use schema::{publish_schedules, group_memberships};
+
+    let now = clock::now();
+
+    let groups = group_memberships::table.filter(group_memberships::user_id.eq(user_id))
+        .select(group_memberships::group_id)
+        .get_results::<i32>(&**conn)?;
+
+    Ok(publish_schedules::table.filter(publish_schedules::item_type.eq(item_type))
+           .filter(publish_schedules::group_id.eq_any(groups))
+           .filter(publish_schedules::publish_at.le(now).or(publish_schedules::publish_at.is_null()))
+           .filter(publish_schedules::unpublish_at.gt(now)
+                       .or(publish_schedules::unpublish_at.is_null()))
+           .select(publish_schedules::item_id)
+           .get_results(&**conn)?)
+}
+
+pub fn is_scheduled_for_user(conn: &Connection,
+                             item_type: &str,
+                             item_id: i32,
+                             user_id: i32)
+                             -> Result<bool> {
+    Ok(scheduled_for_user(conn, item_type, user_id)?.contains(&item_id))
+}
+
+pub fn get_all(conn: &Connection) -> Result<Vec<PublishSchedule>> {
+    use schema::publish_schedules;
+
+    Ok(publish_schedules::table.order((publish_schedules::item_type.asc(),
+                                       publish_schedules::item_id.asc()))
+           .get_results(&**conn)?)
+}
+
+/// Sets the publishing schedule of an item, replacing the earlier one.
+pub fn set_schedule(conn: &Connection,
+                    item_type: &str,
+                    item_id: i32,
+                    schedule: &Schedule)
+                    -> Result<PublishSchedule> {
+    use schema::publish_schedules;
+
+    if !ITEM_TYPES.contains(&item_type) {
+        bail!(ErrorKind::InvalidInput);
+    }
+
+    match (schedule.publish_at, schedule.unpublish_at) {
+        (None, None) => bail!(ErrorKind::InvalidInput),
+        (Some(publish_at), Some(unpublish_at)) if publish_at >= unpublish_at => {
+            bail!(ErrorKind::InvalidInput)
+        }
+        _ => (),
+    }
+
+    let new_schedule = NewPublishSchedule {
+        item_type,
+        item_id,
+        publish_at: schedule.publish_at,
+        unpublish_at: schedule.unpublish_at,
+        group_id: schedule.group_id,
+    };
+
+    Ok(diesel::insert_into(publish_schedules::table).values(&new_schedule)
+           .on_conflict((publish_schedules::item_type, publish_schedules::item_id))
+           .do_update()
+           .set(&new_schedule)
+           .get_result(&**conn)?)
+}
+
+pub fn remove_schedule(conn: &Connection, item_type: &str, item_id: i32) -> Result<bool> {
+    use schema::publish_schedules;
+
+    let count = diesel::delete(publish_schedules::table
+            .filter(publish_schedules::item_type.eq(item_type))
+            .filter(publish_schedules::item_id.eq(item_id)))
+        .execute(&**conn)?;
+
+    Ok(count == 1)
+}
+
+fn set_published(conn: &Connection, item_type: &str, id: i32, published: bool) -> Result<()> {
+    use schema::events;
+
+    match item_type {
+        "word" => manage::publish_word(conn, id, published)?,
+        "question" => manage::publish_question(conn, id, published)?,
+        "exercise" => manage::publish_exercise(conn, id, published)?,
+        "event" => {
+            diesel::update(events::table.filter(events::id.eq(id)))
+                .set(events::published.eq(published))
+                .execute(&**conn)?;
+        }
+        _ => bail!(ErrorKind::DatabaseOdd("Unknown item type in the publishing schedules!")),
+    }
+    Ok(())
+}
+
+/// Applies the schedules that have come due: publishes and unpublishes the items of the
+/// schedules without a group, and removes the schedules that have nothing left to do.
+/// Returns the number of the items published or unpublished.
+pub fn apply_schedules(conn: &Connection) -> Result<usize> {
+    use schema::publish_schedules;
+
+    let now = clock::now();
+
+    conn.transaction(|| {
+        let due: Vec<PublishSchedule> =
+            publish_schedules::table.filter(publish_schedules::group_id.is_null())
+                .filter(publish_schedules::publish_at.le(now)
+                            .or(publish_schedules::unpublish_at.le(now)))
+                .get_results(&**conn)?;
+
+        let mut applied = 0;
+        for schedule in due {
+            if schedule.publish_at.map_or(false, |t| t <= now) {
+                set_published(conn, &schedule.item_type, schedule.item_id, true)?;
+                applied += 1;
+                info!("Published {} {} as scheduled.", schedule.item_type, schedule.item_id);
+            }
+            if schedule.unpublish_at.map_or(false, |t| t <= now) {
+                set_published(conn, &schedule.item_type, schedule.item_id, false)?;
+                applied += 1;
+                info!("Unpublished {} {} as scheduled.", schedule.item_type, schedule.item_id);
+            }
+
+            let publish_at = schedule.publish_at.filter(|&t| t > now);
+            let unpublish_at = schedule.unpublish_at.filter(|&t| t > now);
+            if publish_at.is_none() && unpublish_at.is_none() {
+                diesel::delete(publish_schedules::table
+                        .filter(publish_schedules::id.eq(schedule.id)))
+                    .execute(&**conn)?;
+            } else {
+                diesel::update(publish_schedules::table
+                        .filter(publish_schedules::id.eq(schedule.id)))
+                    .set((publish_schedules::publish_at.eq(publish_at),
+                          publish_schedules::unpublish_at.eq(unpublish_at)))
+                    .execute(&**conn)?;
+            }
+        }
+
+        // The schedules of the groups are over
+        diesel::delete(publish_schedules::table.filter(publish_schedules::group_id.is_not_null())
+                .filter(publish_schedules::unpublish_at.le(now)))
+            .execute(&**conn)?;
+
+        Ok(applied)
+    })
+}
@@ -1,14 +1,54 @@
 use super::*;
 use rand::{Rng, thread_rng, seq::SliceRandom};
 use unicode_normalization::UnicodeNormalization;
-use serde::Serialize;
+use serde::{Serialize, Deserialize};
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
 use error_chain::bail;
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Answered {
     W(WAnsweredData),
     Q(QAnsweredData),
     E(EAnsweredData),
+    C(CAnsweredData),
+    A(AAnsweredData),
+    D(DAnsweredData),
+}
+
+impl Answered {
+    pub fn asked_id(&self) -> i32 {
+        match *self {
+            Answered::W(ref a) => a.id,
+            Answered::Q(ref a) => a.id,
+            Answered::E(ref a) => a.id,
+            Answered::C(ref a) => a.id,
+            Answered::A(ref a) => a.id,
+            Answered::D(ref a) => a.id,
+        }
+    }
+
+    pub fn answered_date(&self) -> DateTime<Utc> {
+        match *self {
+            Answered::W(ref a) => a.checked_date,
+            Answered::Q(ref a) => a.answered_date,
+            Answered::E(ref a) => a.answered_date,
+            Answered::C(ref a) => a.answered_date,
+            Answered::A(ref a) => a.answered_date,
+            Answered::D(ref a) => a.answered_date,
+        }
+    }
+
+    pub fn set_answered_date(&mut self, date: DateTime<Utc>) {
+        match *self {
+            Answered::W(ref mut a) => a.checked_date = date,
+            Answered::Q(ref mut a) => a.answered_date = date,
+            Answered::E(ref mut a) => a.answered_date = date,
+            Answered::C(ref mut a) => a.answered_date = date,
+            Answered::A(ref mut a) => a.answered_date = date,
+            Answered::D(ref mut a) => a.answered_date = date,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -16,22 +56,81 @@ pub enum QuizType {
     Question(i32),
     Exercise(i32),
     Word(i32),
+    Comparison(i32),
+    Accent(i32),
+    Dictation(i32),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
 pub enum Quiz {
     W(WordJson),
     E(ExerciseJson),
     Q(QuestionJson),
+    C(ComparisonJson),
+    A(AccentJson),
+    D(DictationJson),
     F(FutureJson),
 }
 
+impl Quiz {
+    /// The id of the pending item. Futures aren't asked, so they don't have one.
+    pub fn asked_id(&self) -> Option<i32> {
+        match *self {
+            Quiz::Q(ref q) => Some(q.asked_id),
+            Quiz::E(ref e) => Some(e.asked_id),
+            Quiz::W(ref w) => Some(w.asked_id),
+            Quiz::C(ref c) => Some(c.asked_id),
+            Quiz::A(ref a) => Some(a.asked_id),
+            Quiz::D(ref d) => Some(d.asked_id),
+            Quiz::F(_) => None,
+        }
+    }
+}
+
+/// The outcome of an answer to a quiz that is graded on the server. It's returned along with
+/// the next quiz, so the right answer isn't revealed before the answer is logged.
+#[derive(Serialize, Debug, Clone)]
+pub struct AnswerResult {
+    pub asked_id: i32,
+    pub correct: bool,
+    /// The zero-based index of the accent nucleus, `None` for heiban.
+    pub right_nucleus: Option<i32>,
+    /// The dictated word or sentence without the accent marks.
+    pub word: Option<String>,
+    /// The played word of a comparison that matches the written form, either 1 or 2.
+    pub right_choice: Option<i32>,
+}
+
 #[derive(Serialize, Debug, Clone)]
 pub struct FutureJson {
     pub quiz_type: &'static str,
     pub due_date: String,
 }
 
+/// The projected review workload of one local day of the learner.
+#[derive(Serialize, Debug, Clone)]
+pub struct ForecastDay {
+    pub day_start: DateTime<Utc>,
+    /// Items that fall due during the day.
+    pub due: i64,
+    /// Items projected to be reviewed during the day, including the ones left over
+    /// from earlier days, within the daily quiz limit.
+    pub projected: i64,
+    /// Items left over to the next day because of the daily quiz limit.
+    pub backlog: i64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Forecast {
+    pub overdue: i64,
+    pub days: Vec<ForecastDay>,
+    pub unseen_words: i64,
+    /// The day the currently published words run out with the daily word limit.
+    /// `None` if new words aren't introduced at all.
+    pub words_run_out: Option<DateTime<Utc>>,
+}
+
 #[derive(Serialize, Debug, Clone)]
 pub struct QuestionJson {
     pub quiz_type: &'static str,
@@ -50,6 +149,8 @@ pub struct ExerciseJson {
     pub word: String,
     pub explanation: String,
     pub must_record: bool,
+    /// For fetching the pitch contour of the narrator.
+    pub audio_id: i32,
 }
 
 #[derive(Serialize, Debug, Clone)]
@@ -59,9 +160,459 @@ pub struct WordJson {
     pub word: String,
     pub explanation: String,
     pub show_accents: bool,
+    /// The parsed accent of the word. `None` for sentences.
+    pub accent: Option<accent::AccentedWord>,
+}
+
+/// Two words that differ only by their pitch accent are played, and the learner chooses
+/// the one that matches the written form. The answer is graded on the server; the right
+/// choice is sent only in the `AnswerResult`.
+#[derive(Serialize, Debug, Clone)]
+pub struct ComparisonJson {
+    pub quiz_type: &'static str,
+    pub asked_id: i32,
+    pub word: String,
+    pub explanation: String,
+}
+
+/// The learner hears a word and marks the mora after which the pitch drops, if it drops at all.
+/// The answer is graded on the server; the right nucleus is sent only in the `AnswerResult`.
+#[derive(Serialize, Debug, Clone)]
+pub struct AccentJson {
+    pub quiz_type: &'static str,
+    pub asked_id: i32,
+    pub word: String,
+    pub explanation: String,
+    pub morae: Vec<String>,
+}
+
+/// The learner hears a word or a sentence and types it in kana. The answer is graded
+/// on the server, so the expected answer isn't sent; it comes only in the `AnswerResult`.
+#[derive(Serialize, Debug, Clone)]
+pub struct DictationJson {
+    pub quiz_type: &'static str,
+    pub asked_id: i32,
+    pub explanation: String,
+}
+
+
+
+/* SCHEDULING */
+
+
+/// A spaced repetition algorithm. `log_answer_due_item` takes care of the per-session
+/// streak bookkeeping (an item has to be answered right `streak_limit` times in a row);
+/// the scheduler decides how long to wait until the item is due again.
+///
+/// The algorithm-specific state (`ease_factor`, `difficulty`, `stability`) is stored in
+/// `due_items`. Every scheduler keeps to its own columns, so moving a user from one
+/// scheduler to another doesn't lose the history of the other.
+pub trait Scheduler {
+    /// The name that is recorded in the review log.
+    fn name(&self) -> &'static str;
+
+    /// Called on every answer, before the streak is checked.
+    /// Schedulers that react to individual misses can update their state here.
+    fn on_answer(&self,
+                 _due_item: &mut DueItem,
+                 _recall: Recall,
+                 _metrics: &UserMetrics,
+                 _now: chrono::DateTime<chrono::offset::Utc>) {
+    }
+
+    /// Called when the streak is full, with the recall of the answer that filled it.
+    /// Must set `due_item.due_delay` (seconds).
+    fn on_review(&self,
+                 due_item: &mut DueItem,
+                 recall: Recall,
+                 metrics: &UserMetrics,
+                 now: chrono::DateTime<chrono::offset::Utc>);
+}
+
+/// The original ganba.re rule: multiply the delay by `delay_multiplier` on every full
+/// streak, start over from zero on a miss. Hard and easy recalls scale the multiplied delay
+/// by `hard_interval_percent` and `easy_interval_percent`.
+pub struct MultiplierScheduler;
+
+impl Scheduler for MultiplierScheduler {
+    fn name(&self) -> &'static str {
+        "multiplier"
+    }
+
+    fn on_review(&self,
+                 due_item: &mut DueItem,
+                 recall: Recall,
+                 metrics: &UserMetrics,
+                 _now: chrono::DateTime<chrono::offset::Utc>) {
+        use std::cmp::{max, min};
+
+        due_item.due_delay = if recall.is_correct() {
+            let delay = due_item.due_delay as i64 * metrics.delay_multiplier as i64 *
+                        recall.interval_percent(metrics) as i64 / 100;
+            max(min(delay, i32::max_value() as i64) as i32,
+                metrics.initial_delay)
+        } else {
+            0
+        };
+    }
+}
+
+const DAY_SECONDS: f64 = 24.0 * 60.0 * 60.0;
+
+fn days_to_delay(days: f64) -> i32 {
+    // The delay is stored as i32 seconds; that caps it at about 68 years.
+    (days * DAY_SECONDS).min(i32::max_value() as f64) as i32
+}
+
+/// SuperMemo 2. A miss lowers the ease factor and restarts the interval sequence of
+/// 1 day, 6 days, and then multiples of the ease factor. After that, a session without misses
+/// lowers the ease factor if the recall was hard and raises it if it was easy.
+pub struct Sm2Scheduler;
+
+impl Sm2Scheduler {
+    /// The SM-2 quality of the response, from 0 to 5. Under 3 is a miss.
+    fn quality(recall: Recall) -> f64 {
+        match recall {
+            Recall::Again => 2.0,
+            Recall::Hard => 3.0,
+            Recall::Good => 4.0,
+            Recall::Easy => 5.0,
+        }
+    }
+
+    fn update_ease(due_item: &mut DueItem, quality: f64) {
+        let ease = due_item.ease_factor + 0.1 - (5.0 - quality) * (0.08 + (5.0 - quality) * 0.02);
+        due_item.ease_factor = ease.max(1.3);
+    }
 }
 
+impl Scheduler for Sm2Scheduler {
+    fn name(&self) -> &'static str {
+        "sm2"
+    }
+
+    fn on_answer(&self,
+                 due_item: &mut DueItem,
+                 recall: Recall,
+                 _metrics: &UserMetrics,
+                 _now: chrono::DateTime<chrono::offset::Utc>) {
+        // Lower the ease only once per session; due_delay 0 marks that the sequence has
+        // already restarted.
+        if !recall.is_correct() && due_item.due_delay > 0 {
+            Sm2Scheduler::update_ease(due_item, Sm2Scheduler::quality(recall));
+            due_item.due_delay = 0;
+        }
+    }
 
+    fn on_review(&self,
+                 due_item: &mut DueItem,
+                 recall: Recall,
+                 metrics: &UserMetrics,
+                 _now: chrono::DateTime<chrono::offset::Utc>) {
+        use std::cmp::max;
+
+        if !recall.is_correct() {
+            due_item.due_delay = 0;
+            return;
+        }
+
+        let one_day = days_to_delay(1.0);
+        due_item.due_delay = if due_item.due_delay == 0 {
+            one_day
+        } else if due_item.due_delay <= one_day {
+            Sm2Scheduler::update_ease(due_item, Sm2Scheduler::quality(recall));
+            days_to_delay(6.0)
+        } else {
+            Sm2Scheduler::update_ease(due_item, Sm2Scheduler::quality(recall));
+            days_to_delay(due_item.due_delay as f64 / DAY_SECONDS * due_item.ease_factor)
+        };
+        due_item.due_delay = max(due_item.due_delay, metrics.initial_delay);
+    }
+}
+
+/// An FSRS-style scheduler (Free Spaced Repetition Scheduler, v4 formulas with the
+/// default weights). Keeps track of memory stability (in days) and item difficulty
+/// (1–10) and schedules the next review for when the recall probability drops
+/// to `REQUEST_RETENTION`.
+pub struct FsrsScheduler;
+
+const FSRS_WEIGHTS: [f64; 17] = [0.4, 0.6, 2.4, 5.8, 4.93, 0.94, 0.86, 0.01, 1.49, 0.14, 0.94,
+                                 2.18, 0.05, 0.34, 1.26, 0.29, 2.61];
+const FSRS_DECAY: f64 = -0.5;
+const FSRS_FACTOR: f64 = 19.0 / 81.0;
+const REQUEST_RETENTION: f64 = 0.9;
+
+impl FsrsScheduler {
+    /// The FSRS grade: 1 = again, 2 = hard, 3 = good, 4 = easy.
+    fn grade(recall: Recall) -> f64 {
+        match recall {
+            Recall::Again => 1.0,
+            Recall::Hard => 2.0,
+            Recall::Good => 3.0,
+            Recall::Easy => 4.0,
+        }
+    }
+
+    fn initial_difficulty(grade: f64) -> f64 {
+        let w = &FSRS_WEIGHTS;
+        (w[4] - (grade - 3.0) * w[5]).max(1.0).min(10.0)
+    }
+
+    fn next_difficulty(difficulty: f64, grade: f64) -> f64 {
+        let w = &FSRS_WEIGHTS;
+        let difficulty = difficulty - w[6] * (grade - 3.0);
+        let difficulty = w[7] * FsrsScheduler::initial_difficulty(4.0) + (1.0 - w[7]) * difficulty;
+        difficulty.max(1.0).min(10.0)
+    }
+
+    fn retrievability(elapsed_days: f64, stability: f64) -> f64 {
+        (1.0 + FSRS_FACTOR * elapsed_days / stability).powf(FSRS_DECAY)
+    }
+
+    fn elapsed_days(due_item: &DueItem, now: chrono::DateTime<chrono::offset::Utc>) -> f64 {
+        let last_review = due_item.due_date - chrono::Duration::seconds(due_item.due_delay as i64);
+        (now.signed_duration_since(last_review).num_seconds() as f64 / DAY_SECONDS).max(0.0)
+    }
+
+    fn first_answer(due_item: &mut DueItem, grade: f64) {
+        due_item.stability = FSRS_WEIGHTS[grade as usize - 1];
+        due_item.difficulty = FsrsScheduler::initial_difficulty(grade);
+    }
+}
+
+impl Scheduler for FsrsScheduler {
+    fn name(&self) -> &'static str {
+        "fsrs"
+    }
+
+    fn on_answer(&self,
+                 due_item: &mut DueItem,
+                 recall: Recall,
+                 _metrics: &UserMetrics,
+                 now: chrono::DateTime<chrono::offset::Utc>) {
+        if recall.is_correct() {
+            return;
+        }
+        let w = &FSRS_WEIGHTS;
+        let grade = FsrsScheduler::grade(recall);
+
+        if due_item.stability <= 0.0 {
+            FsrsScheduler::first_answer(due_item, grade);
+        } else if due_item.due_delay > 0 {
+            let d = due_item.difficulty;
+            let s = due_item.stability;
+            let r = FsrsScheduler::retrievability(FsrsScheduler::elapsed_days(due_item, now), s);
+            due_item.stability = (w[11] * d.powf(-w[12]) * ((s + 1.0).powf(w[13]) - 1.0) *
+                                  (w[14] * (1.0 - r)).exp())
+                .min(s);
+            due_item.difficulty = FsrsScheduler::next_difficulty(d, grade);
+        }
+        // due_delay 0 marks a lapse in this session, so that the stability is lowered only once
+        due_item.due_delay = 0;
+    }
+
+    fn on_review(&self,
+                 due_item: &mut DueItem,
+                 recall: Recall,
+                 metrics: &UserMetrics,
+                 now: chrono::DateTime<chrono::offset::Utc>) {
+        use std::cmp::max;
+        let w = &FSRS_WEIGHTS;
+
+        if !recall.is_correct() {
+            due_item.due_delay = 0;
+            return;
+        }
+        let grade = FsrsScheduler::grade(recall);
+
+        if due_item.stability <= 0.0 {
+            FsrsScheduler::first_answer(due_item, grade);
+        } else if due_item.due_delay > 0 {
+            let d = due_item.difficulty;
+            let s = due_item.stability;
+            let r = FsrsScheduler::retrievability(FsrsScheduler::elapsed_days(due_item, now), s);
+            let bonus = match recall {
+                Recall::Hard => w[15],
+                Recall::Easy => w[16],
+                _ => 1.0,
+            };
+            due_item.stability = s *
+                                 (1.0 +
+                                  w[8].exp() * (11.0 - d) * s.powf(-w[9]) *
+                                  ((w[10] * (1.0 - r)).exp() - 1.0) * bonus);
+            due_item.difficulty = FsrsScheduler::next_difficulty(d, grade);
+        }
+
+        let interval_days = due_item.stability / FSRS_FACTOR *
+                            (REQUEST_RETENTION.powf(1.0 / FSRS_DECAY) - 1.0);
+        due_item.due_delay = max(days_to_delay(interval_days), metrics.initial_delay);
+    }
+}
+
+/// Returns the scheduler of the user. The scheduler is chosen by group membership
+/// (`sm2_scheduler`, `fsrs_scheduler`), so that scheduling experiments can be run
+/// on groups of users. Users that belong to neither use `MultiplierScheduler`.
+pub fn get_scheduler(conn: &Connection, user_id: i32) -> Result<Box<dyn Scheduler>> {
+    if user::check_user_group(conn, user_id, "fsrs_scheduler")? {
+        Ok(Box::new(FsrsScheduler))
+    } else if user::check_user_group(conn, user_id, "sm2_scheduler")? {
+        Ok(Box::new(Sm2Scheduler))
+    } else {
+        Ok(Box::new(MultiplierScheduler))
+    }
+}
+
+/// The graded quality of a recall. Answers are graded right after they are checked:
+/// a correct answer is `Hard` if it was slow compared to the learner's own median answer time
+/// (`slow_answer_percent` of the median) or if the audio was replayed more than
+/// `max_good_audio_times` times, and `Easy` if it was fast (`fast_answer_percent` of the
+/// median) and the audio was played at most once. The recall is passed on to the scheduler,
+/// which maps it to the grades of its own algorithm.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Recall {
+    Again,
+    Hard,
+    Good,
+    Easy,
+}
+
+impl Recall {
+    /// The recall of an answer when there's nothing else to go by than the correctness.
+    pub fn from_correct(correct: bool) -> Recall {
+        if correct { Recall::Good } else { Recall::Again }
+    }
+
+    /// The name that is recorded in the review log.
+    pub fn name(self) -> &'static str {
+        match self {
+            Recall::Again => "again",
+            Recall::Hard => "hard",
+            Recall::Good => "good",
+            Recall::Easy => "easy",
+        }
+    }
+
+    pub fn is_correct(self) -> bool {
+        self != Recall::Again
+    }
+
+    /// How much `MultiplierScheduler` scales the multiplied interval, in percents.
+    pub fn interval_percent(self, metrics: &UserMetrics) -> i32 {
+        match self {
+            Recall::Hard => metrics.hard_interval_percent,
+            Recall::Easy => metrics.easy_interval_percent,
+            Recall::Again | Recall::Good => 100,
+        }
+    }
+}
+
+/// Grades an answer. `median_ms` is the learner's median answer time of correct answers to
+/// this type of item; without it, the answer time isn't taken into account.
+/// `audio_times` is the number of times the audio was played, if the answer records it.
+pub fn grade_recall(correct: bool,
+                    active_answer_time_ms: i32,
+                    median_ms: Option<i32>,
+                    audio_times: Option<i32>,
+                    metrics: &UserMetrics)
+                    -> Recall {
+    if !correct {
+        return Recall::Again;
+    }
+
+    let time_percent = median_ms.filter(|&m| m > 0)
+        .map(|m| active_answer_time_ms as i64 * 100 / m as i64);
+    let replayed = audio_times.map_or(false, |t| t > metrics.max_good_audio_times);
+
+    if replayed || time_percent.map_or(false, |p| p > metrics.slow_answer_percent as i64) {
+        Recall::Hard
+    } else if audio_times.map_or(true, |t| t <= 1) &&
+              time_percent.map_or(false, |p| p < metrics.fast_answer_percent as i64) {
+        Recall::Easy
+    } else {
+        Recall::Good
+    }
+}
+
+/// The median answer time of the learner's latest correct answers to items of `item_type`.
+/// Returns `None` if there aren't enough answers to tell.
+fn median_answer_time(conn: &Connection, user_id: i32, item_type: &str) -> Result<Option<i32>> {
+    use diesel::expression::dsl::*;
+    use diesel::sql_types::{BigInt, Nullable, Double};
+
+    let (count, median): (i64, Option<f64>) = sql::<(BigInt, Nullable<Double>)>(&format!(r###"
+SELECT COUNT(*), percentile_cont(0.5) WITHIN GROUP (ORDER BY t.active_answer_time_ms)
+FROM (
+    SELECT active_answer_time_ms FROM review_log
+    WHERE user_id={} AND item_type='{}' AND correct = true
+    ORDER BY answered_date DESC
+    LIMIT 100
+) AS t;
+"###, user_id, item_type)) // Injection isn't possible: user_id is numerical and item_type is one of the static item type names.
+        .get_result(&**conn)?;
+
+    if count < 10 {
+        return Ok(None);
+    }
+
+    Ok(median.map(|m| m as i32))
+}
+
+
+
+
+
+
+
+
+
+
+
+
+/// How the new words are ordered. Like the scheduler, the order is chosen by group membership
+/// (`frequency_ordering`, `jlpt_ordering`), so that the orderings can be compared on groups
+/// of users. The words that aren't on any list come after the ones that are.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NewWordOrder {
+    /// By `words.priority`, randomly among the words of the same priority.
+    Priority,
+    /// By the best rank of the word in the frequency lists.
+    Frequency,
+    /// By the JLPT level, from N5 to N1.
+    JlptLevel,
+}
+
+impl NewWordOrder {
+    /// The `ORDER BY` clause for the raw SQL queries that choose words from `words`.
+    fn order_by(self) -> &'static str {
+        match self {
+            NewWordOrder::Priority => "words.priority DESC, RANDOM()",
+            NewWordOrder::Frequency => r###"(
+        SELECT MIN(word_list_entries.rank)
+            FROM word_list_entries
+            JOIN word_lists
+            ON word_lists.id=word_list_entries.list_id
+            WHERE word_lists.list_type='frequency' AND word_list_entries.reading=words.reading
+    ) ASC NULLS LAST, words.priority DESC, RANDOM()"###,
+            NewWordOrder::JlptLevel => r###"(
+        SELECT MAX(word_list_entries.rank)
+            FROM word_list_entries
+            JOIN word_lists
+            ON word_lists.id=word_list_entries.list_id
+            WHERE word_lists.list_type='jlpt' AND word_list_entries.reading=words.reading
+    ) DESC NULLS LAST, words.priority DESC, RANDOM()"###,
+        }
+    }
+}
+
+pub fn get_new_word_order(conn: &Connection, user_id: i32) -> Result<NewWordOrder> {
+    if user::check_user_group(conn, user_id, "frequency_ordering")? {
+        Ok(NewWordOrder::Frequency)
+    } else if user::check_user_group(conn, user_id, "jlpt_ordering")? {
+        Ok(NewWordOrder::JlptLevel)
+    } else {
+        Ok(NewWordOrder::Priority)
+    }
+}
 
 
 
@@ -78,7 +629,8 @@ pub struct WordJson {
 fn new_pending_item(conn: &Connection,
                     user_id: i32,
                     quiz_n_audio: QuizType,
-                    test_item: bool)
+                    test_item: bool,
+                    practice: bool)
                     -> Result<PendingItem> {
     use schema::pending_items;
     use self::QuizType::*;
@@ -87,6 +639,9 @@ fn new_pending_item(conn: &Connection,
         Question(id) => ("question", id),
         Exercise(id) => ("exercise", id),
         Word(id) => ("word", id),
+        Comparison(id) => ("comparison", id),
+        Accent(id) => ("accent", id),
+        Dictation(id) => ("dictation", id),
     };
 
     Ok(diesel::insert_into(pending_items::table).values(&NewPendingItem {
@@ -94,6 +649,7 @@ fn new_pending_item(conn: &Connection,
                            audio_file_id: audio_file_id,
                            item_type: item_type,
                            test_item: test_item,
+                           practice: practice,
                        })
                .get_result(&**conn)?)
 }
@@ -112,6 +668,27 @@ fn register_future_e_answer(conn: &Connection, data: &EAskedData) -> Result<()>
     Ok(())
 }
 
+fn register_future_c_answer(conn: &Connection, data: &CAskedData) -> Result<()> {
+    use schema::c_asked_data;
+
+    diesel::insert_into(c_asked_data::table).values(data).execute(&**conn)?;
+    Ok(())
+}
+
+fn register_future_a_answer(conn: &Connection, data: &AAskedData) -> Result<()> {
+    use schema::a_asked_data;
+
+    diesel::insert_into(a_asked_data::table).values(data).execute(&**conn)?;
+    Ok(())
+}
+
+fn register_future_d_answer(conn: &Connection, data: &DAskedData) -> Result<()> {
+    use schema::d_asked_data;
+
+    diesel::insert_into(d_asked_data::table).values(data).execute(&**conn)?;
+    Ok(())
+}
+
 fn register_future_w_answer(conn: &Connection, data: &WAskedData) -> Result<()> {
     use schema::w_asked_data;
 
@@ -119,39 +696,83 @@ fn register_future_w_answer(conn: &Connection, data: &WAskedData) -> Result<()>
     Ok(())
 }
 
-fn log_answer_due_item(conn: &Connection,
-                       mut due_item: DueItem,
-                       skill_id: i32,
-                       correct: bool,
-                       metrics: &mut UserMetrics)
-                       -> Result<DueItem> {
-    use std::cmp::max;
+/// Updates the streaks and the due date of `due_item` according to an answer.
+/// The first miss after a review, or the first one of the day, is counted as a lapse and
+/// breaks the overall streak; the re-asks of the same session don't count as further lapses.
+/// An item that lapses `leech_threshold` times is a leech: it's suspended until a manager
+/// unsuspends it. (A threshold of 0 disables this.)
+/// When the streak gets full, the scheduler chooses the delay according to the recall of the
+/// answer that filled it.
+/// Returns true if the streak got full, and the item was thus reviewed by the scheduler.
+pub fn schedule_answer(scheduler: &dyn Scheduler,
+                       due_item: &mut DueItem,
+                       recall: Recall,
+                       metrics: &UserMetrics,
+                       now: DateTime<Utc>)
+                       -> bool {
+
+    let correct = recall.is_correct();
+
+    scheduler.on_answer(due_item, recall, metrics, now);
+
+    let lapsed_today = due_item.lapsed_date
+        .map_or(false, |lapsed| lapsed >= user::local_day_start(metrics, now));
+    if !correct && !lapsed_today {
+        due_item.lapsed_date = Some(now);
+        due_item.correct_streak_overall = 0;
+        due_item.lapses += 1;
+        if metrics.leech_threshold > 0 && due_item.lapses >= metrics.leech_threshold {
+            due_item.suspended = true;
+        }
+    }
 
     due_item.correct_streak_this_time = if correct {
         due_item.correct_streak_this_time + 1
     } else {
         0
     };
-    due_item.cooldown_delay = chrono::offset::Utc::now() +
-                              chrono::Duration::seconds(metrics.cooldown_delay as i64);
+    due_item.cooldown_delay = now + chrono::Duration::seconds(metrics.cooldown_delay as i64);
 
     if due_item.correct_streak_this_time >= metrics.streak_limit {
-        metrics.quizes_since_break += metrics.streak_limit;
-        metrics.quizes_today += metrics.streak_limit;
         due_item.correct_streak_this_time = 0;
+        due_item.lapsed_date = None;
         due_item.correct_streak_overall = if correct {
             due_item.correct_streak_overall + 1
         } else {
             0
         };
-        due_item.due_delay = if correct {
-            max(due_item.due_delay * metrics.delay_multiplier,
-                metrics.initial_delay)
-        } else {
-            0
-        };
-        due_item.due_date = chrono::offset::Utc::now() +
-                            chrono::Duration::seconds(due_item.due_delay as i64);
+        scheduler.on_review(due_item, recall, metrics, now);
+        due_item.due_date = now + chrono::Duration::seconds(due_item.due_delay as i64);
+        true
+    } else {
+        false
+    }
+}
+
+/// `audio_times` is the number of times the audio was played, for the answer types that record it.
+fn log_answer_due_item(conn: &Connection,
+                       mut due_item: DueItem,
+                       skill_id: i32,
+                       audio_times: Option<i32>,
+                       metrics: &mut UserMetrics,
+                       mut review: NewReviewLog)
+                       -> Result<DueItem> {
+    use schema::review_log;
+
+    let scheduler = get_scheduler(conn, due_item.user_id)?;
+    let now = clock::now();
+    let before = due_item.clone();
+
+    let median_ms = median_answer_time(conn, due_item.user_id, review.item_type)?;
+    let recall = grade_recall(review.correct,
+                              review.active_answer_time_ms,
+                              median_ms,
+                              audio_times,
+                              metrics);
+
+    if schedule_answer(&*scheduler, &mut due_item, recall, metrics, now) {
+        metrics.quizes_since_break += metrics.streak_limit;
+        metrics.quizes_today += metrics.streak_limit;
         if due_item.correct_streak_overall >= metrics.streak_skill_bump_criteria {
 
             debug!("Skill bump because of correct_streak_overall > the criteria! Skill: {} Of \
@@ -164,15 +785,29 @@ fn log_answer_due_item(conn: &Connection,
         };
     }
 
-    Ok(due_item.save_changes(&**conn)?)
+    if due_item.suspended && !before.suspended {
+        info!("Due item {} of user {} is a leech with {} lapses. Suspending it.",
+              due_item.id,
+              due_item.user_id,
+              due_item.lapses);
+    }
+
+    let due_item: DueItem = due_item.save_changes(&**conn)?;
+
+    set_review_schedule(&mut review, &before, &due_item, scheduler.name());
+    review.recall = Some(recall.name());
+    diesel::insert_into(review_log::table).values(&review).execute(&**conn)?;
+
+    Ok(due_item)
 }
 
 fn log_answer_new_due_item(conn: &Connection,
                            user_id: i32,
                            item_type: &str,
                            skill_id: i32,
-                           correct: bool,
-                           metrics: &mut UserMetrics)
+                           audio_times: Option<i32>,
+                           metrics: &mut UserMetrics,
+                           review: NewReviewLog)
                            -> Result<DueItem> {
     use schema::due_items;
 
@@ -189,24 +824,177 @@ fn log_answer_new_due_item(conn: &Connection,
         user_id: user_id,
         correct_streak_this_time: 0,
         correct_streak_overall: 0,
-        due_date: chrono::offset::Utc::now(),
+        due_date: clock::now(),
         due_delay: 0,
-        cooldown_delay: chrono::offset::Utc::now(),
+        cooldown_delay: clock::now(),
         item_type: item_type,
     };
 
     let due_item: DueItem = diesel::insert_into(due_items::table).values(&new_due_item)
         .get_result(&**conn)?;
 
-    Ok(log_answer_due_item(conn, due_item, skill_id, correct, metrics)?)
+    Ok(log_answer_due_item(conn, due_item, skill_id, audio_times, metrics, review)?)
+}
+
+/// The base of the review log entries: an unscheduled answer without an answer level
+/// or answer times. The scheduling fields are set by `set_review_schedule`.
+fn review_log<'a>(user_id: i32,
+                  item_type: &'a str,
+                  item_id: i32,
+                  asked_id: i32,
+                  answered_date: DateTime<Utc>,
+                  correct: bool)
+                  -> NewReviewLog<'a> {
+    NewReviewLog {
+        user_id: user_id,
+        asked_id: asked_id,
+        item_type: item_type,
+        item_id: item_id,
+        due_item_id: None,
+        answered_date: answered_date,
+        correct: correct,
+        answer_level: None,
+        active_answer_time_ms: 0,
+        full_answer_time_ms: None,
+        full_spent_time_ms: 0,
+        prev_due_delay: None,
+        new_due_delay: None,
+        prev_correct_streak_this_time: None,
+        new_correct_streak_this_time: None,
+        prev_correct_streak_overall: None,
+        new_correct_streak_overall: None,
+        new_due_date: None,
+        scheduler: None,
+        reconstructed: false,
+        recall: None,
+    }
+}
+
+/// A review log entry of a question answer.
+pub fn q_review<'a>(user_id: i32, asked: &QAskedData, answered: &QAnsweredData) -> NewReviewLog<'a> {
+    NewReviewLog {
+        active_answer_time_ms: answered.active_answer_time_ms,
+        full_answer_time_ms: Some(answered.full_answer_time_ms),
+        full_spent_time_ms: answered.full_spent_time_ms,
+        ..review_log(user_id,
+                     "question",
+                     asked.question_id,
+                     answered.id,
+                     answered.answered_date,
+                     asked.correct_qa_id == answered.answered_qa_id.unwrap_or(-1))
+    }
+}
+
+/// A review log entry of an exercise answer.
+pub fn e_review<'a>(user_id: i32, asked: &EAskedData, answered: &EAnsweredData) -> NewReviewLog<'a> {
+    NewReviewLog {
+        answer_level: Some(answered.answer_level),
+        active_answer_time_ms: answered.active_answer_time_ms,
+        full_answer_time_ms: Some(answered.full_answer_time_ms),
+        full_spent_time_ms: answered.full_spent_time_ms,
+        ..review_log(user_id,
+                     "exercise",
+                     asked.exercise_id,
+                     answered.id,
+                     answered.answered_date,
+                     answered.answer_level > 0)
+    }
+}
+
+/// A review log entry of a comparison answer.
+pub fn c_review<'a>(user_id: i32, asked: &CAskedData, answered: &CAnsweredData) -> NewReviewLog<'a> {
+    NewReviewLog {
+        active_answer_time_ms: answered.active_answer_time_ms,
+        full_answer_time_ms: Some(answered.full_answer_time_ms),
+        full_spent_time_ms: answered.full_spent_time_ms,
+        ..review_log(user_id,
+                     "comparison",
+                     asked.pair_id,
+                     answered.id,
+                     answered.answered_date,
+                     answered.answered_choice == Some(asked.right_choice))
+    }
+}
+
+/// A review log entry of an accent answer.
+pub fn a_review<'a>(user_id: i32, asked: &AAskedData, answered: &AAnsweredData) -> NewReviewLog<'a> {
+    NewReviewLog {
+        active_answer_time_ms: answered.active_answer_time_ms,
+        full_answer_time_ms: Some(answered.full_answer_time_ms),
+        full_spent_time_ms: answered.full_spent_time_ms,
+        ..review_log(user_id,
+                     "accent",
+                     asked.word_id,
+                     answered.id,
+                     answered.answered_date,
+                     answered.answered_nucleus == asked.right_nucleus)
+    }
+}
+
+/// A review log entry of a dictation answer.
+pub fn d_review<'a>(user_id: i32, asked: &DAskedData, answered: &DAnsweredData) -> NewReviewLog<'a> {
+    NewReviewLog {
+        answer_level: Some(answered.answer_level),
+        active_answer_time_ms: answered.active_answer_time_ms,
+        full_answer_time_ms: Some(answered.full_answer_time_ms),
+        full_spent_time_ms: answered.full_spent_time_ms,
+        ..review_log(user_id,
+                     "dictation",
+                     asked.word_id,
+                     answered.id,
+                     answered.answered_date,
+                     answered.answer_level >= dictation::PASSING_LEVEL)
+    }
+}
+
+/// A review log entry of a word. Words aren't scheduled, so there's no scheduling data.
+pub fn w_review<'a>(user_id: i32, asked: &WAskedData, answered: &WAnsweredData) -> NewReviewLog<'a> {
+    NewReviewLog {
+        active_answer_time_ms: answered.active_answer_time_ms,
+        full_spent_time_ms: answered.full_spent_time_ms,
+        ..review_log(user_id, "word", asked.word_id, answered.id, answered.checked_date, true)
+    }
+}
+
+/// Sets the scheduling fields of a review log entry from the states of the due item
+/// before and after the answer.
+pub fn set_review_schedule(review: &mut NewReviewLog,
+                           before: &DueItem,
+                           after: &DueItem,
+                           scheduler: &'static str) {
+    review.due_item_id = Some(after.id);
+    review.prev_due_delay = Some(before.due_delay);
+    review.new_due_delay = Some(after.due_delay);
+    review.prev_correct_streak_this_time = Some(before.correct_streak_this_time);
+    review.new_correct_streak_this_time = Some(after.correct_streak_this_time);
+    review.prev_correct_streak_overall = Some(before.correct_streak_overall);
+    review.new_correct_streak_overall = Some(after.correct_streak_overall);
+    review.new_due_date = Some(after.due_date);
+    review.scheduler = Some(scheduler);
+}
+
+fn log_answer(conn: &Connection,
+              user_id: i32,
+              answer_enum: &Answered,
+              metrics: &mut UserMetrics)
+              -> Result<()> {
+    match *answer_enum {
+        Answered::W(ref answer_word) => log_answer_word(conn, user_id, answer_word, metrics),
+        Answered::E(ref exercise) => log_answer_exercise(conn, user_id, exercise, metrics),
+        Answered::Q(ref answer) => log_answer_question(conn, user_id, answer, metrics),
+        Answered::C(ref answer) => log_answer_comparison(conn, user_id, answer, metrics),
+        Answered::A(ref answer) => log_answer_accent(conn, user_id, answer, metrics),
+        Answered::D(ref answer) => log_answer_dictation(conn, user_id, answer, metrics),
+    }
 }
 
 fn log_answer_word(conn: &Connection, user_id: i32, answered: &WAnsweredData, metrics: &mut UserMetrics) -> Result<()> {
-    use schema::{user_stats, pending_items, w_asked_data, w_answered_data, words};
+    use schema::{user_stats, pending_items, w_asked_data, w_answered_data, words, review_log};
 
     let (mut pending_item, asked): (PendingItem, WAskedData) =
         pending_items::table.inner_join(w_asked_data::table)
             .filter(pending_items::id.eq(answered.id))
+            .for_update()
             .get_result(&**conn)?;
 
     if !pending_item.pending {
@@ -214,6 +1002,10 @@ fn log_answer_word(conn: &Connection, user_id: i32, answered: &WAnsweredData, me
         return Ok(());
     }
 
+    if pending_item.practice {
+        bail!(ErrorKind::InvalidInput);
+    }
+
     // This Q&A is now considered done
     metrics.new_words_today += 1;
     metrics.new_words_since_break += 1;
@@ -221,6 +1013,8 @@ fn log_answer_word(conn: &Connection, user_id: i32, answered: &WAnsweredData, me
     let _: PendingItem = pending_item.save_changes(&**conn)?;
 
     diesel::insert_into(w_answered_data::table).values(answered).execute(&**conn)?;
+    diesel::insert_into(review_log::table).values(&w_review(user_id, &asked, answered))
+        .execute(&**conn)?;
 
     let word: Word = words::table.filter(words::id.eq(asked.word_id)).get_result(&**conn)?;
 
@@ -253,6 +1047,7 @@ fn log_answer_question(conn: &Connection,
     let (mut pending_item, asked): (PendingItem, QAskedData) =
         pending_items::table.inner_join(q_asked_data::table)
             .filter(pending_items::id.eq(answered.id))
+            .for_update()
             .get_result(&**conn)?;
 
     if !pending_item.pending {
@@ -260,11 +1055,16 @@ fn log_answer_question(conn: &Connection,
         return Ok(());
     }
 
+    if pending_item.practice {
+        bail!(ErrorKind::InvalidInput);
+    }
+
     // This Q&A is now considered done
     pending_item.pending = false;
     let _: PendingItem = pending_item.save_changes(&**conn)?;
 
     let correct = asked.correct_qa_id == answered.answered_qa_id.unwrap_or(-1);
+    let review = q_review(user_id, &asked, answered);
 
     diesel::insert_into(q_answered_data::table).values(answered).execute(&**conn)?;
 
@@ -280,23 +1080,6 @@ fn log_answer_question(conn: &Connection,
     let _: UserStats = stats.save_changes(&**conn)?;
 
 
-    // If the answer was wrong, register a new pending question
-    // with the same specs right away for a follow-up review
-    if !correct {
-
-        let pending_item = new_pending_item(conn,
-                                            user_id,
-                                            QuizType::Question(pending_item.audio_file_id),
-                                            false)?;
-        let asked_data = QAskedData {
-            id: pending_item.id,
-            question_id: asked.question_id,
-            correct_qa_id: asked.correct_qa_id,
-        };
-        register_future_q_answer(conn, &asked_data)?;
-
-    }
-
 
     // Update the data for the question (Diesel doesn't support UPSERT so we have to branch)
 
@@ -311,9 +1094,9 @@ fn log_answer_question(conn: &Connection,
             .optional()?;
 
     // Update the data for this question (due date, statistics etc.)
-    Ok(if let Some((_, due_item)) = questiondata {
+    let due_item = if let Some((_, due_item)) = questiondata {
 
-           log_answer_due_item(conn, due_item, question.skill_id, correct, metrics)?;
+           log_answer_due_item(conn, due_item, question.skill_id, None, metrics, review)?
 
        } else {
            // New!
@@ -322,8 +1105,9 @@ fn log_answer_question(conn: &Connection,
                                                user_id,
                                                "question",
                                                question.skill_id,
-                                               correct,
-                                               metrics)?;
+                                               None,
+                                               metrics,
+                                               review)?;
 
            let questiondata = QuestionData {
             question_id: asked.question_id,
@@ -331,7 +1115,28 @@ fn log_answer_question(conn: &Connection,
         };
            let _: QuestionData = diesel::insert_into(question_data::table).values(&questiondata)
             .get_result(&**conn)?;
-       })
+           due_item
+       };
+
+    // If the answer was wrong, register a new pending question
+    // with the same specs right away for a follow-up review, unless the question is a leech
+    if !correct && !due_item.suspended {
+
+        let pending_item = new_pending_item(conn,
+                                            user_id,
+                                            QuizType::Question(pending_item.audio_file_id),
+                                            false,
+                                            false)?;
+        let asked_data = QAskedData {
+            id: pending_item.id,
+            question_id: asked.question_id,
+            correct_qa_id: asked.correct_qa_id,
+        };
+        register_future_q_answer(conn, &asked_data)?;
+
+    }
+
+    Ok(())
 }
 
 fn log_answer_exercise(conn: &Connection,
@@ -342,11 +1147,12 @@ fn log_answer_exercise(conn: &Connection,
     use schema::{user_stats, pending_items, e_asked_data, e_answered_data, due_items, exercise_data,
                  exercises};
 
-    let correct = answered.answer_level > 0;
+    let correct = answered.answer_level > 0 && pronunciation::passes(conn, user_id, answered.id)?;
 
     let (mut pending_item, asked): (PendingItem, EAskedData) =
         pending_items::table.inner_join(e_asked_data::table)
             .filter(pending_items::id.eq(answered.id))
+            .for_update()
             .get_result(&**conn)?;
 
     if !pending_item.pending {
@@ -354,11 +1160,17 @@ fn log_answer_exercise(conn: &Connection,
         return Ok(());
     }
 
+    if pending_item.practice {
+        bail!(ErrorKind::InvalidInput);
+    }
+
     // This Q&A is now considered done
     pending_item.pending = false;
     let _: PendingItem = pending_item.save_changes(&**conn)?;
 
     diesel::insert_into(e_answered_data::table).values(answered).execute(&**conn)?;
+    let mut review = e_review(user_id, &asked, answered);
+    review.correct = correct;
 
     let mut stats: UserStats = user_stats::table.filter(user_stats::id.eq(user_id))
         .get_result(&**conn)?;
@@ -366,19 +1178,57 @@ fn log_answer_exercise(conn: &Connection,
     stats.all_active_time_ms += answered.active_answer_time_ms as i64;
     stats.all_spent_time_ms += answered.full_spent_time_ms as i64;
     stats.quiz_all_times += 1;
-    if answered.answer_level > 0 {
+    if correct {
         stats.quiz_correct_times += 1;
     }
     let _: UserStats = stats.save_changes(&**conn)?;
 
 
+    let exercise: Exercise = exercises::table.filter(exercises::id.eq(asked.exercise_id))
+        .get_result(&**conn)?;
+
+    let exercisedata: Option<(ExerciseData, DueItem)> =
+        exercise_data::table.inner_join(due_items::table)
+            .filter(due_items::user_id.eq(user_id))
+            .filter(exercise_data::exercise_id.eq(asked.exercise_id))
+            .get_result(&**conn)
+            .optional()?;
+
+    // Update the data for this word exercise (due date, statistics etc.)
+    let due_item = if let Some((_, due_item)) = exercisedata {
+
+           log_answer_due_item(conn, due_item, exercise.skill_id, Some(answered.audio_times), metrics, review)?
+
+       } else {
+           // New!
+
+           let due_item = log_answer_new_due_item(conn,
+                                               user_id,
+                                               "exercise",
+                                               exercise.skill_id,
+                                               Some(answered.audio_times),
+                                               metrics,
+                                               review)?;
+
+           let exercisedata = ExerciseData {
+            due: due_item.id,
+            exercise_id: asked.exercise_id,
+        };
+           let _: ExerciseData =
+            diesel::insert_into(exercise_data::table).values(&exercisedata)
+                .get_result(&**conn)
+                .chain_err(|| "Couldn't save the question tally data to database!")?;
+           due_item
+       };
+
     // If the answer was wrong, register a new pending question
-    // with the same specs right away for a follow-up review
-    if answered.answer_level == 0 {
+    // with the same specs right away for a follow-up review, unless the exercise is a leech
+    if !correct && !due_item.suspended {
 
         let pending_item = new_pending_item(conn,
                                             user_id,
                                             QuizType::Exercise(pending_item.audio_file_id),
+                                            false,
                                             false)?;
         let asked_data = EAskedData {
             id: pending_item.id,
@@ -389,42 +1239,304 @@ fn log_answer_exercise(conn: &Connection,
 
     }
 
-    let exercise: Exercise = exercises::table.filter(exercises::id.eq(asked.exercise_id))
+    Ok(())
+}
+
+fn log_answer_comparison(conn: &Connection,
+                         user_id: i32,
+                         answered: &CAnsweredData,
+                         metrics: &mut UserMetrics)
+                         -> Result<()> {
+    use schema::{user_stats, pending_items, c_asked_data, c_answered_data, due_items,
+                 comparison_data, comparison_pairs};
+
+    let (mut pending_item, asked): (PendingItem, CAskedData) =
+        pending_items::table.inner_join(c_asked_data::table)
+            .filter(pending_items::id.eq(answered.id))
+            .for_update()
+            .get_result(&**conn)?;
+
+    if !pending_item.pending {
+        info!("User is trying to answer twice to the same comparison! Ignoring the later answer.");
+        return Ok(());
+    }
+
+    if pending_item.practice {
+        bail!(ErrorKind::InvalidInput);
+    }
+
+    // This Q&A is now considered done
+    pending_item.pending = false;
+    let _: PendingItem = pending_item.save_changes(&**conn)?;
+
+    let correct = answered.answered_choice == Some(asked.right_choice);
+    let review = c_review(user_id, &asked, answered);
+
+    diesel::insert_into(c_answered_data::table).values(answered).execute(&**conn)?;
+
+    let mut stats: UserStats = user_stats::table.filter(user_stats::id.eq(user_id))
         .get_result(&**conn)?;
 
-    let exercisedata: Option<(ExerciseData, DueItem)> =
-        exercise_data::table.inner_join(due_items::table)
+    stats.all_active_time_ms += answered.full_answer_time_ms as i64;
+    stats.all_spent_time_ms += answered.full_spent_time_ms as i64;
+    stats.quiz_all_times += 1;
+    if correct {
+        stats.quiz_correct_times += 1;
+    }
+    let _: UserStats = stats.save_changes(&**conn)?;
+
+    let pair: ComparisonPair =
+        comparison_pairs::table.filter(comparison_pairs::id.eq(asked.pair_id)).get_result(&**conn)?;
+
+    let comparisondata: Option<(ComparisonData, DueItem)> =
+        comparison_data::table.inner_join(due_items::table)
             .filter(due_items::user_id.eq(user_id))
-            .filter(exercise_data::exercise_id.eq(asked.exercise_id))
+            .filter(comparison_data::pair_id.eq(asked.pair_id))
             .get_result(&**conn)
             .optional()?;
 
-    // Update the data for this word exercise (due date, statistics etc.)
-    Ok(if let Some((_, due_item)) = exercisedata {
+    // Update the data for this comparison (due date, statistics etc.)
+    let due_item = if let Some((_, due_item)) = comparisondata {
 
-           log_answer_due_item(conn, due_item, exercise.skill_id, correct, metrics)?;
+           log_answer_due_item(conn, due_item, pair.skill_id, None, metrics, review)?
 
        } else {
            // New!
 
            let due_item = log_answer_new_due_item(conn,
                                                user_id,
-                                               "exercise",
-                                               exercise.skill_id,
-                                               correct,
-                                               metrics)?;
+                                               "comparison",
+                                               pair.skill_id,
+                                               None,
+                                               metrics,
+                                               review)?;
+
+           let comparisondata = ComparisonData {
+            pair_id: asked.pair_id,
+            due: due_item.id,
+        };
+           let _: ComparisonData = diesel::insert_into(comparison_data::table)
+            .values(&comparisondata)
+            .get_result(&**conn)?;
+           due_item
+       };
 
-           let exercisedata = ExerciseData {
+    // If the answer was wrong, ask the same comparison again right away for a follow-up review,
+    // unless it's a leech
+    if !correct && !due_item.suspended {
+
+        let pending_item = new_pending_item(conn,
+                                            user_id,
+                                            QuizType::Comparison(pending_item.audio_file_id),
+                                            false,
+                                            false)?;
+        let asked_data = CAskedData { id: pending_item.id, ..asked };
+        register_future_c_answer(conn, &asked_data)?;
+
+    }
+
+    Ok(())
+}
+
+/// The answer is graded here against the accent of the word as it was when it was asked,
+/// so the client can't claim to be right.
+fn log_answer_accent(conn: &Connection,
+                     user_id: i32,
+                     answered: &AAnsweredData,
+                     metrics: &mut UserMetrics)
+                     -> Result<()> {
+    use schema::{user_stats, pending_items, a_asked_data, a_answered_data, due_items,
+                 accent_data, words};
+
+    let (mut pending_item, asked): (PendingItem, AAskedData) =
+        pending_items::table.inner_join(a_asked_data::table)
+            .filter(pending_items::id.eq(answered.id))
+            .for_update()
+            .get_result(&**conn)?;
+
+    if !pending_item.pending {
+        info!("User is trying to answer twice to the same accent quiz! Ignoring the later answer.");
+        return Ok(());
+    }
+
+    if pending_item.practice {
+        bail!(ErrorKind::InvalidInput);
+    }
+
+    // This Q&A is now considered done
+    pending_item.pending = false;
+    let _: PendingItem = pending_item.save_changes(&**conn)?;
+
+    let correct = answered.answered_nucleus == asked.right_nucleus;
+    let review = a_review(user_id, &asked, answered);
+
+    diesel::insert_into(a_answered_data::table).values(answered).execute(&**conn)?;
+
+    let mut stats: UserStats = user_stats::table.filter(user_stats::id.eq(user_id))
+        .get_result(&**conn)?;
+
+    stats.all_active_time_ms += answered.active_answer_time_ms as i64;
+    stats.all_spent_time_ms += answered.full_spent_time_ms as i64;
+    stats.quiz_all_times += 1;
+    if correct {
+        stats.quiz_correct_times += 1;
+    }
+    let _: UserStats = stats.save_changes(&**conn)?;
+
+    let word: Word = words::table.filter(words::id.eq(asked.word_id)).get_result(&**conn)?;
+
+    let accentdata: Option<(AccentData, DueItem)> =
+        accent_data::table.inner_join(due_items::table)
+            .filter(due_items::user_id.eq(user_id))
+            .filter(accent_data::word_id.eq(asked.word_id))
+            .get_result(&**conn)
+            .optional()?;
+
+    // Update the data for the accent of this word (due date, statistics etc.)
+    let due_item = if let Some((_, due_item)) = accentdata {
+
+           log_answer_due_item(conn, due_item, word.skill_nugget, None, metrics, review)?
+
+       } else {
+           // New!
+
+           let due_item = log_answer_new_due_item(conn,
+                                               user_id,
+                                               "accent",
+                                               word.skill_nugget,
+                                               None,
+                                               metrics,
+                                               review)?;
+
+           let accentdata = AccentData {
+            word_id: asked.word_id,
             due: due_item.id,
-            exercise_id: asked.exercise_id,
         };
-           let _: ExerciseData =
-            diesel::insert_into(exercise_data::table).values(&exercisedata)
-                .get_result(&**conn)
-                .chain_err(|| "Couldn't save the question tally data to database!")?;
-       })
+           let _: AccentData = diesel::insert_into(accent_data::table)
+            .values(&accentdata)
+            .get_result(&**conn)?;
+           due_item
+       };
+
+    // If the answer was wrong, ask the same word again right away for a follow-up review,
+    // unless it's a leech
+    if !correct && !due_item.suspended {
+
+        let pending_item = new_pending_item(conn,
+                                            user_id,
+                                            QuizType::Accent(pending_item.audio_file_id),
+                                            false,
+                                            false)?;
+        let asked_data = AAskedData { id: pending_item.id, ..asked };
+        register_future_a_answer(conn, &asked_data)?;
+
+    }
+
+    Ok(())
 }
 
+/// The answer is graded here against the kana that was expected when the word was asked,
+/// so the client can't claim to be right. Any answer level sent by the client is ignored.
+fn log_answer_dictation(conn: &Connection,
+                        user_id: i32,
+                        answered: &DAnsweredData,
+                        metrics: &mut UserMetrics)
+                        -> Result<()> {
+    use schema::{user_stats, pending_items, d_asked_data, d_answered_data, due_items,
+                 dictation_data, words};
+
+    let (mut pending_item, asked): (PendingItem, DAskedData) =
+        pending_items::table.inner_join(d_asked_data::table)
+            .filter(pending_items::id.eq(answered.id))
+            .for_update()
+            .get_result(&**conn)?;
+
+    if !pending_item.pending {
+        info!("User is trying to answer twice to the same dictation! Ignoring the later answer.");
+        return Ok(());
+    }
+
+    if pending_item.practice {
+        bail!(ErrorKind::InvalidInput);
+    }
+
+    // This Q&A is now considered done
+    pending_item.pending = false;
+    let _: PendingItem = pending_item.save_changes(&**conn)?;
+
+    let answered = DAnsweredData {
+        answer_level: dictation::grade(&asked.expected_kana, &answered.answered_text),
+        ..answered.clone()
+    };
+    let correct = answered.answer_level >= dictation::PASSING_LEVEL;
+    let review = d_review(user_id, &asked, &answered);
+
+    diesel::insert_into(d_answered_data::table).values(&answered).execute(&**conn)?;
+
+    let mut stats: UserStats = user_stats::table.filter(user_stats::id.eq(user_id))
+        .get_result(&**conn)?;
+
+    stats.all_active_time_ms += answered.active_answer_time_ms as i64;
+    stats.all_spent_time_ms += answered.full_spent_time_ms as i64;
+    stats.quiz_all_times += 1;
+    if correct {
+        stats.quiz_correct_times += 1;
+    }
+    let _: UserStats = stats.save_changes(&**conn)?;
+
+    let word: Word = words::table.filter(words::id.eq(asked.word_id)).get_result(&**conn)?;
+
+    let dictationdata: Option<(DictationData, DueItem)> =
+        dictation_data::table.inner_join(due_items::table)
+            .filter(due_items::user_id.eq(user_id))
+            .filter(dictation_data::word_id.eq(asked.word_id))
+            .get_result(&**conn)
+            .optional()?;
+
+    // Update the data for the dictation of this word (due date, statistics etc.)
+    let due_item = if let Some((_, due_item)) = dictationdata {
+
+           log_answer_due_item(conn, due_item, word.skill_nugget, Some(answered.audio_times), metrics, review)?
+
+       } else {
+           // New!
+
+           let due_item = log_answer_new_due_item(conn,
+                                               user_id,
+                                               "dictation",
+                                               word.skill_nugget,
+                                               Some(answered.audio_times),
+                                               metrics,
+                                               review)?;
+
+           let dictationdata = DictationData {
+            word_id: asked.word_id,
+            due: due_item.id,
+        };
+           let _: DictationData = diesel::insert_into(dictation_data::table)
+            .values(&dictationdata)
+            .get_result(&**conn)?;
+           due_item
+       };
+
+    // If the answer was wrong, ask the same word again right away for a follow-up review,
+    // unless it's a leech
+    if !correct && !due_item.suspended {
+
+        let pending_item = new_pending_item(conn,
+                                            user_id,
+                                            QuizType::Dictation(pending_item.audio_file_id),
+                                            false,
+                                            false)?;
+        let asked_data = DAskedData { id: pending_item.id, ..asked };
+        register_future_d_answer(conn, &asked_data)?;
+
+    }
+
+    Ok(())
+}
+
+
 
 
 
@@ -488,43 +1600,151 @@ fn load_word(conn: &Connection, id: i32) -> Result<Option<Word>> {
            .optional()?)
 }
 
+/// Finds out which question, exercise, comparison, accent or dictation the due item is about.
+fn due_item_quiztype(conn: &Connection, due: &DueItem) -> Result<QuizType> {
+    use schema::{question_data, exercise_data, comparison_data, accent_data, dictation_data};
+
+    Ok(match due.item_type.as_str() {
+           "question" => {
+            QuizType::Question(question_data::table.filter(question_data::due.eq(due.id))
+                .get_result::<QuestionData>(&**conn)?
+                .question_id)
+        }
+           "exercise" => {
+            QuizType::Exercise(exercise_data::table.filter(exercise_data::due.eq(due.id))
+                .get_result::<ExerciseData>(&**conn)?
+                .exercise_id)
+        }
+           "comparison" => {
+            QuizType::Comparison(comparison_data::table.filter(comparison_data::due.eq(due.id))
+                .get_result::<ComparisonData>(&**conn)?
+                .pair_id)
+        }
+           "accent" => {
+            QuizType::Accent(accent_data::table.filter(accent_data::due.eq(due.id))
+                .get_result::<AccentData>(&**conn)?
+                .word_id)
+        }
+           "dictation" => {
+            QuizType::Dictation(dictation_data::table.filter(dictation_data::due.eq(due.id))
+                .get_result::<DictationData>(&**conn)?
+                .word_id)
+        }
+           _ => {
+               return Err(ErrorKind::DatabaseOdd("Database contains due_item with an odd item_type \
+                                               value!")
+                                  .into())
+           }
+       })
+}
+
 fn choose_next_due_item(conn: &Connection, user_id: i32) -> Result<Option<(DueItem, QuizType)>> {
-    use schema::{due_items, question_data, exercise_data};
+    use schema::due_items;
+
+    let due_item: Option<DueItem> = due_items::table.filter(due_items::user_id.eq(user_id))
+        .filter(due_items::suspended.eq(false))
+        .filter(due_items::parked.eq(false))
+        .order(due_items::due_date.asc())
+        .first(&**conn)
+        .optional()?;
+
+    match due_item {
+        Some(due_item) => {
+            let quiztype = due_item_quiztype(conn, &due_item)?;
+            Ok(Some((due_item, quiztype)))
+        }
+        None => Ok(None),
+    }
+}
+
+/// The due items that are already asked and are waiting for an answer.
+/// Normally there's at most one, but prefetched quizzes reserve many at once.
+fn reserved_due_items(conn: &Connection, user_id: i32) -> Result<Vec<i32>> {
+    use schema::{due_items, question_data, exercise_data, comparison_data, accent_data,
+                 dictation_data, pending_items, q_asked_data, e_asked_data, c_asked_data,
+                 a_asked_data, d_asked_data};
+
+    let pending_questions: Vec<i32> = pending_items::table.inner_join(q_asked_data::table)
+        .filter(pending_items::user_id.eq(user_id))
+        .filter(pending_items::pending.eq(true))
+        .filter(pending_items::practice.eq(false))
+        .select(q_asked_data::question_id)
+        .get_results(&**conn)?;
+
+    let pending_exercises: Vec<i32> = pending_items::table.inner_join(e_asked_data::table)
+        .filter(pending_items::user_id.eq(user_id))
+        .filter(pending_items::pending.eq(true))
+        .filter(pending_items::practice.eq(false))
+        .select(e_asked_data::exercise_id)
+        .get_results(&**conn)?;
 
-    let due_questions: Option<(DueItem, Option<QuestionData>)> =
-        due_items::table.left_outer_join(question_data::table)
-            .filter(due_items::user_id.eq(user_id))
-            .order(due_items::due_date.asc())
-            .first(&**conn)
-            .optional()?;
+    let pending_comparisons: Vec<i32> = pending_items::table.inner_join(c_asked_data::table)
+        .filter(pending_items::user_id.eq(user_id))
+        .filter(pending_items::pending.eq(true))
+        .filter(pending_items::practice.eq(false))
+        .select(c_asked_data::pair_id)
+        .get_results(&**conn)?;
 
-    let due_exercises: Option<(DueItem, Option<ExerciseData>)> =
-        due_items::table.left_outer_join(exercise_data::table)
-            .filter(due_items::user_id.eq(user_id))
-            .order(due_items::due_date.asc())
-            .first(&**conn)
-            .optional()?;
+    let pending_accents: Vec<i32> = pending_items::table.inner_join(a_asked_data::table)
+        .filter(pending_items::user_id.eq(user_id))
+        .filter(pending_items::pending.eq(true))
+        .filter(pending_items::practice.eq(false))
+        .select(a_asked_data::word_id)
+        .get_results(&**conn)?;
 
-    let due_item = due_questions.into_iter()
-        .zip(due_exercises)
-        .next()
-        .map(|zipped| match zipped {
-                 ((di, Some(qdata)), (_, None)) => (di, QuizType::Question(qdata.question_id)),
-                 ((_, None), (di, Some(edata))) => (di, QuizType::Exercise(edata.exercise_id)),
-                 e => {
-            println!("WHY? {:?}", e);
-            unreachable!()
-        }
-             });
+    let pending_dictations: Vec<i32> = pending_items::table.inner_join(d_asked_data::table)
+        .filter(pending_items::user_id.eq(user_id))
+        .filter(pending_items::pending.eq(true))
+        .filter(pending_items::practice.eq(false))
+        .select(d_asked_data::word_id)
+        .get_results(&**conn)?;
+
+    if pending_questions.is_empty() && pending_exercises.is_empty() &&
+       pending_comparisons.is_empty() && pending_accents.is_empty() &&
+       pending_dictations.is_empty() {
+        return Ok(vec![]);
+    }
 
-    Ok(due_item)
+    let mut reserved: Vec<i32> = due_items::table.inner_join(question_data::table)
+        .filter(due_items::user_id.eq(user_id))
+        .filter(question_data::question_id.eq_any(pending_questions))
+        .select(due_items::id)
+        .get_results(&**conn)?;
+
+    reserved.extend(due_items::table.inner_join(exercise_data::table)
+        .filter(due_items::user_id.eq(user_id))
+        .filter(exercise_data::exercise_id.eq_any(pending_exercises))
+        .select(due_items::id)
+        .get_results::<i32>(&**conn)?);
+
+    reserved.extend(due_items::table.inner_join(comparison_data::table)
+        .filter(due_items::user_id.eq(user_id))
+        .filter(comparison_data::pair_id.eq_any(pending_comparisons))
+        .select(due_items::id)
+        .get_results::<i32>(&**conn)?);
+
+    reserved.extend(due_items::table.inner_join(accent_data::table)
+        .filter(due_items::user_id.eq(user_id))
+        .filter(accent_data::word_id.eq_any(pending_accents))
+        .select(due_items::id)
+        .get_results::<i32>(&**conn)?);
+
+    reserved.extend(due_items::table.inner_join(dictation_data::table)
+        .filter(due_items::user_id.eq(user_id))
+        .filter(dictation_data::word_id.eq_any(pending_dictations))
+        .select(due_items::id)
+        .get_results::<i32>(&**conn)?);
+
+    Ok(reserved)
 }
 
 pub fn count_overdue_items(conn: &Connection, user_id: i32) -> Result<i64> {
     use schema::due_items;
 
     let count: i64 = due_items::table.filter(due_items::user_id.eq(user_id))
-        .filter(due_items::due_date.lt(chrono::offset::Utc::now()))
+        .filter(due_items::suspended.eq(false))
+        .filter(due_items::parked.eq(false))
+        .filter(due_items::due_date.lt(clock::now()))
         .count()
         .get_result(&**conn)?;
 
@@ -532,64 +1752,76 @@ pub fn count_overdue_items(conn: &Connection, user_id: i32) -> Result<i64> {
 }
 
 fn choose_random_overdue_item(conn: &Connection, user_id: i32) -> Result<Option<QuizType>> {
-    use schema::{due_items, question_data, exercise_data};
+    use schema::due_items;
+
+    let reserved = reserved_due_items(conn, user_id)?;
 
     let due: Option<DueItem> = due_items::table.filter(due_items::user_id.eq(user_id))
-        .filter(due_items::due_date.lt(chrono::offset::Utc::now()))
-        .filter(due_items::cooldown_delay.lt(chrono::offset::Utc::now()))
+        .filter(due_items::suspended.eq(false))
+        .filter(due_items::parked.eq(false))
+        .filter(due_items::due_date.lt(clock::now()))
+        .filter(due_items::id.ne_all(reserved))
+        .filter(due_items::cooldown_delay.lt(clock::now()))
         .order(sql::random)
         .first(&**conn)
         .optional()?;
 
-    Ok(match due {
-           Some(ref due) if due.item_type == "question" => {
-            Some(QuizType::Question(question_data::table.filter(question_data::due.eq(due.id))
-                .get_result::<QuestionData>(&**conn)?
-                .question_id))
-        }
-           Some(ref due) if due.item_type == "exercise" => {
-            Some(QuizType::Exercise(exercise_data::table.filter(exercise_data::due.eq(due.id))
-                .get_result::<ExerciseData>(&**conn)?
-                .exercise_id))
-        }
-           Some(_) => {
-               return Err(ErrorKind::DatabaseOdd("Database contains due_item with an odd item_type \
-                                               value!")
-                                  .into())
-           }
-           None => None,
-       })
+    match due {
+        Some(ref due) => Ok(Some(due_item_quiztype(conn, due)?)),
+        None => Ok(None),
+    }
 }
 
 fn choose_random_overdue_item_include_cooldown(conn: &Connection,
                                                user_id: i32)
                                                -> Result<Option<QuizType>> {
-    use schema::{due_items, question_data, exercise_data};
+    use schema::due_items;
+
+    let reserved = reserved_due_items(conn, user_id)?;
 
     let due: Option<DueItem> = due_items::table.filter(due_items::user_id.eq(user_id))
-        .filter(due_items::due_date.lt(chrono::offset::Utc::now()))
+        .filter(due_items::suspended.eq(false))
+        .filter(due_items::parked.eq(false))
+        .filter(due_items::due_date.lt(clock::now()))
+        .filter(due_items::id.ne_all(reserved))
         .order(sql::random)
         .first(&**conn)
         .optional()?;
 
-    Ok(match due {
-           Some(ref due) if due.item_type == "question" => {
-            Some(QuizType::Question(question_data::table.filter(question_data::due.eq(due.id))
-                .get_result::<QuestionData>(&**conn)?
-                .question_id))
-        }
-           Some(ref due) if due.item_type == "exercise" => {
-            Some(QuizType::Exercise(exercise_data::table.filter(exercise_data::due.eq(due.id))
-                .get_result::<ExerciseData>(&**conn)?
-                .exercise_id))
-        }
-           Some(_) => {
-               return Err(ErrorKind::DatabaseOdd("Database contains due_item with an odd item_type \
-                                               value!")
-                                  .into())
-           }
-           None => None,
-       })
+    match due {
+        Some(ref due) => Ok(Some(due_item_quiztype(conn, due)?)),
+        None => Ok(None),
+    }
+}
+
+/// An SQL condition for the raw queries that choose new content: the item in the table
+/// aliased `alias` is published, or it's scheduled to be published for the user's groups.
+fn published_for_user(item_type: &str, alias: &str, user_id: i32) -> String {
+    format!("({}.published = true OR {})",
+            alias,
+            publishing::sql_scheduled_for_user(item_type, &format!("{}.id", alias), user_id))
+}
+
+/// An SQL condition for the raw queries that choose new content: the item with the id in
+/// `id_column` isn't in the trash.
+fn not_trashed(item_type: &str, id_column: &str) -> String {
+    format!("NOT EXISTS ( SELECT 1 FROM trash WHERE item_type='{}' AND item_id={} )",
+            item_type,
+            id_column)
+}
+
+/// An SQL condition for the raw queries that choose new content: the user has reached
+/// the required levels on all the prerequisites of the skill nugget in `skill_column`.
+fn prerequisites_met(skill_column: &str, user_id: i32) -> String {
+    format!(r###"NOT EXISTS (
+        SELECT 1
+            FROM skill_prerequisites AS p
+            LEFT OUTER JOIN skill_data AS pd
+            ON pd.skill_nugget=p.prerequisite_id AND pd.user_id={}
+            WHERE p.skill_id={} AND COALESCE(pd.skill_level, 0) < p.required_level
+    )"###,
+            user_id,
+            skill_column)
 }
 
 fn choose_new_question(conn: &Connection, user_id: i32) -> Result<Option<QuizQuestion>> {
@@ -646,10 +1878,19 @@ FROM
     ON skill_nugget=skill_id
 WHERE
     q.skill_level <= COALESCE(s.skill_level, 0) AND
-    q.published = true AND
-    q.id NOT IN ( SELECT question_id FROM due_items JOIN question_data ON id=due WHERE user_id={} )
+    {} AND
+    {} AND
+    {} AND
+    q.id NOT IN ( SELECT question_id FROM due_items JOIN question_data ON id=due WHERE user_id={} ) AND
+    q.id NOT IN (
+        SELECT question_id
+            FROM pending_items
+            JOIN q_asked_data
+            ON pending_items.id=q_asked_data.id
+            WHERE user_id={} AND pending_items.pending=true
+    )
 ORDER BY RANDOM();
-"###, user_id, user_id)) // Injection isn't possible: user_id is numerical and non-tainted data.
+"###, user_id, prerequisites_met("q.skill_id", user_id), published_for_user("question", "q", user_id), not_trashed("question", "q.id"), user_id, user_id)) // Injection isn't possible: user_id is numerical and non-tainted data.
         .get_result(&**conn)
         .optional()?;
 
@@ -704,16 +1945,181 @@ FROM
     ON skill_nugget=skill_id
 WHERE
     e.skill_level <= COALESCE(s.skill_level, 0) AND
-    e.published = true AND
-    e.id NOT IN ( SELECT exercise_id FROM due_items JOIN exercise_data ON id=due WHERE user_id={} )
+    {} AND
+    {} AND
+    {} AND
+    e.id NOT IN ( SELECT exercise_id FROM due_items JOIN exercise_data ON id=due WHERE user_id={} ) AND
+    e.id NOT IN (
+        SELECT exercise_id
+            FROM pending_items
+            JOIN e_asked_data
+            ON pending_items.id=e_asked_data.id
+            WHERE user_id={} AND pending_items.pending=true
+    )
 ORDER BY RANDOM();
-"###, user_id, user_id)) // Injection isn't possible: user_id is numerical and non-tainted data.
+"###, user_id, prerequisites_met("e.skill_id", user_id), published_for_user("exercise", "e", user_id), not_trashed("exercise", "e.id"), user_id, user_id)) // Injection isn't possible: user_id is numerical and non-tainted data.
         .get_result(&**conn)
         .optional()?;
 
     Ok(new_exercise)
 }
 
+fn choose_new_comparison(conn: &Connection, user_id: i32) -> Result<Option<ComparisonPair>> {
+    use diesel::expression::dsl::*;
+
+    // Both of the words must be already introduced to the user.
+    let new_comparison: Option<ComparisonPair> =
+        sql::<(
+        diesel::sql_types::Integer,
+        diesel::sql_types::Integer,
+        diesel::sql_types::Integer,
+        diesel::sql_types::Integer,
+        diesel::sql_types::Bool,
+        diesel::sql_types::Integer,
+        )>(&format!(r###"
+SELECT
+    id,
+    skill_id,
+    word_a,
+    word_b,
+    published,
+    c.skill_level
+FROM
+    comparison_pairs AS c
+    LEFT OUTER JOIN
+    (
+        SELECT
+            skill_level,
+            skill_nugget
+        FROM skill_data
+        WHERE user_id={}
+    ) AS s
+    ON skill_nugget=skill_id
+WHERE
+    c.skill_level <= COALESCE(s.skill_level, 0) AND
+    c.published = true AND
+    {} AND
+    {} AND
+    {} AND
+    c.id NOT IN ( SELECT pair_id FROM due_items JOIN comparison_data ON id=due WHERE user_id={} ) AND
+    c.id NOT IN (
+        SELECT pair_id
+            FROM pending_items
+            JOIN c_asked_data
+            ON pending_items.id=c_asked_data.id
+            WHERE user_id={} AND pending_items.pending=true
+    ) AND
+    c.word_a IN (
+        SELECT word_id
+            FROM pending_items
+            JOIN w_asked_data
+            ON pending_items.id=w_asked_data.id
+            WHERE user_id={} AND pending_items.pending=false
+    ) AND
+    c.word_b IN (
+        SELECT word_id
+            FROM pending_items
+            JOIN w_asked_data
+            ON pending_items.id=w_asked_data.id
+            WHERE user_id={} AND pending_items.pending=false
+    )
+ORDER BY RANDOM();
+"###, user_id, not_trashed("comparison", "c.id"), not_trashed("word", "c.word_a"), not_trashed("word", "c.word_b"), user_id, user_id, user_id, user_id)) // Injection isn't possible: user_id is numerical and non-tainted data.
+        .get_result(&**conn)
+        .optional()?;
+
+    Ok(new_comparison)
+}
+
+/// Any word the user has been already introduced to can be asked, as long as its accent
+/// can be parsed. (Sentences can't.)
+fn choose_new_accent(conn: &Connection, user_id: i32) -> Result<Option<Word>> {
+    use diesel::expression::dsl::*;
+
+    let candidates: Vec<i32> = sql::<diesel::sql_types::Integer>(&format!(r###"
+SELECT
+    w.id
+FROM
+    words AS w
+WHERE
+    {} AND
+    {} AND
+    position('／' in w.word) = 0 AND
+    w.id NOT IN ( SELECT word_id FROM due_items JOIN accent_data ON id=due WHERE user_id={} ) AND
+    w.id NOT IN (
+        SELECT word_id
+            FROM pending_items
+            JOIN a_asked_data
+            ON pending_items.id=a_asked_data.id
+            WHERE user_id={} AND pending_items.pending=true
+    ) AND
+    w.id IN (
+        SELECT word_id
+            FROM pending_items
+            JOIN w_asked_data
+            ON pending_items.id=w_asked_data.id
+            WHERE user_id={} AND pending_items.pending=false
+    )
+ORDER BY RANDOM()
+LIMIT 20;
+"###, published_for_user("word", "w", user_id), not_trashed("word", "w.id"), user_id, user_id, user_id)) // Injection isn't possible: user_id is numerical and non-tainted data.
+        .load(&**conn)?;
+
+    for id in candidates {
+        if let Some(word) = load_word(conn, id)? {
+            if accent::parse(&word.word).is_ok() {
+                return Ok(Some(word));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Any word or sentence the user has been already introduced to can be dictated,
+/// as long as it's written in kana only.
+fn choose_new_dictation(conn: &Connection, user_id: i32) -> Result<Option<Word>> {
+    use diesel::expression::dsl::*;
+
+    let candidates: Vec<i32> = sql::<diesel::sql_types::Integer>(&format!(r###"
+SELECT
+    w.id
+FROM
+    words AS w
+WHERE
+    {} AND
+    {} AND
+    w.id NOT IN ( SELECT word_id FROM due_items JOIN dictation_data ON id=due WHERE user_id={} ) AND
+    w.id NOT IN (
+        SELECT word_id
+            FROM pending_items
+            JOIN d_asked_data
+            ON pending_items.id=d_asked_data.id
+            WHERE user_id={} AND pending_items.pending=true
+    ) AND
+    w.id IN (
+        SELECT word_id
+            FROM pending_items
+            JOIN w_asked_data
+            ON pending_items.id=w_asked_data.id
+            WHERE user_id={} AND pending_items.pending=false
+    )
+ORDER BY RANDOM()
+LIMIT 20;
+"###, published_for_user("word", "w", user_id), not_trashed("word", "w.id"), user_id, user_id, user_id)) // Injection isn't possible: user_id is numerical and non-tainted data.
+        .load(&**conn)?;
+
+    for id in candidates {
+        if let Some(word) = load_word(conn, id)? {
+            if dictation::is_dictatable(&word.word) {
+                return Ok(Some(word));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
 fn choose_cooldown_q_or_e(conn: &Connection,
                           user_id: i32,
                           metrics: &UserMetrics)
@@ -721,7 +2127,7 @@ fn choose_cooldown_q_or_e(conn: &Connection,
 
     if metrics.quizes_since_break >= metrics.max_quizes_since_break ||
        metrics.quizes_today >= metrics.max_quizes_today ||
-       metrics.break_until > chrono::offset::Utc::now() {
+       metrics.break_until > clock::now() {
         return Ok(None);
     }
 
@@ -745,6 +2151,25 @@ fn choose_new_q_or_e(conn: &Connection, user_id: i32) -> Result<Option<QuizType>
             return Ok(Some(QuizType::Exercise(e.id)));
         }
     }
+
+    if user::check_user_group(conn, user_id, "comparisons")? {
+        if let Some(c) = choose_new_comparison(conn, user_id)? {
+            return Ok(Some(QuizType::Comparison(c.id)));
+        }
+    }
+
+    if user::check_user_group(conn, user_id, "accent_quizzes")? {
+        if let Some(w) = choose_new_accent(conn, user_id)? {
+            return Ok(Some(QuizType::Accent(w.id)));
+        }
+    }
+
+    if user::check_user_group(conn, user_id, "dictation_quizzes")? {
+        if let Some(w) = choose_new_dictation(conn, user_id)? {
+            return Ok(Some(QuizType::Dictation(w.id)));
+        }
+    }
+
     Ok(None)
 }
 
@@ -755,7 +2180,7 @@ fn choose_q_or_e(conn: &Connection,
 
     if metrics.quizes_since_break >= metrics.max_quizes_since_break ||
        metrics.quizes_today >= metrics.max_quizes_today ||
-       metrics.break_until > chrono::offset::Utc::now() {
+       metrics.break_until > clock::now() {
         debug!("Enough quizes for today. The break/daily limits are full: Quizes/break: {} Quizes/day: {}.",
             metrics.quizes_since_break, metrics.quizes_today);
         return Ok(None);
@@ -778,10 +2203,14 @@ fn choose_q_or_e(conn: &Connection,
     Ok(None)
 }
 
-/// Choose a new word by random.
+/// Choose a new word in the given order.
 /// The word must be published, the required skill level of the word must be smaller
 /// than the user's level on the corresponding skill and the word must not be seen before.
-fn choose_new_random_word(conn: &Connection, user_id: i32) -> Result<Option<Word>> {
+/// The user must have reached the required levels on the prerequisites of the skill.
+fn choose_new_random_word(conn: &Connection,
+                          user_id: i32,
+                          order: NewWordOrder)
+                          -> Result<Option<Word>> {
     use diesel::expression::dsl::*;
 
     /* // Diesel doesn't support this complex queries yet, so doing it directly in SQL
@@ -809,6 +2238,8 @@ fn choose_new_random_word(conn: &Connection, user_id: i32) -> Result<Option<Word
         diesel::sql_types::Bool,
         diesel::sql_types::Integer,
         diesel::sql_types::Integer,
+        diesel::sql_types::Text,
+        diesel::sql_types::Text,
         )>(&format!(r###"
 SELECT
     id,
@@ -818,7 +2249,9 @@ SELECT
     words.skill_nugget,
     published,
     words.skill_level,
-    priority
+    priority,
+    reading,
+    romaji
 FROM
     words
     LEFT OUTER JOIN
@@ -832,7 +2265,9 @@ FROM
     ON skill_data.skill_nugget=words.skill_nugget
 WHERE
     words.skill_level <= COALESCE(skill_data.skill_level, 0) AND
-    words.published = true AND
+    {} AND
+    {} AND
+    {} AND
     words.id NOT IN (
         SELECT word_id
             FROM pending_items
@@ -840,22 +2275,26 @@ WHERE
             ON pending_items.id=w_asked_data.id
             WHERE user_id={} AND pending_items.test_item=false
     )
-ORDER BY words.priority DESC, RANDOM();
-"###, user_id, user_id)) // Injection isn't possible: user_id is numerical and non-tainted data.
+ORDER BY {};
+"###, user_id, prerequisites_met("words.skill_nugget", user_id), published_for_user("word", "words", user_id), not_trashed("word", "words.id"), user_id, order.order_by())) // Injection isn't possible: user_id is numerical and non-tainted data.
         .get_result(&**conn)
         .optional()?;
 
     Ok(new_word)
 }
 
-/// Choose a new word by random.
+/// Choose a new word in the given order.
 /// The word must be published, the required skill level of the word must be smaller
 /// than the user's level on the corresponding skill and the word must not be seen before.
+/// The user must have reached the required levels on the prerequisites of the skill.
 /// Additionally, the user's
 /// skill level on the corresponding skill must be greater than zero.
 /// This ensures that only words of skills that the user
 /// has experience with are selected.
-fn choose_new_paired_word(conn: &Connection, user_id: i32) -> Result<Option<Word>> {
+fn choose_new_paired_word(conn: &Connection,
+                          user_id: i32,
+                          order: NewWordOrder)
+                          -> Result<Option<Word>> {
     use diesel::expression::dsl::*;
     /*
     use schema::{pending_items, words, w_asked_data, skill_nuggets, skill_data};
@@ -888,6 +2327,8 @@ fn choose_new_paired_word(conn: &Connection, user_id: i32) -> Result<Option<Word
         diesel::sql_types::Bool,
         diesel::sql_types::Integer,
         diesel::sql_types::Integer,
+        diesel::sql_types::Text,
+        diesel::sql_types::Text,
         )>(&format!(r###"
 SELECT
     id,
@@ -897,7 +2338,9 @@ SELECT
     words.skill_nugget,
     published,
     words.skill_level,
-    priority
+    priority,
+    reading,
+    romaji
 FROM
     words
     LEFT OUTER JOIN
@@ -912,7 +2355,9 @@ FROM
 WHERE
     COALESCE(skill_data.skill_level, 0) > 0 AND
     words.skill_level <= COALESCE(skill_data.skill_level, 0) AND
-    words.published = true AND
+    {} AND
+    {} AND
+    {} AND
     words.id NOT IN (
         SELECT word_id
             FROM pending_items
@@ -920,8 +2365,8 @@ WHERE
             ON pending_items.id=w_asked_data.id
             WHERE user_id={} AND pending_items.test_item=false
     )
-ORDER BY words.priority DESC, RANDOM();
-"###, user_id, user_id)) // Injection isn't possible: user_id is numerical and non-tainted data.
+ORDER BY {};
+"###, user_id, prerequisites_met("words.skill_nugget", user_id), published_for_user("word", "words", user_id), not_trashed("word", "words.id"), user_id, order.order_by())) // Injection isn't possible: user_id is numerical and non-tainted data.
         .get_result(&**conn)
         .optional()?;
 
@@ -935,20 +2380,21 @@ fn choose_new_word(conn: &Connection, metrics: &UserMetrics) -> Result<Option<Wo
        metrics.quizes_today >= metrics.max_quizes_today ||
        metrics.new_words_since_break >= metrics.max_words_since_break ||
        metrics.new_words_today >= metrics.max_words_today ||
-       metrics.break_until > chrono::offset::Utc::now() {
+       metrics.break_until > clock::now() {
         debug!("Enough words for today. The break/daily limits are full: Quizes/break: {} Quizes/day: {} Words/break: {} Words/day: {}.",
             metrics.quizes_since_break, metrics.quizes_today, metrics.new_words_since_break, metrics.new_words_today);
         return Ok(None);
     }
 
     let user_id = metrics.id;
+    let order = get_new_word_order(conn, user_id)?;
 
-    if let Some(paired_word) = choose_new_paired_word(conn, user_id)? {
+    if let Some(paired_word) = choose_new_paired_word(conn, user_id, order)? {
         debug!("There is a yet-unintroduced pair word; presenting that.");
         return Ok(Some(paired_word));
     }
 
-    if let Some(rand_word) = choose_new_random_word(conn, user_id)? {
+    if let Some(rand_word) = choose_new_random_word(conn, user_id, order)? {
         debug!("No paired words, returning a randomly chosen word.");
         return Ok(Some(rand_word));
     }
@@ -960,7 +2406,7 @@ fn choose_new_word(conn: &Connection, metrics: &UserMetrics) -> Result<Option<Wo
 fn clear_limits(conn: &Connection, metrics: &mut UserMetrics) -> Result<Option<Quiz>> {
     use schema::user_stats;
 
-    if chrono::offset::Utc::now() < metrics.break_until {
+    if clock::now() < metrics.break_until {
         let due_string = metrics.break_until.to_rfc3339();
         return Ok(Some(Quiz::F(FutureJson {
                                    quiz_type: "future",
@@ -970,9 +2416,9 @@ fn clear_limits(conn: &Connection, metrics: &mut UserMetrics) -> Result<Option<Q
 
     // This is important because we have to zero the counts
     // once every day even though we wouldn't break a single time!
-    // We are having 1 AM as the change point of the day
-    // (That's 3 AM in Finland, where the users of this app are likely to reside)
-    if chrono::offset::Utc::now() > (metrics.today.date().and_hms(1, 0, 0) + chrono::Duration::hours(24)) {
+    // The day changes at the user's own day rollover hour, in their own timezone.
+    let day_start = user::local_day_start(metrics, clock::now());
+    if metrics.today < day_start {
 
         let mut stats: UserStats = user_stats::table.filter(user_stats::id.eq(metrics.id))
             .get_result(&**conn)?;
@@ -980,7 +2426,7 @@ fn clear_limits(conn: &Connection, metrics: &mut UserMetrics) -> Result<Option<Q
         stats.days_used += 1;
         let _: UserStats = stats.save_changes(&**conn)?;
 
-        metrics.today = chrono::offset::Utc::today().and_hms(1, 0, 0);
+        metrics.today = day_start;
         metrics.new_words_since_break = 0;
         metrics.quizes_since_break = 0;
         metrics.new_words_today = 0;
@@ -995,13 +2441,119 @@ pub fn things_left_to_do(conn: &Connection,
 
     let next_existing_due =
         choose_next_due_item(conn, user_id)?.map(|(due_item, _)| due_item.due_date);
-    let no_new_words = choose_new_random_word(conn, user_id)?.is_none();
+    // Only the existence of new words matters here, not their order
+    let no_new_words = choose_new_random_word(conn, user_id, NewWordOrder::Priority)?.is_none();
     let no_new_quizes = choose_new_q_or_e(conn, user_id)?.is_none();
 
     Ok((next_existing_due, no_new_words, no_new_quizes))
 }
 
 
+/// Projects the review workload of the learner for `days` local days, starting from today.
+/// Assumes that every item is reviewed on the day it's due if the daily quiz limit allows,
+/// and that the reviews aren't re-scheduled to fall due again inside the forecast period.
+/// A review takes `streak_limit` quizes, so the daily quiz limit allows
+/// `max_quizes_today / streak_limit` reviews.
+pub fn forecast(conn: &Connection, user_id: i32, days: usize) -> Result<Forecast> {
+    use schema::{due_items, pending_items, w_asked_data, words};
+    use std::cmp::{max, min};
+
+    let metrics = user::get_metrics(conn, user_id)?;
+    let now = clock::now();
+    let today = user::local_day_start(&metrics, now);
+
+    let due_dates: Vec<DateTime<Utc>> = due_items::table
+        .filter(due_items::user_id.eq(user_id))
+        .filter(due_items::suspended.eq(false))
+        .filter(due_items::parked.eq(false))
+        .select(due_items::due_date)
+        .order(due_items::due_date.asc())
+        .get_results(&**conn)?;
+
+    let overdue = due_dates.iter().filter(|&&d| d < now).count() as i64;
+
+    let streak_limit = max(1, metrics.streak_limit);
+    // Today, nothing is reviewed if the items that are due can't be asked before tomorrow.
+    let (next_existing_due, _, _) = things_left_to_do(conn, user_id)?;
+    let reviewable_today = next_existing_due.map_or(false, |d| {
+        d < user::next_local_day_start(&metrics, today)
+    });
+
+    let mut forecast_days = Vec::with_capacity(days);
+    let mut remaining = due_dates.as_slice();
+    let mut backlog = 0;
+    let mut day_start = today;
+    for day in 0..days {
+        let day_end = user::next_local_day_start(&metrics, day_start);
+        let due = remaining.iter().take_while(|&&d| d < day_end).count();
+        remaining = &remaining[due..];
+
+        // The quizes already done today count towards today's limit
+        let quizes = if day > 0 {
+            metrics.max_quizes_today
+        } else if !reviewable_today {
+            0
+        } else if metrics.today >= today {
+            max(0, metrics.max_quizes_today - metrics.quizes_today)
+        } else {
+            metrics.max_quizes_today
+        };
+        let limit = (quizes / streak_limit) as i64;
+        backlog += due as i64;
+        let projected = min(backlog, limit);
+        backlog -= projected;
+
+        forecast_days.push(ForecastDay {
+                               day_start: day_start,
+                               due: due as i64,
+                               projected: projected,
+                               backlog: backlog,
+                           });
+        day_start = day_end;
+    }
+
+    let seen_words: Vec<i32> = pending_items::table.inner_join(w_asked_data::table)
+        .filter(pending_items::user_id.eq(user_id))
+        .filter(pending_items::test_item.eq(false))
+        .select(w_asked_data::word_id)
+        .get_results(&**conn)?;
+
+    let scheduled_words = publishing::scheduled_for_user(conn, "word", user_id)?;
+    let unseen_words: i64 = words::table.filter(words::published.eq(true)
+                                                    .or(words::id.eq_any(scheduled_words)))
+        .filter(words::id.ne_all(trash::trashed_ids(conn, "word")?))
+        .filter(words::id.ne_all(seen_words))
+        .count()
+        .get_result(&**conn)?;
+
+    let words_run_out = if unseen_words == 0 {
+        Some(today)
+    } else if metrics.max_words_today <= 0 {
+        None
+    } else {
+        let mut left = unseen_words;
+        let mut day_start = today;
+        if metrics.today >= today {
+            left -= max(0, metrics.max_words_today - metrics.new_words_today) as i64;
+        } else {
+            left -= metrics.max_words_today as i64;
+        }
+        while left > 0 {
+            day_start = user::next_local_day_start(&metrics, day_start);
+            left -= metrics.max_words_today as i64;
+        }
+        Some(day_start)
+    };
+
+    Ok(Forecast {
+           overdue: overdue,
+           days: forecast_days,
+           unseen_words: unseen_words,
+           words_run_out: words_run_out,
+       })
+}
+
+
 fn check_break(conn: &Connection, user_id: i32, metrics: &mut UserMetrics) -> Result<Option<Quiz>> {
     use std::cmp::max;
     use chrono::Duration;
@@ -1038,7 +2590,7 @@ fn check_break(conn: &Connection, user_id: i32, metrics: &mut UserMetrics) -> Re
         metrics.quizes_since_break = 0;
         metrics.new_words_today = 0;
         metrics.quizes_today = 0;
-        metrics.break_until = metrics.today + Duration::hours(24);
+        metrics.break_until = user::next_local_day_start(metrics, metrics.today);
 
         if no_new_words && no_new_quizes {
             // Nothing else left but due items – so no use breaking until there is some available
@@ -1071,13 +2623,13 @@ fn check_break(conn: &Connection, user_id: i32, metrics: &mut UserMetrics) -> Re
         debug!("Starting a short break because the break limits are full: Quizes since break: {}/{} No words: {} No new quizes: {} No due quizes: {}",
             metrics.quizes_since_break, metrics.max_quizes_since_break, no_words, no_new_quizes, no_due_quizes);
 
-        let time_since_last_break = chrono::offset::Utc::now().signed_duration_since(metrics.break_until);
+        let time_since_last_break = clock::now().signed_duration_since(metrics.break_until);
 
         let discounted_breaktime = max(Duration::seconds(0),
                                        Duration::seconds(metrics.break_length as i64) -
                                        time_since_last_break);
 
-        metrics.break_until = chrono::offset::Utc::now() + discounted_breaktime;
+        metrics.break_until = clock::now() + discounted_breaktime;
 
         if no_new_words && no_new_quizes {
             // Nothing else left but due items – so no use breaking until there is some available
@@ -1134,8 +2686,63 @@ fn ask_new_exercise(conn: &Connection, id: i32) -> Result<(Exercise, Word, i32)>
     Ok((exercise, word, audio_file.id))
 }
 
+/// Chooses by random which one of the pair is shown, and the order the two are played in.
+fn ask_new_comparison(conn: &Connection, id: i32) -> Result<(ComparisonPair, Word, i32, i32, i32)> {
+    use schema::comparison_pairs;
+
+    let pair: ComparisonPair = comparison_pairs::table.filter(comparison_pairs::id.eq(id))
+        .get_result(&**conn)?;
+
+    let (word_a, word_b) = match (load_word(conn, pair.word_a)?, load_word(conn, pair.word_b)?) {
+        (Some(a), Some(b)) => (a, b),
+        _ => bail!(ErrorKind::DatabaseOdd("The words of a comparison pair should exist!")),
+    };
+
+    let mut rng = thread_rng();
+    let (shown, other) = if rng.gen() { (word_a, word_b) } else { (word_b, word_a) };
+
+    let shown_audio = audio::load_random_from_bundle(conn, shown.audio_bundle)?;
+    let other_audio = audio::load_random_from_bundle(conn, other.audio_bundle)?;
+
+    let (first_audio_id, second_audio_id, right_choice) = if rng.gen() {
+        (shown_audio.id, other_audio.id, 1)
+    } else {
+        (other_audio.id, shown_audio.id, 2)
+    };
+
+    Ok((pair, shown, first_audio_id, second_audio_id, right_choice))
+}
+
+fn ask_new_accent(conn: &Connection, id: i32) -> Result<(Word, accent::AccentedWord, i32)> {
+    let word = try_or!( load_word(conn, id)?,
+                else bail!(
+                    ErrorKind::DatabaseOdd(
+                        "This function was called on the premise that the data exists!"
+                    )) );
+
+    let accented = accent::parse(&word.word)
+        .chain_err(|| ErrorKind::DatabaseOdd("Accent quizzes are only about words with a valid accent!"))?;
+
+    let audio_file = audio::load_random_from_bundle(conn, word.audio_bundle)?;
+
+    Ok((word, accented, audio_file.id))
+}
+
+fn ask_new_dictation(conn: &Connection, id: i32) -> Result<(Word, i32)> {
+    let word = try_or!( load_word(conn, id)?,
+                else bail!(
+                    ErrorKind::DatabaseOdd(
+                        "This function was called on the premise that the data exists!"
+                    )) );
+
+    let audio_file = audio::load_random_from_bundle(conn, word.audio_bundle)?;
+
+    Ok((word, audio_file.id))
+}
+
 pub fn penditem_to_quiz(conn: &Connection, pi: &PendingItem) -> Result<Quiz> {
-    use schema::{q_asked_data, e_asked_data, w_asked_data};
+    use schema::{q_asked_data, e_asked_data, w_asked_data, c_asked_data, a_asked_data,
+                 d_asked_data};
 
     Ok(match pi {
            pi if pi.item_type == "question" => {
@@ -1143,50 +2750,111 @@ pub fn penditem_to_quiz(conn: &Connection, pi: &PendingItem) -> Result<Quiz> {
         let asked: QAskedData = q_asked_data::table.filter(q_asked_data::id.eq(pi.id))
             .get_result(&**conn).chain_err(|| ErrorKind::DatabaseOdd("Bug: If item was in pending, it should also be in asked data?!"))?;
 
-        let (question, answers, _) = try_or!{ load_question(conn, asked.question_id)?,
+        let (question, answers, _) = try_or!{ load_question(conn, asked.question_id)?,
+                else bail!(
+                    ErrorKind::DatabaseOdd(
+                        "Bug: If the item was set pending in the first place, it should exist!"
+                    )) };
+
+        let mut answer_choices: Vec<_> =
+            answers.into_iter().map(|a| (a.id, a.answer_text)).collect();
+            answer_choices.shuffle(&mut thread_rng());
+
+        Quiz::Q(QuestionJson {
+                    quiz_type: "question",
+                    asked_id: pi.id,
+                    explanation: question.q_explanation,
+                    question: question.question_text,
+                    right_a: asked.correct_qa_id,
+                    answers: answer_choices,
+                })
+
+    }
+           pi if pi.item_type == "exercise" => {
+
+        let asked: EAskedData = e_asked_data::table.filter(e_asked_data::id.eq(pi.id))
+            .get_result(&**conn)?;
+
+        let word = try_or!{ load_word(conn, asked.word_id)?,
+                else bail!(
+                    ErrorKind::DatabaseOdd(
+                        "Bug: If the item was set pending in the first place, it should exists!"
+                    )) };
+
+        Quiz::E(ExerciseJson {
+                    quiz_type: "exercise",
+                    event_name: "training".to_owned(),
+                    asked_id: pi.id,
+                    word: word.word.nfc().collect::<String>(),
+                    explanation: word.explanation,
+                    must_record: false,
+                    audio_id: pi.audio_file_id,
+                })
+
+    }
+           pi if pi.item_type == "word" => {
+
+        let asked: WAskedData = w_asked_data::table.filter(w_asked_data::id.eq(pi.id))
+            .get_result(&**conn)?;
+
+        let word = try_or!{ load_word(conn, asked.word_id)?,
+                else bail!(
+                    ErrorKind::DatabaseOdd(
+                        "Bug: If the item was set pending in the first place, it should exist!"
+                    )) };
+
+        Quiz::W(WordJson {
+                    quiz_type: "word",
+                    asked_id: pi.id,
+                    accent: accent::parse(&word.word).ok(),
+                    word: word.word.nfc().collect::<String>(),
+                    explanation: word.explanation,
+                    show_accents: asked.show_accents,
+                })
+    }
+           pi if pi.item_type == "comparison" => {
+
+        let asked: CAskedData = c_asked_data::table.filter(c_asked_data::id.eq(pi.id))
+            .get_result(&**conn)?;
+
+        let word = try_or!{ load_word(conn, asked.word_id)?,
                 else bail!(
                     ErrorKind::DatabaseOdd(
                         "Bug: If the item was set pending in the first place, it should exist!"
                     )) };
 
-        let mut answer_choices: Vec<_> =
-            answers.into_iter().map(|a| (a.id, a.answer_text)).collect();
-            answer_choices.shuffle(&mut thread_rng());
-
-        Quiz::Q(QuestionJson {
-                    quiz_type: "question",
+        Quiz::C(ComparisonJson {
+                    quiz_type: "comparison",
                     asked_id: pi.id,
-                    explanation: question.q_explanation,
-                    question: question.question_text,
-                    right_a: asked.correct_qa_id,
-                    answers: answer_choices,
+                    word: word.word.nfc().collect::<String>(),
+                    explanation: word.explanation,
                 })
-
     }
-           pi if pi.item_type == "exercise" => {
+           pi if pi.item_type == "accent" => {
 
-        let asked: EAskedData = e_asked_data::table.filter(e_asked_data::id.eq(pi.id))
+        let asked: AAskedData = a_asked_data::table.filter(a_asked_data::id.eq(pi.id))
             .get_result(&**conn)?;
 
         let word = try_or!{ load_word(conn, asked.word_id)?,
                 else bail!(
                     ErrorKind::DatabaseOdd(
-                        "Bug: If the item was set pending in the first place, it should exists!"
+                        "Bug: If the item was set pending in the first place, it should exist!"
                     )) };
 
-        Quiz::E(ExerciseJson {
-                    quiz_type: "exercise",
-                    event_name: "training".to_owned(),
+        let accented = accent::parse(&word.word)
+            .chain_err(|| ErrorKind::DatabaseOdd("Accent quizzes are only about words with a valid accent!"))?;
+
+        Quiz::A(AccentJson {
+                    quiz_type: "accent",
                     asked_id: pi.id,
-                    word: word.word.nfc().collect::<String>(),
+                    word: accented.kana(),
                     explanation: word.explanation,
-                    must_record: false,
+                    morae: accented.morae,
                 })
-
     }
-           pi if pi.item_type == "word" => {
+           pi if pi.item_type == "dictation" => {
 
-        let asked: WAskedData = w_asked_data::table.filter(w_asked_data::id.eq(pi.id))
+        let asked: DAskedData = d_asked_data::table.filter(d_asked_data::id.eq(pi.id))
             .get_result(&**conn)?;
 
         let word = try_or!{ load_word(conn, asked.word_id)?,
@@ -1195,15 +2863,13 @@ pub fn penditem_to_quiz(conn: &Connection, pi: &PendingItem) -> Result<Quiz> {
                         "Bug: If the item was set pending in the first place, it should exist!"
                     )) };
 
-        Quiz::W(WordJson {
-                    quiz_type: "word",
+        Quiz::D(DictationJson {
+                    quiz_type: "dictation",
                     asked_id: pi.id,
-                    word: word.word.nfc().collect::<String>(),
                     explanation: word.explanation,
-                    show_accents: asked.show_accents,
                 })
     }
-           _ => unreachable!("Bug: There is only three kinds of quiz types!"),
+           _ => unreachable!("Bug: There is only six kinds of quiz types!"),
        })
 }
 
@@ -1213,7 +2879,9 @@ pub fn return_pending_item(conn: &Connection, user_id: i32) -> Result<Option<Qui
     let pending_item: Option<PendingItem> =
         pending_items::table.filter(pending_items::user_id.eq(user_id))
             .filter(pending_items::pending.eq(true).and(pending_items::test_item.eq(false)))
-            .get_result(&**conn)
+            .filter(pending_items::practice.eq(false))
+            .order(pending_items::id.asc())
+            .first(&**conn)
             .optional()?;
 
     let quiz_type = match pending_item {
@@ -1227,6 +2895,14 @@ pub fn return_pending_item(conn: &Connection, user_id: i32) -> Result<Option<Qui
 }
 
 pub fn return_q_or_e(conn: &Connection, user_id: i32, quiztype: QuizType) -> Result<Option<Quiz>> {
+    ask_q_or_e(conn, user_id, quiztype, false)
+}
+
+fn ask_q_or_e(conn: &Connection,
+              user_id: i32,
+              quiztype: QuizType,
+              practice: bool)
+              -> Result<Option<Quiz>> {
 
     match quiztype {
         QuizType::Question(id) => {
@@ -1234,7 +2910,7 @@ pub fn return_q_or_e(conn: &Connection, user_id: i32, quiztype: QuizType) -> Res
             let (question, right_a_id, answers, q_audio_id) = ask_new_question(conn, id)?;
 
             let pending_item =
-                new_pending_item(conn, user_id, QuizType::Question(q_audio_id), false)?;
+                new_pending_item(conn, user_id, QuizType::Question(q_audio_id), false, practice)?;
 
             let asked_data = QAskedData {
                 id: pending_item.id,
@@ -1265,7 +2941,7 @@ pub fn return_q_or_e(conn: &Connection, user_id: i32, quiztype: QuizType) -> Res
             let (exercise, word, audio_id) = ask_new_exercise(conn, id)?;
 
             let pending_item =
-                new_pending_item(conn, user_id, QuizType::Exercise(audio_id), false)?;
+                new_pending_item(conn, user_id, QuizType::Exercise(audio_id), false, practice)?;
 
             let asked_data = EAskedData {
                 id: pending_item.id,
@@ -1282,20 +2958,102 @@ pub fn return_q_or_e(conn: &Connection, user_id: i32, quiztype: QuizType) -> Res
                 word: word.word.nfc().collect::<String>(),
                 explanation: word.explanation,
                 must_record: false,
+                audio_id: audio_id,
             };
 
             Ok(Some(Quiz::E(quiz_json)))
         }
+        QuizType::Comparison(id) => {
+
+            let (pair, word, first_audio_id, second_audio_id, right_choice) =
+                ask_new_comparison(conn, id)?;
+
+            let pending_item =
+                new_pending_item(conn, user_id, QuizType::Comparison(first_audio_id), false, practice)?;
+
+            let asked_data = CAskedData {
+                id: pending_item.id,
+                pair_id: pair.id,
+                word_id: word.id,
+                first_audio_id: first_audio_id,
+                second_audio_id: second_audio_id,
+                right_choice: right_choice,
+            };
+
+            register_future_c_answer(conn, &asked_data)?;
+
+            let quiz_json = ComparisonJson {
+                quiz_type: "comparison",
+                asked_id: pending_item.id,
+                word: word.word.nfc().collect::<String>(),
+                explanation: word.explanation,
+            };
+
+            Ok(Some(Quiz::C(quiz_json)))
+        }
+        QuizType::Accent(id) => {
+
+            let (word, accented, audio_id) = ask_new_accent(conn, id)?;
+
+            let pending_item =
+                new_pending_item(conn, user_id, QuizType::Accent(audio_id), false, practice)?;
+
+            let asked_data = AAskedData {
+                id: pending_item.id,
+                word_id: word.id,
+                right_nucleus: accented.nucleus.map(|n| n as i32),
+            };
+
+            register_future_a_answer(conn, &asked_data)?;
+
+            let quiz_json = AccentJson {
+                quiz_type: "accent",
+                asked_id: pending_item.id,
+                word: accented.kana(),
+                explanation: word.explanation,
+                morae: accented.morae,
+            };
+
+            Ok(Some(Quiz::A(quiz_json)))
+        }
+        QuizType::Dictation(id) => {
+
+            let (word, audio_id) = ask_new_dictation(conn, id)?;
+
+            let pending_item =
+                new_pending_item(conn, user_id, QuizType::Dictation(audio_id), false, practice)?;
+
+            let asked_data = DAskedData {
+                id: pending_item.id,
+                word_id: word.id,
+                expected_kana: dictation::normalize(&word.word),
+            };
+
+            register_future_d_answer(conn, &asked_data)?;
+
+            let quiz_json = DictationJson {
+                quiz_type: "dictation",
+                asked_id: pending_item.id,
+                explanation: word.explanation,
+            };
+
+            Ok(Some(Quiz::D(quiz_json)))
+        }
         QuizType::Word(_) => unreachable!(),
     }
 }
 
 pub fn return_word(conn: &Connection, user_id: i32, the_word: Word) -> Result<Option<Quiz>> {
+    ask_word(conn, user_id, the_word, false)
+}
+
+fn ask_word(conn: &Connection, user_id: i32, the_word: Word, practice: bool) -> Result<Option<Quiz>> {
 
     let audio_file = audio::load_random_from_bundle(&*conn, the_word.audio_bundle)?;
     let show_accents = user::check_user_group(conn, user_id, "show_accents")?;
 
-    let pending_item = new_pending_item(conn, user_id, QuizType::Word(audio_file.id), false)?;
+    let pending_item =
+        new_pending_item(conn, user_id, QuizType::Word(audio_file.id), false, practice)?;
 
     let asked_data = WAskedData {
         id: pending_item.id,
@@ -1307,6 +3065,7 @@ pub fn return_word(conn: &Connection, user_id: i32, the_word: Word) -> Result<Op
 
     let quiz_json = WordJson {
         quiz_type: "word",
+        accent: accent::parse(&the_word.word).ok(),
         word: the_word.word.nfc().collect::<String>(),
         explanation: the_word.explanation,
         asked_id: pending_item.id,
@@ -1393,7 +3152,7 @@ pub fn test_item(conn: &Connection,
                 panic!("Word: {:?}.\nAudio bundle: {:?}", w, bundle);
             }
 
-            pending_item = new_pending_item(conn, user_id, QuizType::Word(audio_id), true)?;
+            pending_item = new_pending_item(conn, user_id, QuizType::Word(audio_id), true, false)?;
 
             let asked_data = WAskedData {
                 id: pending_item.id,
@@ -1412,6 +3171,7 @@ pub fn test_item(conn: &Connection,
             Quiz::W(WordJson {
                         quiz_type: "word",
                         asked_id: pending_item.id,
+                        accent: accent::parse(&word.word).ok(),
                         word: word.word.nfc().collect::<String>(),
                         explanation: word.explanation,
                         show_accents: asked_data.show_accents,
@@ -1429,7 +3189,7 @@ pub fn test_item(conn: &Connection,
                 panic!("Q Answer: {:?}.\nAudio bundle: {:?}", ans, bundle);
             }
 
-            pending_item = new_pending_item(conn, user_id, QuizType::Question(audio_id), true)?;
+            pending_item = new_pending_item(conn, user_id, QuizType::Question(audio_id), true, false)?;
 
             let asked_data = QAskedData {
                 id: pending_item.id,
@@ -1473,7 +3233,7 @@ pub fn test_item(conn: &Connection,
                 panic!("Word: {:?}.\nAudio bundle: {:?}", w, bundle);
             }
 
-            pending_item = new_pending_item(conn, user_id, QuizType::Exercise(audio_id), true)?;
+            pending_item = new_pending_item(conn, user_id, QuizType::Exercise(audio_id), true, false)?;
 
             let asked_data = EAskedData {
                 id: pending_item.id,
@@ -1496,6 +3256,7 @@ pub fn test_item(conn: &Connection,
                         word: word.word.nfc().collect::<String>(),
                         explanation: word.explanation,
                         must_record: false,
+                        audio_id: audio_id,
                     })
         }
     };
@@ -1522,6 +3283,15 @@ fn get_new_quiz_inner(conn: &Connection,
         return Ok(Some(pending_quiz));
     }
 
+    choose_quiz(conn, user_id, metrics)
+}
+
+/// Chooses a new quiz and asks it, not minding the items that are already pending.
+fn choose_quiz(conn: &Connection,
+               user_id: i32,
+               metrics: &mut UserMetrics)
+               -> Result<Option<Quiz>> {
+
     // Clear the per-day limits if it's tomorrow already and stop if we are in the middle of a break
     if let Some(future) = clear_limits(conn, metrics)? {
         return Ok(Some(future));
@@ -1580,30 +3350,732 @@ pub fn get_new_quiz(conn: &Connection, user_id: i32) -> Result<Option<Quiz>> {
 }
 
 
+/// Logs the answer and returns the next quiz. The answer and the state changes it causes
+/// are committed atomically; if the pending item was already answered, the answer is ignored.
 pub fn get_next_quiz(conn: &Connection,
                      user_id: i32,
                      answer_enum: Answered)
                      -> Result<Option<Quiz>> {
     use schema::user_metrics;
 
-    let mut metrics: UserMetrics = user_metrics::table.filter(user_metrics::id.eq(user_id))
-        .get_result(&**conn)?;
+    conn.transaction(|| {
+        let mut metrics: UserMetrics = user_metrics::table.filter(user_metrics::id.eq(user_id))
+            .get_result(&**conn)?;
+
+        log_answer(conn, user_id, &answer_enum, &mut metrics)?;
+
+        let result = get_new_quiz_inner(conn, user_id, &mut metrics)?;
+
+        let _: UserMetrics = metrics.save_changes(&**conn)?;
+
+        Ok(result)
+    })
+}
+
+/// Whether an answer to the item would be logged: the item is pending, and the answer isn't
+/// a retry of an answer that was already submitted with the same `idempotency_key`.
+/// The side effects of an answer that happen outside of `get_next_quiz` are checked with this.
+pub fn awaits_answer(conn: &Connection,
+                     user_id: i32,
+                     asked_id: i32,
+                     idempotency_key: Option<&str>)
+                     -> Result<bool> {
+    use schema::{answer_submissions, pending_items};
+
+    if let Some(idempotency_key) = idempotency_key {
+        let submitted: Option<AnswerSubmission> = answer_submissions::table
+            .filter(answer_submissions::user_id.eq(user_id))
+            .filter(answer_submissions::idempotency_key.eq(idempotency_key))
+            .get_result(&**conn)
+            .optional()?;
+        if submitted.is_some() {
+            return Ok(false);
+        }
+    }
+
+    let pending_item: Option<PendingItem> = pending_items::table
+        .filter(pending_items::id.eq(asked_id))
+        .filter(pending_items::user_id.eq(user_id))
+        .filter(pending_items::pending.eq(true))
+        .get_result(&**conn)
+        .optional()?;
+
+    Ok(pending_item.is_some())
+}
+
+/// Like `get_next_quiz`, but a retry with the same `idempotency_key` doesn't log the answer again;
+/// it returns the quiz that was returned the first time, if it's still pending.
+pub fn get_next_quiz_idempotent(conn: &Connection,
+                                user_id: i32,
+                                answer_enum: Answered,
+                                idempotency_key: &str)
+                                -> Result<Option<Quiz>> {
+    use schema::{answer_submissions, pending_items};
+
+    if idempotency_key.is_empty() || idempotency_key.len() > 64 {
+        bail!(ErrorKind::InvalidInput);
+    }
+
+    conn.transaction(|| {
+        let inserted = diesel::insert_into(answer_submissions::table)
+            .values(&NewAnswerSubmission { user_id, idempotency_key })
+            .on_conflict_do_nothing()
+            .execute(&**conn)?;
+
+        if inserted == 0 {
+            info!("The answer with this idempotency key was already submitted. Returning the earlier quiz.");
+
+            let submission: AnswerSubmission = answer_submissions::table
+                .filter(answer_submissions::user_id.eq(user_id))
+                .filter(answer_submissions::idempotency_key.eq(idempotency_key))
+                .for_update()
+                .get_result(&**conn)?;
+
+            let pending_item: Option<PendingItem> = match submission.next_pending_item {
+                Some(id) => pending_items::table.filter(pending_items::id.eq(id))
+                    .filter(pending_items::pending.eq(true))
+                    .get_result(&**conn)
+                    .optional()?,
+                None => None,
+            };
+
+            return match pending_item {
+                Some(ref pi) => Ok(Some(penditem_to_quiz(conn, pi)?)),
+                None => get_new_quiz(conn, user_id),
+            };
+        }
+
+        let result = get_next_quiz(conn, user_id, answer_enum)?;
+
+        let next_pending_item = result.as_ref().and_then(Quiz::asked_id);
+
+        diesel::update(answer_submissions::table
+                .filter(answer_submissions::user_id.eq(user_id))
+                .filter(answer_submissions::idempotency_key.eq(idempotency_key)))
+            .set(answer_submissions::next_pending_item.eq(next_pending_item))
+            .execute(&**conn)?;
+
+        Ok(result)
+    })
+}
+
+/// The result of an already logged answer, for the quiz types that are graded on the server.
+/// `None` if the item isn't an answered comparison, accent or dictation quiz of the user.
+pub fn answer_result(conn: &Connection, user_id: i32, asked_id: i32) -> Result<Option<AnswerResult>> {
+    use schema::{pending_items, c_asked_data, c_answered_data, a_asked_data, a_answered_data,
+                 d_asked_data, d_answered_data};
+
+    let comparison: Option<(i32, Option<i32>)> =
+        c_answered_data::table.inner_join(c_asked_data::table.inner_join(pending_items::table))
+            .filter(pending_items::id.eq(asked_id))
+            .filter(pending_items::user_id.eq(user_id))
+            .filter(pending_items::pending.eq(false))
+            .select((c_asked_data::right_choice, c_answered_data::answered_choice))
+            .get_result(&**conn)
+            .optional()?;
+
+    if let Some((right_choice, answered_choice)) = comparison {
+        return Ok(Some(AnswerResult {
+                           asked_id,
+                           correct: answered_choice == Some(right_choice),
+                           right_nucleus: None,
+                           word: None,
+                           right_choice: Some(right_choice),
+                       }));
+    }
+
+    let accent: Option<(Option<i32>, Option<i32>)> =
+        a_answered_data::table.inner_join(a_asked_data::table.inner_join(pending_items::table))
+            .filter(pending_items::id.eq(asked_id))
+            .filter(pending_items::user_id.eq(user_id))
+            .filter(pending_items::pending.eq(false))
+            .select((a_asked_data::right_nucleus, a_answered_data::answered_nucleus))
+            .get_result(&**conn)
+            .optional()?;
+
+    if let Some((right_nucleus, answered_nucleus)) = accent {
+        return Ok(Some(AnswerResult {
+                           asked_id,
+                           correct: answered_nucleus == right_nucleus,
+                           right_nucleus,
+                           word: None,
+                           right_choice: None,
+                       }));
+    }
 
-    match answer_enum {
-        Answered::W(answer_word) => {
-            log_answer_word(conn, user_id, &answer_word, &mut metrics)?;
+    let dictation: Option<(i32, i32)> =
+        d_answered_data::table.inner_join(d_asked_data::table.inner_join(pending_items::table))
+            .filter(pending_items::id.eq(asked_id))
+            .filter(pending_items::user_id.eq(user_id))
+            .filter(pending_items::pending.eq(false))
+            .select((d_asked_data::word_id, d_answered_data::answer_level))
+            .get_result(&**conn)
+            .optional()?;
+
+    if let Some((word_id, answer_level)) = dictation {
+        let word = try_or!{ load_word(conn, word_id)?,
+                else bail!(ErrorKind::DatabaseOdd("Bug: An asked word should exist!")) };
+        return Ok(Some(AnswerResult {
+                           asked_id,
+                           correct: answer_level >= dictation::PASSING_LEVEL,
+                           right_nucleus: None,
+                           word: Some(accent::strip_marks(&word.word)),
+                           right_choice: None,
+                       }));
+    }
+
+    Ok(None)
+}
+
+/// Returns up to `count` quizzes at once, for studying without a connection.
+/// The already pending items are returned first, and the rest are asked (and thus reserved)
+/// in advance. The limits are counted when the answers arrive, so the quizzes handed out here
+/// are counted against a copy of the user metrics. If the limits get full, the last quiz is
+/// a `Quiz::F` that tells when the break ends.
+pub fn prefetch_quizzes(conn: &Connection, user_id: i32, count: usize) -> Result<Vec<Quiz>> {
+    use schema::{user_metrics, pending_items};
+
+    conn.transaction(|| {
+        let mut metrics: UserMetrics = user_metrics::table.filter(user_metrics::id.eq(user_id))
+            .get_result(&**conn)?;
+
+        let pending: Vec<PendingItem> =
+            pending_items::table.filter(pending_items::user_id.eq(user_id))
+                .filter(pending_items::pending.eq(true).and(pending_items::test_item.eq(false)))
+                .filter(pending_items::practice.eq(false))
+                .order(pending_items::id.asc())
+                .limit(count as i64)
+                .get_results(&**conn)?;
+
+        let mut quizzes = pending.iter()
+            .map(|pi| penditem_to_quiz(conn, pi))
+            .collect::<Result<Vec<_>>>()?;
+
+        clear_limits(conn, &mut metrics)?;
+
+        let mut projected = metrics.clone();
+
+        for quiz in &quizzes {
+            count_towards_limits(&mut projected, quiz);
+        }
+
+        while quizzes.len() < count {
+            match choose_quiz(conn, user_id, &mut projected)? {
+                Some(future @ Quiz::F(_)) => {
+                    quizzes.push(future);
+                    break;
+                }
+                Some(quiz) => {
+                    count_towards_limits(&mut projected, &quiz);
+                    quizzes.push(quiz);
+                }
+                None => break,
+            }
         }
-        Answered::E(exercise) => {
-            log_answer_exercise(conn, user_id, &exercise, &mut metrics)?;
+
+        let _: UserMetrics = metrics.save_changes(&**conn)?;
+
+        Ok(quizzes)
+    })
+}
+
+fn count_towards_limits(metrics: &mut UserMetrics, quiz: &Quiz) {
+    match *quiz {
+        Quiz::Q(_) | Quiz::E(_) | Quiz::C(_) | Quiz::A(_) | Quiz::D(_) => {
+            metrics.quizes_since_break += 1;
+            metrics.quizes_today += 1;
         }
-        Answered::Q(answer) => {
-            log_answer_question(conn, user_id, &answer, &mut metrics)?;
+        Quiz::W(_) => {
+            metrics.new_words_since_break += 1;
+            metrics.new_words_today += 1;
         }
+        Quiz::F(_) => (),
     }
+}
 
-    let result = get_new_quiz_inner(conn, user_id, &mut metrics)?;
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncStatus {
+    Logged,
+    AlreadyAnswered,
+    Missing,
+    /// The content that was asked has been moved to the trash.
+    Deleted,
+}
 
-    let _: UserMetrics = metrics.save_changes(&**conn)?;
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncResult {
+    pub asked_id: i32,
+    pub status: SyncStatus,
+}
 
-    Ok(result)
+/// Whether the content asked by a pending item has been moved to the trash.
+fn is_pending_item_trashed(conn: &Connection, pi: &PendingItem) -> Result<bool> {
+    use schema::{q_asked_data, e_asked_data, c_asked_data, comparison_pairs, a_asked_data,
+                 d_asked_data, w_asked_data};
+
+    let (item_type, item_id): (&str, i32) = match pi.item_type.as_str() {
+        "question" => {
+            ("question",
+             q_asked_data::table.filter(q_asked_data::id.eq(pi.id))
+                 .select(q_asked_data::question_id)
+                 .get_result(&**conn)?)
+        }
+        "exercise" => {
+            ("exercise",
+             e_asked_data::table.filter(e_asked_data::id.eq(pi.id))
+                 .select(e_asked_data::exercise_id)
+                 .get_result(&**conn)?)
+        }
+        "comparison" => {
+            let (pair_id, word_a, word_b): (i32, i32, i32) =
+                c_asked_data::table.inner_join(comparison_pairs::table)
+                    .filter(c_asked_data::id.eq(pi.id))
+                    .select((comparison_pairs::id,
+                             comparison_pairs::word_a,
+                             comparison_pairs::word_b))
+                    .get_result(&**conn)?;
+            return Ok(trash::is_trashed(conn, "comparison", pair_id)? ||
+                      trash::is_trashed(conn, "word", word_a)? ||
+                      trash::is_trashed(conn, "word", word_b)?);
+        }
+        "accent" => {
+            ("word",
+             a_asked_data::table.filter(a_asked_data::id.eq(pi.id))
+                 .select(a_asked_data::word_id)
+                 .get_result(&**conn)?)
+        }
+        "dictation" => {
+            ("word",
+             d_asked_data::table.filter(d_asked_data::id.eq(pi.id))
+                 .select(d_asked_data::word_id)
+                 .get_result(&**conn)?)
+        }
+        "word" => {
+            ("word",
+             w_asked_data::table.filter(w_asked_data::id.eq(pi.id))
+                 .select(w_asked_data::word_id)
+                 .get_result(&**conn)?)
+        }
+        _ => bail!(ErrorKind::DatabaseOdd("Unknown pending item type!")),
+    };
+
+    trash::is_trashed(conn, item_type, item_id)
+}
+
+/// Logs answers that were collected without a connection, in order. Every answer is logged as if
+/// it was given at its client-side timestamp, clamped between the asking time and the present.
+/// Answers to items that are already answered, that don't exist (anymore) or that don't belong
+/// to the user aren't logged, but reported in the result. So aren't answers to content that
+/// has been moved to the trash.
+pub fn sync_answers(conn: &Connection,
+                    user_id: i32,
+                    answers: Vec<Answered>)
+                    -> Result<Vec<SyncResult>> {
+    use schema::{user_metrics, pending_items};
+    use diesel::result::Error::NotFound;
+
+    conn.transaction(|| {
+        let mut metrics: UserMetrics = user_metrics::table.filter(user_metrics::id.eq(user_id))
+            .get_result(&**conn)?;
+
+        let now = clock::now();
+        let mut results = Vec::with_capacity(answers.len());
+
+        for mut answer in answers {
+            let asked_id = answer.asked_id();
+
+            let pending_item: Option<PendingItem> =
+                pending_items::table.filter(pending_items::id.eq(asked_id))
+                    .filter(pending_items::user_id.eq(user_id))
+                    .filter(pending_items::test_item.eq(false))
+                    .filter(pending_items::practice.eq(false))
+                    .for_update()
+                    .get_result(&**conn)
+                    .optional()?;
+
+            let status = match pending_item {
+                None => SyncStatus::Missing,
+                Some(ref pi) if !pi.pending => SyncStatus::AlreadyAnswered,
+                Some(ref pi) if is_pending_item_trashed(conn, pi)? => SyncStatus::Deleted,
+                Some(ref pi) => {
+                    let answered_date = answer.answered_date().max(pi.asked_date).min(now);
+                    answer.set_answered_date(answered_date);
+
+                    let clock = Arc::new(clock::FakeClock::new(answered_date));
+                    let logged = clock::with_clock(clock, || {
+                        conn.transaction(|| {
+                            clear_limits(conn, &mut metrics)?;
+                            log_answer(conn, user_id, &answer, &mut metrics)
+                        })
+                    });
+
+                    match logged {
+                        Ok(()) => SyncStatus::Logged,
+                        Err(Error(ErrorKind::DieselError(NotFound), _)) => SyncStatus::Missing,
+                        Err(e) => return Err(e),
+                    }
+                }
+            };
+
+            results.push(SyncResult { asked_id, status });
+        }
+
+        let _: UserMetrics = metrics.save_changes(&**conn)?;
+
+        Ok(results)
+    })
+}
+
+pub fn clean_old_answer_submissions(conn: &Connection, how_old: chrono::Duration) -> Result<usize> {
+    use schema::answer_submissions;
+
+    let deleted_count = diesel::delete(answer_submissions::table
+            .filter(answer_submissions::submitted.lt(clock::now() - how_old)))
+        .execute(&**conn)?;
+
+    Ok(deleted_count)
+}
+
+
+
+/* PRACTICE */
+
+/// Chooses by random an item the user has already seen: a question, exercise, comparison,
+/// accent or dictation that has a due item, or a word. Optionally limited to a single skill nugget.
+fn choose_practice_item(conn: &Connection,
+                        user_id: i32,
+                        skill_nugget: Option<i32>)
+                        -> Result<Option<QuizType>> {
+    use schema::{due_items, question_data, quiz_questions, exercise_data, exercises,
+                 comparison_data, comparison_pairs, accent_data, dictation_data, pending_items,
+                 w_asked_data, words};
+
+    // The trashed content isn't practiced, and neither are the leeches
+    let trashed_words = trash::trashed_ids(conn, "word")?;
+
+    let mut questions = question_data::table.inner_join(due_items::table)
+        .inner_join(quiz_questions::table)
+        .filter(due_items::user_id.eq(user_id))
+        .filter(due_items::parked.eq(false))
+        .filter(due_items::suspended.eq(false))
+        .filter(quiz_questions::id.ne_all(trash::trashed_ids(conn, "question")?))
+        .select(question_data::question_id)
+        .into_boxed();
+
+    let mut exercises = exercise_data::table.inner_join(due_items::table)
+        .inner_join(exercises::table)
+        .filter(due_items::user_id.eq(user_id))
+        .filter(due_items::parked.eq(false))
+        .filter(due_items::suspended.eq(false))
+        .filter(exercises::id.ne_all(trash::trashed_ids(conn, "exercise")?))
+        .select(exercise_data::exercise_id)
+        .into_boxed();
+
+    let mut comparisons = comparison_data::table.inner_join(due_items::table)
+        .inner_join(comparison_pairs::table)
+        .filter(due_items::user_id.eq(user_id))
+        .filter(due_items::parked.eq(false))
+        .filter(due_items::suspended.eq(false))
+        .filter(comparison_pairs::id.ne_all(trash::trashed_ids(conn, "comparison")?))
+        .filter(comparison_pairs::word_a.ne_all(&trashed_words))
+        .filter(comparison_pairs::word_b.ne_all(&trashed_words))
+        .select(comparison_data::pair_id)
+        .into_boxed();
+
+    let mut accents = accent_data::table.inner_join(due_items::table)
+        .inner_join(words::table)
+        .filter(due_items::user_id.eq(user_id))
+        .filter(due_items::parked.eq(false))
+        .filter(due_items::suspended.eq(false))
+        .filter(words::id.ne_all(&trashed_words))
+        .select(accent_data::word_id)
+        .into_boxed();
+
+    let mut dictations = dictation_data::table.inner_join(due_items::table)
+        .inner_join(words::table)
+        .filter(due_items::user_id.eq(user_id))
+        .filter(due_items::parked.eq(false))
+        .filter(due_items::suspended.eq(false))
+        .filter(words::id.ne_all(&trashed_words))
+        .select(dictation_data::word_id)
+        .into_boxed();
+
+    let mut words = w_asked_data::table.inner_join(pending_items::table)
+        .inner_join(words::table)
+        .filter(pending_items::user_id.eq(user_id))
+        .filter(pending_items::test_item.eq(false))
+        .filter(pending_items::practice.eq(false))
+        .filter(words::id.ne_all(&trashed_words))
+        .select(w_asked_data::word_id)
+        .distinct()
+        .into_boxed();
+
+    if let Some(skill_nugget) = skill_nugget {
+        questions = questions.filter(quiz_questions::skill_id.eq(skill_nugget));
+        exercises = exercises.filter(exercises::skill_id.eq(skill_nugget));
+        comparisons = comparisons.filter(comparison_pairs::skill_id.eq(skill_nugget));
+        accents = accents.filter(words::skill_nugget.eq(skill_nugget));
+        dictations = dictations.filter(words::skill_nugget.eq(skill_nugget));
+        words = words.filter(words::skill_nugget.eq(skill_nugget));
+    }
+
+    let mut seen: Vec<QuizType> = questions.get_results(&**conn)?
+        .into_iter()
+        .map(QuizType::Question)
+        .collect();
+    seen.extend(exercises.get_results::<i32>(&**conn)?.into_iter().map(QuizType::Exercise));
+    seen.extend(comparisons.get_results::<i32>(&**conn)?.into_iter().map(QuizType::Comparison));
+    seen.extend(accents.get_results::<i32>(&**conn)?.into_iter().map(QuizType::Accent));
+    seen.extend(dictations.get_results::<i32>(&**conn)?.into_iter().map(QuizType::Dictation));
+    seen.extend(words.get_results::<i32>(&**conn)?.into_iter().map(QuizType::Word));
+
+    Ok(seen.choose(&mut thread_rng()).cloned())
+}
+
+/// The skill nugget of the question, exercise, comparison pair or word asked by a pending item.
+fn pending_item_skill(conn: &Connection, pi: &PendingItem) -> Result<i32> {
+    use schema::{q_asked_data, quiz_questions, e_asked_data, exercises, c_asked_data,
+                 comparison_pairs, a_asked_data, d_asked_data, w_asked_data, words};
+
+    Ok(match pi.item_type.as_str() {
+           "question" => {
+               q_asked_data::table.inner_join(quiz_questions::table)
+                   .filter(q_asked_data::id.eq(pi.id))
+                   .select(quiz_questions::skill_id)
+                   .get_result(&**conn)?
+           }
+           "exercise" => {
+               e_asked_data::table.inner_join(exercises::table)
+                   .filter(e_asked_data::id.eq(pi.id))
+                   .select(exercises::skill_id)
+                   .get_result(&**conn)?
+           }
+           "comparison" => {
+               c_asked_data::table.inner_join(comparison_pairs::table)
+                   .filter(c_asked_data::id.eq(pi.id))
+                   .select(comparison_pairs::skill_id)
+                   .get_result(&**conn)?
+           }
+           "accent" => {
+               a_asked_data::table.inner_join(words::table)
+                   .filter(a_asked_data::id.eq(pi.id))
+                   .select(words::skill_nugget)
+                   .get_result(&**conn)?
+           }
+           "dictation" => {
+               d_asked_data::table.inner_join(words::table)
+                   .filter(d_asked_data::id.eq(pi.id))
+                   .select(words::skill_nugget)
+                   .get_result(&**conn)?
+           }
+           "word" => {
+               w_asked_data::table.inner_join(words::table)
+                   .filter(w_asked_data::id.eq(pi.id))
+                   .select(words::skill_nugget)
+                   .get_result(&**conn)?
+           }
+           _ => bail!(ErrorKind::DatabaseOdd("Unknown item type in the pending items!")),
+       })
+}
+
+fn log_practice_answer(conn: &Connection, user_id: i32, answer_enum: &Answered) -> Result<()> {
+    use schema::{pending_items, q_answered_data, e_answered_data, w_answered_data, c_answered_data,
+                 a_answered_data, d_asked_data, d_answered_data};
+
+    let mut pending_item: PendingItem =
+        try_or!(pending_items::table.filter(pending_items::id.eq(answer_enum.asked_id()))
+                    .filter(pending_items::user_id.eq(user_id))
+                    .filter(pending_items::practice.eq(true))
+                    .for_update()
+                    .get_result(&**conn)
+                    .optional()?,
+                else bail!(ErrorKind::InvalidInput));
+
+    if !pending_item.pending {
+        info!("User is trying to answer twice to the same practice item! Ignoring the later answer.");
+        return Ok(());
+    }
+
+    pending_item.pending = false;
+    let _: PendingItem = pending_item.save_changes(&**conn)?;
+
+    match (answer_enum, pending_item.item_type.as_str()) {
+        (&Answered::W(ref a), "word") => {
+            let a = WAnsweredData { practice: true, ..a.clone() };
+            diesel::insert_into(w_answered_data::table).values(&a).execute(&**conn)?
+        }
+        (&Answered::Q(ref a), "question") => {
+            let a = QAnsweredData { practice: true, ..a.clone() };
+            diesel::insert_into(q_answered_data::table).values(&a).execute(&**conn)?
+        }
+        (&Answered::E(ref a), "exercise") => {
+            let a = EAnsweredData { practice: true, ..a.clone() };
+            diesel::insert_into(e_answered_data::table).values(&a).execute(&**conn)?
+        }
+        (&Answered::C(ref a), "comparison") => {
+            let a = CAnsweredData { practice: true, ..a.clone() };
+            diesel::insert_into(c_answered_data::table).values(&a).execute(&**conn)?
+        }
+        (&Answered::A(ref a), "accent") => {
+            let a = AAnsweredData { practice: true, ..a.clone() };
+            diesel::insert_into(a_answered_data::table).values(&a).execute(&**conn)?
+        }
+        (&Answered::D(ref a), "dictation") => {
+            let asked: DAskedData = d_asked_data::table.filter(d_asked_data::id.eq(a.id))
+                .get_result(&**conn)?;
+            let a = DAnsweredData {
+                answer_level: dictation::grade(&asked.expected_kana, &a.answered_text),
+                practice: true,
+                ..a.clone()
+            };
+            diesel::insert_into(d_answered_data::table).values(&a).execute(&**conn)?
+        }
+        _ => bail!(ErrorKind::InvalidInput),
+    };
+
+    Ok(())
+}
+
+/// Cram mode: returns a quiz about something the user has already seen, for drilling.
+/// Practice items are kept apart from the spaced repetition: answering them doesn't affect
+/// the due items, the user metrics or the skill levels, and they don't count towards
+/// the breaks.
+pub fn get_cram_quiz(conn: &Connection,
+                     user_id: i32,
+                     skill_nugget: Option<i32>)
+                     -> Result<Option<Quiz>> {
+    use schema::pending_items;
+
+    conn.transaction(|| {
+        let pending_item: Option<PendingItem> =
+            pending_items::table.filter(pending_items::user_id.eq(user_id))
+                .filter(pending_items::pending.eq(true))
+                .filter(pending_items::practice.eq(true))
+                .get_result(&**conn)
+                .optional()?;
+
+        if let Some(mut pi) = pending_item {
+            let same_skill = match skill_nugget {
+                Some(skill_nugget) => pending_item_skill(conn, &pi)? == skill_nugget,
+                None => true,
+            };
+            if same_skill {
+                debug!("There was a pending practice item! Returning it.");
+                return Ok(Some(penditem_to_quiz(conn, &pi)?));
+            }
+            // The item is about another skill; practice items can be dropped unanswered.
+            pi.pending = false;
+            let _: PendingItem = pi.save_changes(&**conn)?;
+        }
+
+        match choose_practice_item(conn, user_id, skill_nugget)? {
+            Some(QuizType::Word(word_id)) => {
+                let word = try_or!(load_word(conn, word_id)?,
+                        else bail!(ErrorKind::DatabaseOdd("The word was seen, so it should exist!")));
+                ask_word(conn, user_id, word, true)
+            }
+            Some(quiztype) => ask_q_or_e(conn, user_id, quiztype, true),
+            None => Ok(None),
+        }
+    })
+}
+
+pub fn get_next_cram_quiz(conn: &Connection,
+                          user_id: i32,
+                          answer_enum: Answered,
+                          skill_nugget: Option<i32>)
+                          -> Result<Option<Quiz>> {
+    conn.transaction(|| {
+        log_practice_answer(conn, user_id, &answer_enum)?;
+        get_cram_quiz(conn, user_id, skill_nugget)
+    })
+}
+
+#[test]
+fn test_grade_recall() {
+    let metrics = user::test_metrics(clock::now());
+
+    assert_eq!(grade_recall(false, 100, Some(2000), Some(1), &metrics), Recall::Again);
+    assert_eq!(grade_recall(true, 2000, Some(2000), Some(1), &metrics), Recall::Good);
+    assert_eq!(grade_recall(true, 5000, Some(2000), Some(1), &metrics), Recall::Hard);
+    assert_eq!(grade_recall(true, 2000, Some(2000), Some(5), &metrics), Recall::Hard);
+    assert_eq!(grade_recall(true, 500, Some(2000), Some(1), &metrics), Recall::Easy);
+    assert_eq!(grade_recall(true, 500, Some(2000), Some(2), &metrics), Recall::Good);
+    assert_eq!(grade_recall(true, 500, None, None, &metrics), Recall::Good);
+}
+
+#[cfg(test)]
+fn test_due_item(now: DateTime<Utc>) -> DueItem {
+    DueItem {
+        id: 1,
+        user_id: 1,
+        due_date: now,
+        due_delay: 1000,
+        cooldown_delay: now,
+        correct_streak_overall: 0,
+        correct_streak_this_time: 0,
+        item_type: "question".to_owned(),
+        ease_factor: 2.5,
+        difficulty: 0.0,
+        stability: 0.0,
+        lapses: 0,
+        suspended: false,
+        parked: false,
+        lapsed_date: None,
+    }
+}
+
+#[test]
+fn test_schedule_recall() {
+    let now = clock::now();
+    let metrics = user::test_metrics(now);
+
+    let mut due_item = test_due_item(now);
+    assert!(schedule_answer(&MultiplierScheduler, &mut due_item, Recall::Hard, &metrics, now));
+    assert_eq!(due_item.due_delay, 1000);
+    assert!(schedule_answer(&MultiplierScheduler, &mut due_item, Recall::Easy, &metrics, now));
+    assert_eq!(due_item.due_delay, 2600);
+
+    // The other schedulers grade the recall themselves; an easier recall means a longer delay
+    let reviewed_delay = |scheduler: &dyn Scheduler, recall| {
+        let mut due_item = DueItem {
+            due_delay: days_to_delay(10.0),
+            difficulty: 5.0,
+            stability: 10.0,
+            ..test_due_item(now)
+        };
+        assert!(schedule_answer(scheduler, &mut due_item, recall, &metrics, now));
+        due_item.due_delay
+    };
+    for scheduler in &[&Sm2Scheduler as &dyn Scheduler, &FsrsScheduler] {
+        let hard = reviewed_delay(*scheduler, Recall::Hard);
+        let good = reviewed_delay(*scheduler, Recall::Good);
+        let easy = reviewed_delay(*scheduler, Recall::Easy);
+        assert!(hard < good && good < easy, "{}: {} {} {}", scheduler.name(), hard, good, easy);
+    }
+}
+
+#[test]
+fn test_lapses() {
+    let now = clock::now();
+    let metrics = user::test_metrics(now);
+
+    let mut due_item = test_due_item(now);
+    assert!(schedule_answer(&MultiplierScheduler, &mut due_item, Recall::Good, &metrics, now));
+
+    // Only the first miss after a review is a lapse
+    assert!(!schedule_answer(&MultiplierScheduler, &mut due_item, Recall::Again, &metrics, now));
+    assert!(!schedule_answer(&MultiplierScheduler, &mut due_item, Recall::Again, &metrics, now));
+    assert_eq!((due_item.lapses, due_item.correct_streak_overall), (1, 0));
+
+    // An item that is never answered right lapses once a day, and eventually becomes a leech
+    let mut due_item = test_due_item(now);
+    for day in 0..metrics.leech_threshold {
+        let now = now + chrono::Duration::days(day as i64);
+        assert!(!schedule_answer(&MultiplierScheduler, &mut due_item, Recall::Again, &metrics, now));
+        assert!(!schedule_answer(&MultiplierScheduler, &mut due_item, Recall::Again, &metrics, now));
+        assert_eq!(due_item.lapses, day + 1);
+    }
+    assert!(due_item.suspended);
 }
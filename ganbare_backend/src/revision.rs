@@ -0,0 +1,305 @@
+//! The history of the content edits done through the manager API.
+//!
+//! Every edit records JSON snapshots of the item before and after the edit, and the editor.
+//! Reverting a revision restores the item to the snapshot taken before the revision.
+//! The revert is recorded as a revision too, so it can be reverted in turn.
+//!
+//! Moving an item to the trash and restoring it are recorded as well; reverting them
+//! restores the item from the trash or trashes it again. Purging an item from the trash is
+//! recorded as a deletion without an editor.
+
+use super::*;
+use serde::{Serialize, Deserialize};
+use serde::de::DeserializeOwned;
+use error_chain::bail;
+
+pub const ITEM_TYPES: &[&str] = &["word", "question", "answer", "exercise", "variant",
+                                  "comparison"];
+
+/// The answers are deleted along with their question, so they are a part of its snapshot.
+#[derive(Serialize, Deserialize, Debug)]
+struct QuestionSnapshot {
+    question: QuizQuestion,
+    answers: Vec<Answer>,
+}
+
+/// The variants are deleted along with their exercise, so they are a part of its snapshot.
+#[derive(Serialize, Deserialize, Debug)]
+struct ExerciseSnapshot {
+    exercise: Exercise,
+    variants: Vec<ExerciseVariant>,
+}
+
+fn to_json<T: Serialize>(item: Option<T>) -> Result<Option<String>> {
+    item.map(|i| serde_json::to_string(&i))
+        .transpose()
+        .chain_err(|| "Couldn't serialize the snapshot!")
+}
+
+fn from_json<T: DeserializeOwned>(json: &str) -> Result<T> {
+    serde_json::from_str(json).chain_err(|| ErrorKind::DatabaseOdd("Malformed revision snapshot!"))
+}
+
+/// The current state of an item as JSON, or `None` if it doesn't exist.
+fn snapshot(conn: &Connection, item_type: &str, id: i32) -> Result<Option<String>> {
+    use schema::{words, quiz_questions, question_answers, exercises, exercise_variants,
+                 comparison_pairs};
+
+    match item_type {
+        "word" => {
+            let word: Option<Word> = words::table.filter(words::id.eq(id))
+                .get_result(&**conn)
+                .optional()?;
+            to_json(word)
+        }
+        "question" => {
+            let question: Option<QuizQuestion> =
+                quiz_questions::table.filter(quiz_questions::id.eq(id))
+                    .get_result(&**conn)
+                    .optional()?;
+            let question = try_or!(question, else return Ok(None));
+            let answers: Vec<Answer> =
+                question_answers::table.filter(question_answers::question_id.eq(id))
+                    .order(question_answers::id)
+                    .get_results(&**conn)?;
+            to_json(Some(QuestionSnapshot { question, answers }))
+        }
+        "answer" => {
+            let answer: Option<Answer> = question_answers::table.filter(question_answers::id.eq(id))
+                .get_result(&**conn)
+                .optional()?;
+            to_json(answer)
+        }
+        "exercise" => {
+            let exercise: Option<Exercise> = exercises::table.filter(exercises::id.eq(id))
+                .get_result(&**conn)
+                .optional()?;
+            let exercise = try_or!(exercise, else return Ok(None));
+            let variants: Vec<ExerciseVariant> =
+                exercise_variants::table.filter(exercise_variants::exercise_id.eq(id))
+                    .order(exercise_variants::id)
+                    .get_results(&**conn)?;
+            to_json(Some(ExerciseSnapshot { exercise, variants }))
+        }
+        "variant" => {
+            let variant: Option<ExerciseVariant> =
+                exercise_variants::table.filter(exercise_variants::id.eq(id))
+                    .get_result(&**conn)
+                    .optional()?;
+            to_json(variant)
+        }
+        "comparison" => {
+            let pair: Option<ComparisonPair> =
+                comparison_pairs::table.filter(comparison_pairs::id.eq(id))
+                    .get_result(&**conn)
+                    .optional()?;
+            to_json(pair)
+        }
+        _ => bail!(ErrorKind::InvalidInput),
+    }
+}
+
+/// Sets an item to the state of a snapshot, inserting it with its old id if it was deleted.
+fn restore(conn: &Connection, item_type: &str, json: &str) -> Result<()> {
+
+    macro_rules! upsert {
+        ($table:ident, $item:expr) => {
+            let updated = diesel::update(schema::$table::table
+                                             .filter(schema::$table::id.eq($item.id)))
+                .set(&$item)
+                .execute(&**conn)?;
+            if updated == 0 {
+                diesel::insert_into(schema::$table::table).values(&$item).execute(&**conn)?;
+            }
+        }
+    }
+
+    match item_type {
+        "word" => {
+            let word: Word = from_json(json)?;
+            upsert!(words, word);
+        }
+        "question" => {
+            let snapshot: QuestionSnapshot = from_json(json)?;
+            upsert!(quiz_questions, snapshot.question);
+            for answer in snapshot.answers {
+                upsert!(question_answers, answer);
+            }
+        }
+        "answer" => {
+            let answer: Answer = from_json(json)?;
+            upsert!(question_answers, answer);
+        }
+        "exercise" => {
+            let snapshot: ExerciseSnapshot = from_json(json)?;
+            upsert!(exercises, snapshot.exercise);
+            for variant in snapshot.variants {
+                upsert!(exercise_variants, variant);
+            }
+        }
+        "variant" => {
+            let variant: ExerciseVariant = from_json(json)?;
+            upsert!(exercise_variants, variant);
+        }
+        "comparison" => {
+            let pair: ComparisonPair = from_json(json)?;
+            upsert!(comparison_pairs, pair);
+        }
+        _ => bail!(ErrorKind::InvalidInput),
+    }
+    Ok(())
+}
+
+fn record(conn: &Connection,
+          editor_id: Option<i32>,
+          item_type: &str,
+          item_id: i32,
+          action: &str,
+          before: Option<&str>,
+          after: Option<&str>)
+          -> Result<Revision> {
+    use schema::revisions;
+
+    Ok(diesel::insert_into(revisions::table).values(&NewRevision {
+                                                         item_type,
+                                                         item_id,
+                                                         editor_id,
+                                                         action,
+                                                         before,
+                                                         after,
+                                                     })
+           .get_result(&**conn)?)
+}
+
+/// Runs `mutation`, which edits or deletes an item, and records the change as a revision
+/// by `editor_id`. Nothing is recorded if the item didn't exist or didn't change.
+pub fn track<T, F>(conn: &Connection,
+                   editor_id: i32,
+                   item_type: &str,
+                   item_id: i32,
+                   mutation: F)
+                   -> Result<T>
+    where F: FnOnce() -> Result<T>
+{
+    track_by(conn, Some(editor_id), item_type, item_id, mutation)
+}
+
+/// Like `track`, but for the changes that no editor makes, like purging the trash.
+pub fn track_unattended<T, F>(conn: &Connection,
+                              item_type: &str,
+                              item_id: i32,
+                              mutation: F)
+                              -> Result<T>
+    where F: FnOnce() -> Result<T>
+{
+    track_by(conn, None, item_type, item_id, mutation)
+}
+
+fn track_by<T, F>(conn: &Connection,
+                  editor_id: Option<i32>,
+                  item_type: &str,
+                  item_id: i32,
+                  mutation: F)
+                  -> Result<T>
+    where F: FnOnce() -> Result<T>
+{
+    conn.transaction(|| {
+        let before = snapshot(conn, item_type, item_id)?;
+        let result = mutation()?;
+        let after = snapshot(conn, item_type, item_id)?;
+
+        if let Some(ref before) = before {
+            if after.as_ref() != Some(before) {
+                let action = if after.is_some() { "update" } else { "delete" };
+                record(conn,
+                       editor_id,
+                       item_type,
+                       item_id,
+                       action,
+                       Some(before),
+                       after.as_ref().map(String::as_str))?;
+            }
+        }
+        Ok(result)
+    })
+}
+
+/// Records that `editor_id` moved an item to the trash, or restored it if `trashed` is false.
+/// The trash has item types that have no history; nothing is recorded for them.
+pub fn record_trash(conn: &Connection,
+                    editor_id: i32,
+                    item_type: &str,
+                    item_id: i32,
+                    trashed: bool)
+                    -> Result<()> {
+    if !ITEM_TYPES.contains(&item_type) {
+        return Ok(());
+    }
+
+    let current = snapshot(conn, item_type, item_id)?;
+    let current = current.as_ref().map(String::as_str);
+    if trashed {
+        record(conn, Some(editor_id), item_type, item_id, "trash", current, None)?;
+    } else {
+        record(conn, Some(editor_id), item_type, item_id, "restore", None, current)?;
+    }
+    Ok(())
+}
+
+/// The revisions of an item, the latest first.
+pub fn get_history(conn: &Connection, item_type: &str, item_id: i32) -> Result<Vec<Revision>> {
+    use schema::revisions;
+
+    if !ITEM_TYPES.contains(&item_type) {
+        bail!(ErrorKind::InvalidInput);
+    }
+
+    Ok(revisions::table.filter(revisions::item_type.eq(item_type))
+           .filter(revisions::item_id.eq(item_id))
+           .order(revisions::id.desc())
+           .get_results(&**conn)?)
+}
+
+/// Restores the item of a revision to what it was before the revision.
+/// Returns the revision that records the revert, or `None` if there is no such revision
+/// or if the revision has nothing to restore.
+pub fn revert(conn: &Connection, editor_id: i32, revision_id: i32) -> Result<Option<Revision>> {
+    use schema::revisions;
+
+    conn.transaction(|| {
+        let revision: Option<Revision> = revisions::table.filter(revisions::id.eq(revision_id))
+            .get_result(&**conn)
+            .optional()?;
+        let revision = try_or!(revision, else return Ok(None));
+
+        // Moving to the trash is reverted by restoring from the trash and vice versa;
+        // those record revisions of their own.
+        let untrashed = match revision.action.as_str() {
+            "trash" => trash::restore(conn, &revision.item_type, revision.item_id, editor_id)?,
+            "restore" => trash::trash(conn, &revision.item_type, revision.item_id, editor_id)?,
+            _ => false,
+        };
+        if untrashed {
+            let latest: Revision = revisions::table.filter(revisions::item_type
+                                                               .eq(&revision.item_type))
+                .filter(revisions::item_id.eq(revision.item_id))
+                .order(revisions::id.desc())
+                .first(&**conn)?;
+            return Ok(Some(latest));
+        }
+
+        let target = try_or!(revision.before, else return Ok(None));
+
+        let current = snapshot(conn, &revision.item_type, revision.item_id)?;
+        restore(conn, &revision.item_type, &target)?;
+        let restored = snapshot(conn, &revision.item_type, revision.item_id)?;
+
+        Ok(Some(record(conn,
+                       Some(editor_id),
+                       &revision.item_type,
+                       revision.item_id,
+                       "revert",
+                       current.as_ref().map(String::as_str),
+                       restored.as_ref().map(String::as_str))?))
+    })
+}
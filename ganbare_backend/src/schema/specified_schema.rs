@@ -1,3 +1,39 @@
+table! {
+    a_answered_data (id) {
+        id -> Int4,
+        answered_nucleus -> Nullable<Int4>,
+        answered_date -> Timestamptz,
+        active_answer_time_ms -> Int4,
+        full_answer_time_ms -> Int4,
+        full_spent_time_ms -> Int4,
+        practice -> Bool,
+    }
+}
+
+table! {
+    a_asked_data (id) {
+        id -> Int4,
+        word_id -> Int4,
+        right_nucleus -> Nullable<Int4>,
+    }
+}
+
+table! {
+    accent_data (due, word_id) {
+        word_id -> Int4,
+        due -> Int4,
+    }
+}
+
+table! {
+    answer_submissions (user_id, idempotency_key) {
+        user_id -> Int4,
+        idempotency_key -> Varchar,
+        next_pending_item -> Nullable<Int4>,
+        submitted -> Timestamptz,
+    }
+}
+
 table! {
     anon_aliases (id) {
         id -> Int4,
@@ -14,6 +50,14 @@ table! {
     }
 }
 
+table! {
+    audio_pitch (audio_file_id) {
+        audio_file_id -> Int4,
+        contour -> Text,
+        extracted -> Timestamptz,
+    }
+}
+
 table! {
     audio_files (id) {
         id -> Int4,
@@ -25,6 +69,76 @@ table! {
     }
 }
 
+table! {
+    c_answered_data (id) {
+        id -> Int4,
+        answered_choice -> Nullable<Int4>,
+        answered_date -> Timestamptz,
+        active_answer_time_ms -> Int4,
+        full_answer_time_ms -> Int4,
+        full_spent_time_ms -> Int4,
+        practice -> Bool,
+    }
+}
+
+table! {
+    c_asked_data (id) {
+        id -> Int4,
+        pair_id -> Int4,
+        word_id -> Int4,
+        first_audio_id -> Int4,
+        second_audio_id -> Int4,
+        right_choice -> Int4,
+    }
+}
+
+table! {
+    comparison_data (due, pair_id) {
+        pair_id -> Int4,
+        due -> Int4,
+    }
+}
+
+table! {
+    comparison_pairs (id) {
+        id -> Int4,
+        skill_id -> Int4,
+        word_a -> Int4,
+        word_b -> Int4,
+        published -> Bool,
+        skill_level -> Int4,
+    }
+}
+
+table! {
+    d_answered_data (id) {
+        id -> Int4,
+        answered_text -> Varchar,
+        answer_level -> Int4,
+        answered_date -> Timestamptz,
+        active_answer_time_ms -> Int4,
+        full_answer_time_ms -> Int4,
+        full_spent_time_ms -> Int4,
+        audio_times -> Int4,
+        practice -> Bool,
+    }
+}
+
+table! {
+    d_asked_data (id) {
+        id -> Int4,
+        word_id -> Int4,
+        expected_kana -> Varchar,
+    }
+}
+
+table! {
+    dictation_data (due, word_id) {
+        word_id -> Int4,
+        due -> Int4,
+    }
+}
+
 table! {
     disable_nag_codes (user_id) {
         user_id -> Int4,
@@ -43,6 +157,13 @@ table! {
         correct_streak_overall -> Int4,
         correct_streak_this_time -> Int4,
         item_type -> Varchar,
+        ease_factor -> Float8,
+        difficulty -> Float8,
+        stability -> Float8,
+        lapses -> Int4,
+        suspended -> Bool,
+        parked -> Bool,
+        lapsed_date -> Nullable<Timestamptz>,
     }
 }
 
@@ -56,6 +177,7 @@ table! {
         answer_level -> Nullable<Int4>,
         full_spent_time_ms -> Int4,
         reflected_time_ms -> Int4,
+        practice -> Bool,
     }
 }
 
@@ -164,6 +286,27 @@ table! {
         pending -> Bool,
         item_type -> Varchar,
         test_item -> Bool,
+        practice -> Bool,
+    }
+}
+
+table! {
+    pronunciation_scores (id) {
+        id -> Int4,
+        file_name -> Varchar,
+        score -> Nullable<Float4>,
+        scored -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    publish_schedules (id) {
+        id -> Int4,
+        item_type -> Varchar,
+        item_id -> Int4,
+        publish_at -> Nullable<Timestamptz>,
+        unpublish_at -> Nullable<Timestamptz>,
+        group_id -> Nullable<Int4>,
     }
 }
 
@@ -175,6 +318,7 @@ table! {
         active_answer_time_ms -> Int4,
         full_answer_time_ms -> Int4,
         full_spent_time_ms -> Int4,
+        practice -> Bool,
     }
 }
 
@@ -226,6 +370,46 @@ table! {
     }
 }
 
+table! {
+    review_log (id) {
+        id -> Int4,
+        user_id -> Int4,
+        asked_id -> Int4,
+        item_type -> Varchar,
+        item_id -> Int4,
+        due_item_id -> Nullable<Int4>,
+        answered_date -> Timestamptz,
+        correct -> Bool,
+        answer_level -> Nullable<Int4>,
+        active_answer_time_ms -> Int4,
+        full_answer_time_ms -> Nullable<Int4>,
+        full_spent_time_ms -> Int4,
+        prev_due_delay -> Nullable<Int4>,
+        new_due_delay -> Nullable<Int4>,
+        prev_correct_streak_this_time -> Nullable<Int4>,
+        new_correct_streak_this_time -> Nullable<Int4>,
+        prev_correct_streak_overall -> Nullable<Int4>,
+        new_correct_streak_overall -> Nullable<Int4>,
+        new_due_date -> Nullable<Timestamptz>,
+        scheduler -> Nullable<Varchar>,
+        reconstructed -> Bool,
+        recall -> Nullable<Varchar>,
+    }
+}
+
+table! {
+    revisions (id) {
+        id -> Int4,
+        item_type -> Varchar,
+        item_id -> Int4,
+        editor_id -> Nullable<Int4>,
+        action -> Varchar,
+        before -> Nullable<Varchar>,
+        after -> Nullable<Varchar>,
+        edited -> Timestamptz,
+    }
+}
+
 table! {
     sessions (id) {
         id -> Int4,
@@ -245,6 +429,14 @@ table! {
     }
 }
 
+table! {
+    skill_prerequisites (skill_id, prerequisite_id) {
+        skill_id -> Int4,
+        prerequisite_id -> Int4,
+        required_level -> Int4,
+    }
+}
+
 table! {
     skill_nuggets (id) {
         id -> Int4,
@@ -252,6 +444,26 @@ table! {
     }
 }
 
+table! {
+    trash (id) {
+        id -> Int4,
+        item_type -> Varchar,
+        item_id -> Int4,
+        trashed_by -> Nullable<Int4>,
+        trashed -> Timestamptz,
+        cascaded_from -> Nullable<Int4>,
+    }
+}
+
+table! {
+    user_audio_pitch (user_id, file_name) {
+        user_id -> Int4,
+        file_name -> Varchar,
+        contour -> Text,
+        extracted -> Timestamptz,
+    }
+}
+
 table! {
     user_groups (id) {
         id -> Int4,
@@ -279,6 +491,14 @@ table! {
         streak_limit -> Int4,
         cooldown_delay -> Int4,
         streak_skill_bump_criteria -> Int4,
+        timezone -> Varchar,
+        day_rollover_hour -> Int4,
+        leech_threshold -> Int4,
+        slow_answer_percent -> Int4,
+        fast_answer_percent -> Int4,
+        max_good_audio_times -> Int4,
+        hard_interval_percent -> Int4,
+        easy_interval_percent -> Int4,
     }
 }
 
@@ -311,6 +531,7 @@ table! {
         audio_times -> Int4,
         checked_date -> Timestamptz,
         active_answer_time_ms -> Int4,
+        practice -> Bool,
     }
 }
 
@@ -322,6 +543,25 @@ table! {
     }
 }
 
+table! {
+    word_list_entries (id) {
+        id -> Int4,
+        list_id -> Int4,
+        word -> Varchar,
+        reading -> Varchar,
+        rank -> Int4,
+    }
+}
+
+table! {
+    word_lists (id) {
+        id -> Int4,
+        list_name -> Varchar,
+        list_type -> Varchar,
+        imported -> Timestamptz,
+    }
+}
+
 table! {
     words (id) {
         id -> Int4,
@@ -332,14 +572,35 @@ table! {
         published -> Bool,
         skill_level -> Int4,
         priority -> Int4,
+        reading -> Varchar,
+        romaji -> Varchar,
     }
 }
 
+joinable!(a_answered_data -> a_asked_data (id));
+joinable!(a_asked_data -> pending_items (id));
+joinable!(a_asked_data -> words (word_id));
+joinable!(accent_data -> due_items (due));
+joinable!(accent_data -> words (word_id));
 joinable!(anon_aliases -> user_groups (group_id));
 joinable!(anon_aliases -> users (user_id));
 joinable!(audio_files -> audio_bundles (bundle_id));
 joinable!(audio_files -> narrators (narrators_id));
+joinable!(audio_pitch -> audio_files (audio_file_id));
+joinable!(c_answered_data -> c_asked_data (id));
+joinable!(c_asked_data -> comparison_pairs (pair_id));
+joinable!(c_asked_data -> pending_items (id));
+joinable!(c_asked_data -> words (word_id));
+joinable!(comparison_data -> comparison_pairs (pair_id));
+joinable!(comparison_data -> due_items (due));
+joinable!(comparison_pairs -> skill_nuggets (skill_id));
+joinable!(d_answered_data -> d_asked_data (id));
+joinable!(d_asked_data -> pending_items (id));
+joinable!(d_asked_data -> words (word_id));
+joinable!(dictation_data -> due_items (due));
+joinable!(dictation_data -> words (word_id));
 joinable!(disable_nag_codes -> users (user_id));
+joinable!(answer_submissions -> users (user_id));
 joinable!(due_items -> users (user_id));
 joinable!(e_answered_data -> e_asked_data (id));
 joinable!(e_asked_data -> exercise_variants (word_id));
@@ -360,6 +621,8 @@ joinable!(group_memberships -> users (user_id));
 joinable!(passwords -> users (id));
 joinable!(pending_items -> audio_files (audio_file_id));
 joinable!(pending_items -> users (user_id));
+joinable!(pronunciation_scores -> e_asked_data (id));
+joinable!(publish_schedules -> user_groups (group_id));
 joinable!(q_answered_data -> q_asked_data (id));
 joinable!(q_answered_data -> question_answers (answered_qa_id));
 joinable!(q_asked_data -> pending_items (id));
@@ -370,21 +633,39 @@ joinable!(question_data -> due_items (due));
 joinable!(question_data -> quiz_questions (question_id));
 joinable!(quiz_questions -> skill_nuggets (skill_id));
 joinable!(reset_email_secrets -> users (user_id));
+joinable!(review_log -> users (user_id));
+joinable!(revisions -> users (editor_id));
 joinable!(sessions -> users (user_id));
 joinable!(skill_data -> skill_nuggets (skill_nugget));
 joinable!(skill_data -> users (user_id));
+joinable!(skill_prerequisites -> skill_nuggets (skill_id));
+joinable!(trash -> users (trashed_by));
+joinable!(user_audio_pitch -> users (user_id));
 joinable!(user_metrics -> users (id));
 joinable!(user_stats -> users (id));
 joinable!(w_answered_data -> w_asked_data (id));
 joinable!(w_asked_data -> pending_items (id));
 joinable!(w_asked_data -> words (word_id));
+joinable!(word_list_entries -> word_lists (list_id));
 joinable!(words -> audio_bundles (audio_bundle));
 joinable!(words -> skill_nuggets (skill_nugget));
 
 allow_tables_to_appear_in_same_query!(
+    a_answered_data,
+    a_asked_data,
+    accent_data,
     anon_aliases,
+    answer_submissions,
     audio_bundles,
+    audio_pitch,
     audio_files,
+    c_answered_data,
+    c_asked_data,
+    comparison_data,
+    comparison_pairs,
+    d_answered_data,
+    d_asked_data,
+    dictation_data,
     disable_nag_codes,
     due_items,
     e_answered_data,
@@ -400,20 +681,29 @@ allow_tables_to_appear_in_same_query!(
     passwords,
     pending_email_confirms,
     pending_items,
+    pronunciation_scores,
+    publish_schedules,
     q_answered_data,
     q_asked_data,
     question_answers,
     question_data,
     quiz_questions,
     reset_email_secrets,
+    review_log,
+    revisions,
     sessions,
     skill_data,
     skill_nuggets,
+    skill_prerequisites,
+    trash,
+    user_audio_pitch,
     user_groups,
     user_metrics,
     user_stats,
     users,
     w_answered_data,
     w_asked_data,
+    word_list_entries,
+    word_lists,
     words,
 );
\ No newline at end of file
@@ -0,0 +1,109 @@
+//! Searching words, questions and skill nuggets.
+//!
+//! The query is normalized (NFKC, lowercase) before matching, and it's also converted to
+//! hiragana (from katakana or romaji) and to romaji, so that a word can be found with any of
+//! the three scripts, regardless of the width of the characters or the accent marks.
+
+use super::*;
+use unicode_normalization::UnicodeNormalization;
+use serde::Serialize;
+
+/// The maximum number of results of each kind.
+const SEARCH_LIMIT: i64 = 50;
+
+#[derive(Serialize, Debug, Default)]
+pub struct SearchResults {
+    pub words: Vec<Word>,
+    pub questions: Vec<QuizQuestion>,
+    pub skills: Vec<SkillNugget>,
+}
+
+/// A pattern for `LIKE` that matches anything containing the text.
+fn like_pattern(text: &str) -> String {
+    let mut pattern = String::from("%");
+    for c in text.chars() {
+        if c == '%' || c == '_' || c == '\\' {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+/// The normalized query, and its hiragana and romaji forms.
+fn query_forms(query: &str) -> (String, String, String) {
+    let query = query.nfkc().collect::<String>().trim().to_lowercase();
+    let hiragana = if query.chars().any(|c| c.is_ascii_alphabetic()) {
+        kana::from_romaji(&query).unwrap_or_else(|| query.clone())
+    } else {
+        kana::reading(&query)
+    };
+    let romaji = kana::to_romaji(&hiragana);
+    (query, hiragana, romaji)
+}
+
+/// Searches the words, questions and skill nuggets that contain the query.
+/// Unpublished words and questions are included only if `include_unpublished` is set.
+pub fn search(conn: &Connection, query: &str, include_unpublished: bool) -> Result<SearchResults> {
+    use schema::{words, quiz_questions, skill_nuggets};
+
+    let (query, hiragana, romaji) = query_forms(query);
+    if query.is_empty() {
+        return Ok(SearchResults::default());
+    }
+    let query = like_pattern(&query);
+    let hiragana = like_pattern(&hiragana);
+    let romaji = like_pattern(&romaji);
+
+    let mut words_query = words::table.filter(words::word.ilike(&query)
+                                                  .or(words::explanation.ilike(&query))
+                                                  .or(words::reading.like(&hiragana))
+                                                  .or(words::romaji.like(&romaji)))
+        .filter(words::id.ne_all(trash::trashed_ids(conn, "word")?))
+        .order(words::id.asc())
+        .limit(SEARCH_LIMIT)
+        .into_boxed();
+    if !include_unpublished {
+        words_query = words_query.filter(words::published.eq(true));
+    }
+    let words: Vec<Word> = words_query.get_results(&**conn)?;
+
+    let mut questions_query =
+        quiz_questions::table.filter(quiz_questions::q_name.ilike(&query)
+                                         .or(quiz_questions::q_name.like(&hiragana))
+                                         .or(quiz_questions::question_text.ilike(&query))
+                                         .or(quiz_questions::question_text.like(&hiragana))
+                                         .or(quiz_questions::q_explanation.ilike(&query)))
+            .filter(quiz_questions::id.ne_all(trash::trashed_ids(conn, "question")?))
+            .order(quiz_questions::id.asc())
+            .limit(SEARCH_LIMIT)
+            .into_boxed();
+    if !include_unpublished {
+        questions_query = questions_query.filter(quiz_questions::published.eq(true));
+    }
+    let questions: Vec<QuizQuestion> = questions_query.get_results(&**conn)?;
+
+    let skills: Vec<SkillNugget> =
+        skill_nuggets::table.filter(skill_nuggets::skill_summary.ilike(&query)
+                                        .or(skill_nuggets::skill_summary.like(&hiragana)))
+            .order(skill_nuggets::id.asc())
+            .limit(SEARCH_LIMIT)
+            .get_results(&**conn)?;
+
+    Ok(SearchResults {
+           words,
+           questions,
+           skills,
+       })
+}
+
+#[test]
+fn test_query_forms() {
+    assert_eq!(query_forms(" Kyōto "),
+               ("kyōto".to_owned(), "きょうと".to_owned(), "kyouto".to_owned()));
+    assert_eq!(query_forms("ｷｮｳﾄ"),
+               ("キョウト".to_owned(), "きょうと".to_owned(), "kyouto".to_owned()));
+    assert_eq!(query_forms("きょ・うと").1, "きょうと");
+    assert_eq!(like_pattern("100%"), "%100\\%%");
+}
@@ -98,7 +98,7 @@ pub fn clean_old_sessions(conn: &Connection, how_old: chrono::Duration) -> Resul
     use schema::sessions;
 
     let deleted_count =
-        diesel::delete(sessions::table.filter(sessions::last_seen.lt(chrono::offset::Utc::now() -
+        diesel::delete(sessions::table.filter(sessions::last_seen.lt(clock::now() -
                                                                      how_old))).execute(&**conn)?;
 
     Ok(deleted_count)
@@ -145,7 +145,7 @@ pub fn check_integrity(sess_id_str: &str,
 use helpers::Cache;
 
 pub fn check(sess: &UserSession, logout_cache: &Cache<i32, UserSession>) -> Result<bool> {
-    if sess.refreshed > chrono::offset::Utc::now() - chrono::Duration::minutes(5) {
+    if sess.refreshed > clock::now() - chrono::Duration::minutes(5) {
         if logout_cache.get(&sess.sess_id)?.is_some() {
             Ok(false) // User was recently logged out so don't trust their cookie!
         } else {
@@ -173,12 +173,12 @@ pub fn db_check(conn: &Connection,
 
     time_it!{"session::db_check", {
 
-        let oldest_viable = chrono::offset::Utc::now() - sess_expire;
+        let oldest_viable = clock::now() - sess_expire;
         if sess.refreshed < oldest_viable {
             return Ok(None); // The session is expired
         }
         
-        let session_refreshed = chrono::offset::Utc::now();
+        let session_refreshed = clock::now();
 
         let db_sess: Option<Session> = diesel::update(sessions::table
             .filter(
@@ -270,7 +270,7 @@ pub fn start(conn: &Connection, user: &User) -> Result<UserSession> {
 
     let sess_secret = fresh_token()?;
 
-    let session_started = chrono::offset::Utc::now();
+    let session_started = clock::now();
 
     let new_sess = NewSession {
         user_id: user.id,
@@ -9,47 +9,47 @@ pub fn save_answer_test_item(conn: &Connection,
                              -> Result<()> {
     use schema::pending_items;
 
-    let answered_id = match *answer_enum {
-        Answered::E(ref a) => a.id,
-        Answered::Q(ref a) => a.id,
-        Answered::W(ref a) => a.id,
-    };
+    let answered_id = answer_enum.asked_id();
 
-    let mut pending_item: PendingItem =
-        pending_items::table.filter(pending_items::id.eq(answered_id)).get_result(&**conn)?;
+    conn.transaction(|| {
+        let mut pending_item: PendingItem =
+            pending_items::table.filter(pending_items::id.eq(answered_id))
+                .for_update()
+                .get_result(&**conn)?;
 
-    assert!(pending_item.test_item);
+        assert!(pending_item.test_item);
 
-    if !pending_item.pending {
-        info!("The user tried to answer to the same question twice! Ignoring the later answer.");
-        return Ok(());
-    }
-    pending_item.pending = false;
-    let _: PendingItem = pending_item.save_changes(&**conn)?;
+        if !pending_item.pending {
+            info!("The user tried to answer to the same question twice! Ignoring the later answer.");
+            return Ok(());
+        }
+        pending_item.pending = false;
+        let _: PendingItem = pending_item.save_changes(&**conn)?;
 
-    event::save_userdata(conn, event, user_id, Some("pending_test_item"), "0")?;
+        event::save_userdata(conn, event, user_id, Some("pending_test_item"), "0")?;
 
-    let mut number = event::get_userdata(conn, event, user_id, "quiz_number")
-        ?
-        .and_then(|d| d.data.parse::<usize>().ok())
-        .unwrap_or(0);
-    let answer_key = "answer_".to_string() + &number.to_string();
-    let answer_json = serde_json::to_string(&answer_enum).unwrap();
-    event::save_userdata(conn,
-                         event,
-                         user_id,
-                         Some(answer_key.as_str()),
-                         answer_json.as_str())?;
-    number += 1;
-    event::save_userdata(conn,
-                         event,
-                         user_id,
-                         Some("quiz_number"),
-                         &number.to_string())?;
+        let mut number = event::get_userdata(conn, event, user_id, "quiz_number")
+            ?
+            .and_then(|d| d.data.parse::<usize>().ok())
+            .unwrap_or(0);
+        let answer_key = "answer_".to_string() + &number.to_string();
+        let answer_json = serde_json::to_string(&answer_enum).unwrap();
+        event::save_userdata(conn,
+                             event,
+                             user_id,
+                             Some(answer_key.as_str()),
+                             answer_json.as_str())?;
+        number += 1;
+        event::save_userdata(conn,
+                             event,
+                             user_id,
+                             Some("quiz_number"),
+                             &number.to_string())?;
 
-    debug!("Pending test item unpended & answer saved.");
+        debug!("Pending test item unpended & answer saved.");
 
-    Ok(())
+        Ok(())
+    })
 }
 
 pub fn get_new_quiz_test(conn: &Connection,
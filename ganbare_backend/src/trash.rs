@@ -0,0 +1,388 @@
+//! The trash for the content deleted through the manager API.
+//!
+//! Trashed words, questions, exercises, comparison pairs, audio bundles and narrators are hidden
+//! from the quizzes
+//! and from the manager listings, and the learners' due items of them are parked, so they aren't
+//! asked. Restoring an item from the trash unparks the due items. The items that have been in
+//! the trash longer than the retention period are purged for good.
+//!
+//! Trashing an audio bundle trashes also the words that use it, and restoring the bundle
+//! restores them. Purging a word purges also the comparison pairs of it.
+//!
+//! Trashing, restoring and purging are recorded in the revision history.
+
+use super::*;
+use chrono::Duration;
+use error_chain::bail;
+
+pub const ITEM_TYPES: &[&str] = &["comparison", "word", "question", "exercise", "bundle",
+                                  "narrator"];
+
+/// The ids of the trashed items of a type.
+pub fn trashed_ids(conn: &Connection, item_type: &str) -> Result<Vec<i32>> {
+    use schema::trash;
+
+    Ok(trash::table.filter(trash::item_type.eq(item_type))
+           .select(trash::item_id)
+           .get_results(&**conn)?)
+}
+
+pub fn is_trashed(conn: &Connection, item_type: &str, item_id: i32) -> Result<bool> {
+    use schema::trash;
+
+    let count: i64 = trash::table.filter(trash::item_type.eq(item_type))
+        .filter(trash::item_id.eq(item_id))
+        .count()
+        .get_result(&**conn)?;
+
+    Ok(count > 0)
+}
+
+/// The name the manager shows for an item, or `None` if the item doesn't exist.
+fn describe(conn: &Connection, item_type: &str, id: i32) -> Result<Option<String>> {
+    use schema::{words, quiz_questions, exercises, comparison_pairs, audio_bundles, narrators};
+
+    Ok(match item_type {
+           "word" => {
+               words::table.filter(words::id.eq(id))
+                   .select(words::word)
+                   .get_result(&**conn)
+                   .optional()?
+           }
+           "question" => {
+               quiz_questions::table.filter(quiz_questions::id.eq(id))
+                   .select(quiz_questions::q_name)
+                   .get_result(&**conn)
+                   .optional()?
+           }
+           "exercise" => {
+               let skill: Option<String> =
+                   exercises::table.inner_join(schema::skill_nuggets::table)
+                       .filter(exercises::id.eq(id))
+                       .select(schema::skill_nuggets::skill_summary)
+                       .get_result(&**conn)
+                       .optional()?;
+               skill.map(|s| format!("{} (exercise)", s))
+           }
+           "comparison" => {
+               let pair: Option<ComparisonPair> =
+                   comparison_pairs::table.filter(comparison_pairs::id.eq(id))
+                       .get_result(&**conn)
+                       .optional()?;
+               match pair {
+                   Some(pair) => {
+                       let word_a: String = words::table.filter(words::id.eq(pair.word_a))
+                           .select(words::word)
+                           .get_result(&**conn)?;
+                       let word_b: String = words::table.filter(words::id.eq(pair.word_b))
+                           .select(words::word)
+                           .get_result(&**conn)?;
+                       Some(format!("{} / {} (comparison)", word_a, word_b))
+                   }
+                   None => None,
+               }
+           }
+           "bundle" => {
+               audio_bundles::table.filter(audio_bundles::id.eq(id))
+                   .select(audio_bundles::listname)
+                   .get_result(&**conn)
+                   .optional()?
+           }
+           "narrator" => {
+               narrators::table.filter(narrators::id.eq(id))
+                   .select(narrators::name)
+                   .get_result(&**conn)
+                   .optional()?
+           }
+           _ => bail!(ErrorKind::InvalidInput),
+       })
+}
+
+/// Parks or unparks the learners' due items of an item. A comparison pair stays parked
+/// as long as it or either of its words is in the trash.
+fn set_parked(conn: &Connection, item_type: &str, id: i32, parked: bool) -> Result<()> {
+    use schema::{due_items, question_data, exercise_data, comparison_data, comparison_pairs,
+                 accent_data, dictation_data};
+
+    let dues: Vec<i32> = match item_type {
+        "word" => {
+            let mut dues: Vec<i32> = accent_data::table.filter(accent_data::word_id.eq(id))
+                .select(accent_data::due)
+                .get_results(&**conn)?;
+            dues.extend(dictation_data::table.filter(dictation_data::word_id.eq(id))
+                            .select(dictation_data::due)
+                            .get_results::<i32>(&**conn)?);
+
+            let pairs: Vec<ComparisonPair> =
+                comparison_pairs::table.filter(comparison_pairs::word_a.eq(id)
+                                                   .or(comparison_pairs::word_b.eq(id)))
+                    .get_results(&**conn)?;
+            let trashed_words = trashed_ids(conn, "word")?;
+            let trashed_pairs = trashed_ids(conn, "comparison")?;
+            let pairs: Vec<i32> = pairs.into_iter()
+                .filter(|p| {
+                            parked ||
+                            !trashed_pairs.contains(&p.id) &&
+                            !trashed_words.contains(&p.word_a) &&
+                            !trashed_words.contains(&p.word_b)
+                        })
+                .map(|p| p.id)
+                .collect();
+            dues.extend(comparison_data::table.filter(comparison_data::pair_id.eq_any(pairs))
+                            .select(comparison_data::due)
+                            .get_results::<i32>(&**conn)?);
+            dues
+        }
+        "question" => {
+            question_data::table.filter(question_data::question_id.eq(id))
+                .select(question_data::due)
+                .get_results(&**conn)?
+        }
+        "exercise" => {
+            exercise_data::table.filter(exercise_data::exercise_id.eq(id))
+                .select(exercise_data::due)
+                .get_results(&**conn)?
+        }
+        "comparison" => {
+            let pair: ComparisonPair = comparison_pairs::table.filter(comparison_pairs::id.eq(id))
+                .get_result(&**conn)?;
+            if !parked &&
+               (is_trashed(conn, "word", pair.word_a)? || is_trashed(conn, "word", pair.word_b)?) {
+                return Ok(());
+            }
+            comparison_data::table.filter(comparison_data::pair_id.eq(id))
+                .select(comparison_data::due)
+                .get_results(&**conn)?
+        }
+        _ => vec![],
+    };
+
+    diesel::update(due_items::table.filter(due_items::id.eq_any(dues)))
+        .set(due_items::parked.eq(parked))
+        .execute(&**conn)?;
+
+    Ok(())
+}
+
+fn insert(conn: &Connection,
+          item_type: &str,
+          item_id: i32,
+          user_id: i32,
+          cascaded_from: Option<i32>)
+          -> Result<TrashedItem> {
+    use schema::trash;
+
+    let item = diesel::insert_into(trash::table).values(&NewTrashedItem {
+                                                             item_type,
+                                                             item_id,
+                                                             trashed_by: user_id,
+                                                             cascaded_from,
+                                                         })
+        .get_result(&**conn)?;
+
+    set_parked(conn, item_type, item_id, true)?;
+    revision::record_trash(conn, user_id, item_type, item_id, true)?;
+
+    Ok(item)
+}
+
+/// Moves an item to the trash. Returns false if there's no such item or it's trashed already.
+pub fn trash(conn: &Connection, item_type: &str, id: i32, user_id: i32) -> Result<bool> {
+    use schema::words;
+
+    if !ITEM_TYPES.contains(&item_type) {
+        bail!(ErrorKind::InvalidInput);
+    }
+
+    conn.transaction(|| {
+        if describe(conn, item_type, id)?.is_none() || is_trashed(conn, item_type, id)? {
+            return Ok(false);
+        }
+
+        let item = insert(conn, item_type, id, user_id, None)?;
+
+        if item_type == "bundle" {
+            let trashed_words = trashed_ids(conn, "word")?;
+            let word_ids: Vec<i32> = words::table.filter(words::audio_bundle.eq(id))
+                .filter(words::id.ne_all(trashed_words))
+                .select(words::id)
+                .get_results(&**conn)?;
+
+            for word_id in word_ids {
+                insert(conn, "word", word_id, user_id, Some(item.id))?;
+            }
+        }
+
+        info!("Moved {} {} to the trash.", item_type, id);
+
+        Ok(true)
+    })
+}
+
+/// Restores an item and the items trashed along with it from the trash.
+/// Returns false if the item isn't in the trash.
+pub fn restore(conn: &Connection, item_type: &str, id: i32, user_id: i32) -> Result<bool> {
+    use schema::trash;
+
+    if !ITEM_TYPES.contains(&item_type) {
+        bail!(ErrorKind::InvalidInput);
+    }
+
+    conn.transaction(|| {
+        let item: Option<TrashedItem> = trash::table.filter(trash::item_type.eq(item_type))
+            .filter(trash::item_id.eq(id))
+            .get_result(&**conn)
+            .optional()?;
+        let item = try_or!(item, else return Ok(false));
+
+        let cascaded: Vec<TrashedItem> = trash::table.filter(trash::cascaded_from.eq(item.id))
+            .get_results(&**conn)?;
+
+        // The cascaded items are deleted in cascade
+        diesel::delete(trash::table.filter(trash::id.eq(item.id))).execute(&**conn)?;
+
+        set_parked(conn, &item.item_type, item.item_id, false)?;
+        revision::record_trash(conn, user_id, &item.item_type, item.item_id, false)?;
+        for c in cascaded {
+            set_parked(conn, &c.item_type, c.item_id, false)?;
+            revision::record_trash(conn, user_id, &c.item_type, c.item_id, false)?;
+        }
+
+        info!("Restored {} {} from the trash.", item_type, id);
+
+        Ok(true)
+    })
+}
+
+/// All the trashed items with their names, the latest first.
+pub fn get_all(conn: &Connection) -> Result<Vec<(TrashedItem, String)>> {
+    use schema::trash;
+
+    let items: Vec<TrashedItem> = trash::table.order(trash::trashed.desc())
+        .get_results(&**conn)?;
+
+    let mut all = Vec::with_capacity(items.len());
+    for item in items {
+        let name = describe(conn, &item.item_type, item.item_id)?.unwrap_or_default();
+        all.push((item, name));
+    }
+
+    Ok(all)
+}
+
+/// Deletes the rows of a data table that refer to an item, and the due items of them.
+macro_rules! delete_due_items {
+    ($conn:ident, $data:ident, $column:ident, $id:expr) => {
+        let dues: Vec<i32> = schema::$data::table.filter(schema::$data::$column.eq($id))
+            .select(schema::$data::due)
+            .get_results(&**$conn)?;
+        diesel::delete(schema::$data::table.filter(schema::$data::$column.eq($id)))
+            .execute(&**$conn)?;
+        diesel::delete(schema::due_items::table.filter(schema::due_items::id.eq_any(dues)))
+            .execute(&**$conn)?;
+    }
+}
+
+/// Deletes a comparison pair for good, along with the due items of it.
+/// The pair doesn't have to be in the trash: the pairs of a purged word go with the word.
+fn purge_comparison(conn: &Connection, id: i32) -> Result<()> {
+    use schema::trash;
+
+    delete_due_items!(conn, comparison_data, pair_id, id);
+    revision::track_unattended(conn,
+                               "comparison",
+                               id,
+                               || manage::remove_comparison(conn, id))?;
+    diesel::delete(trash::table.filter(trash::item_type.eq("comparison"))
+                       .filter(trash::item_id.eq(id)))
+        .execute(&**conn)?;
+
+    Ok(())
+}
+
+/// Deletes a trashed item for good, along with the parked due items of it.
+fn purge_item(conn: &Connection, item: &TrashedItem) -> Result<()> {
+    use schema::{trash, comparison_pairs};
+
+    let (item_type, item_id) = (item.item_type.as_str(), item.item_id);
+
+    match item_type {
+        "comparison" => {
+            purge_comparison(conn, item_id)?;
+        }
+        "word" => {
+            let pairs: Vec<i32> =
+                comparison_pairs::table.filter(comparison_pairs::word_a.eq(item_id)
+                                                   .or(comparison_pairs::word_b.eq(item_id)))
+                    .select(comparison_pairs::id)
+                    .get_results(&**conn)?;
+            for pair_id in pairs {
+                purge_comparison(conn, pair_id)?;
+            }
+            delete_due_items!(conn, accent_data, word_id, item_id);
+            delete_due_items!(conn, dictation_data, word_id, item_id);
+            revision::track_unattended(conn,
+                                       item_type,
+                                       item_id,
+                                       || manage::remove_word(conn, item_id))?;
+        }
+        "question" => {
+            delete_due_items!(conn, question_data, question_id, item_id);
+            revision::track_unattended(conn,
+                                       item_type,
+                                       item_id,
+                                       || manage::remove_question(conn, item_id))?;
+        }
+        "exercise" => {
+            delete_due_items!(conn, exercise_data, exercise_id, item_id);
+            revision::track_unattended(conn,
+                                       item_type,
+                                       item_id,
+                                       || manage::remove_exercise(conn, item_id))?;
+        }
+        "bundle" => {
+            audio::del_bundle(conn, item.item_id)?;
+        }
+        "narrator" => {
+            audio::del_narrator(conn, item.item_id)?;
+        }
+        _ => bail!(ErrorKind::DatabaseOdd("Unknown item type in the trash!")),
+    }
+
+    diesel::delete(trash::table.filter(trash::id.eq(item.id))).execute(&**conn)?;
+
+    Ok(())
+}
+
+/// Purges the items that have been in the trash longer than `retention`.
+/// An item that is still referenced, for example by the learners' answers,
+/// can't be deleted, so it's left in the trash. Returns the number of items purged.
+pub fn purge(conn: &Connection, retention: Duration) -> Result<usize> {
+    use schema::trash;
+
+    let mut expired: Vec<TrashedItem> =
+        trash::table.filter(trash::trashed.lt(clock::now() - retention))
+            .get_results(&**conn)?;
+
+    // The comparison pairs go before their words, and the words before the bundles they use
+    expired.sort_by_key(|i| ITEM_TYPES.iter().position(|&t| t == i.item_type));
+
+    let mut purged = 0;
+    for item in &expired {
+        match conn.transaction(|| purge_item(conn, item)) {
+            Ok(()) => purged += 1,
+            Err(e) => {
+                debug!("Couldn't purge {} {} from the trash; leaving it there. Error: {}",
+                       item.item_type,
+                       item.item_id,
+                       e)
+            }
+        }
+    }
+
+    if purged > 0 {
+        info!("Purged {} items from the trash.", purged);
+    }
+
+    Ok(purged)
+}
@@ -1,6 +1,6 @@
 use super::*;
 use std::time::Instant;
-use chrono::Duration;
+use chrono::{Duration, DateTime, Utc};
 
 /* TODO FIXME this can be a full-blown typed group system some day
 enum Group {
@@ -149,7 +149,7 @@ pub fn check_pw_reset_secret(conn: &Connection,
 
     Ok(match confirm {
            Some((c, u)) => {
-               if c.added < chrono::offset::Utc::now() - chrono::Duration::days(2) {
+               if c.added < clock::now() - chrono::Duration::days(2) {
                    diesel::delete(
                     reset_email_secrets::table
                         .filter(reset_email_secrets::user_id.eq(c.user_id))
@@ -187,7 +187,7 @@ pub fn pw_change_secret(conn: &Connection,
             .optional()?;
 
     if let Some((secret, _)) = earlier_email {
-        if secret.added > chrono::offset::Utc::now() - chrono::Duration::days(1) {
+        if secret.added > clock::now() - chrono::Duration::days(1) {
             return Err(ErrorKind::RateLimitExceeded.into()); // Flood filter
         }
     }
@@ -226,7 +226,7 @@ pub fn new_email_secret(conn: &Connection, user_id: i32, initiated_email: String
                             secret: new_secret,
                             user_id,
                             email: initiated_email,
-                            added: chrono::offset::Utc::now(),
+                            added: clock::now(),
                             type_: type_.to_string(),
                         })
                 .get_result(&**conn)?;
@@ -290,6 +290,8 @@ pub fn remove_user_completely(conn: &Connection, id: i32) -> Result<Option<User>
     delete!(event_experiences, user_id);
     delete!(group_memberships, user_id);
     delete!(anon_aliases, user_id);
+    delete!(answer_submissions, user_id);
+    delete!(review_log, user_id);
     delete!(pending_items, user_id);
     delete!(due_items, user_id);
     delete!(users, id);
@@ -545,6 +547,12 @@ pub fn get_all(conn: &Connection)
     Ok((user_data, groups, confirms))
 }
 
+pub fn get_metrics(conn: &Connection, user_id: i32) -> Result<UserMetrics> {
+    use schema::user_metrics;
+
+    Ok(user_metrics::table.filter(user_metrics::id.eq(user_id)).get_result(&**conn)?)
+}
+
 pub fn set_metrics(conn: &Connection, metrics: &UpdateUserMetrics) -> Result<Option<UserMetrics>> {
     use schema::user_metrics;
 
@@ -555,17 +563,109 @@ pub fn set_metrics(conn: &Connection, metrics: &UpdateUserMetrics) -> Result<Opt
     Ok(item)
 }
 
+pub fn set_timezone(conn: &Connection,
+                    user_id: i32,
+                    timezone: &str,
+                    day_rollover_hour: i32)
+                    -> Result<Option<UserMetrics>> {
+
+    if timezone.parse::<chrono_tz::Tz>().is_err() || day_rollover_hour < 0 ||
+       day_rollover_hour > 23 {
+        return Err(ErrorKind::InvalidInput.into());
+    }
+
+    set_metrics(conn,
+                &UpdateUserMetrics {
+                    id: user_id,
+                    timezone: Some(timezone.to_owned()),
+                    day_rollover_hour: Some(day_rollover_hour),
+                    ..Default::default()
+                })
+}
+
+/// Sets the settings the learner can change on the settings page. Everything is validated
+/// before anything is written, and the settings are written in a single transaction.
+pub fn set_settings(conn: &Connection,
+                    user_id: i32,
+                    nag_emails: bool,
+                    timezone: &str,
+                    day_rollover_hour: i32)
+                    -> Result<Option<UserMetrics>> {
+
+    if timezone.parse::<chrono_tz::Tz>().is_err() || day_rollover_hour < 0 ||
+       day_rollover_hour > 23 {
+        return Err(ErrorKind::InvalidInput.into());
+    }
+
+    conn.transaction(|| {
+        // Joining a group the user is already in would abort the transaction.
+        if nag_emails && !check_user_group(conn, user_id, "nag_emails")? {
+            join_user_group_by_name(conn, user_id, "nag_emails")?;
+        } else if !nag_emails {
+            remove_user_group_by_name(conn, user_id, "nag_emails")?;
+        }
+
+        set_timezone(conn, user_id, timezone, day_rollover_hour)
+    })
+}
+
+/// Returns the moment (in UTC) the learner's local day containing `time` started.
+/// The day changes at `day_rollover_hour` o'clock in the learner's timezone.
+pub fn local_day_start(metrics: &UserMetrics,
+                       time: DateTime<Utc>)
+                       -> DateTime<Utc> {
+    use chrono::TimeZone;
+
+    let tz: chrono_tz::Tz = metrics.timezone.parse().unwrap_or_else(|_| {
+        warn!("User {} has an invalid timezone {:?}! Using UTC.", metrics.id, metrics.timezone);
+        chrono_tz::UTC
+    });
+    let rollover = Duration::hours(metrics.day_rollover_hour as i64);
+
+    let local_date = (time.with_timezone(&tz).naive_local() - rollover).date();
+    let start = local_date.and_hms(0, 0, 0) + rollover;
+
+    // If the rollover hour happens to fall into a DST gap, the day starts when the gap ends.
+    // The gaps are whole multiples of 15 minutes and at most a few hours long.
+    (0..12).filter_map(|i| {
+                           tz.from_local_datetime(&(start + Duration::minutes(15 * i)))
+                               .earliest()
+                       })
+        .next()
+        .unwrap_or_else(|| tz.from_utc_datetime(&start))
+        .with_timezone(&Utc)
+}
+
+/// Returns the moment (in UTC) the learner's local day following the one containing `time`
+/// starts. Local days aren't always 24 hours long because of DST.
+pub fn next_local_day_start(metrics: &UserMetrics,
+                            time: DateTime<Utc>)
+                            -> DateTime<Utc> {
+    local_day_start(metrics,
+                    local_day_start(metrics, time) + Duration::hours(36))
+}
+
+/// Returns the users who haven't been seen since the start of the local day
+/// `inactive` ago, and who still have something to study.
 pub fn get_slackers(conn: &Connection, inactive: Duration) -> Result<Vec<(i32, String)>> {
-    use schema::{users};
+    use schema::{users, user_metrics};
+
+    let now = clock::now();
 
-    let slackers: Vec<(i32, Option<String>)> = users::table
+    // The local day start is never later than `now - inactive`, so this narrows down safely.
+    let slackers: Vec<(i32, Option<String>, DateTime<Utc>, UserMetrics)> = users::table
+        .inner_join(user_metrics::table)
         .filter(users::email.is_not_null())
-        .filter(users::last_seen.lt(chrono::offset::Utc::now() - inactive))
-        .select((users::id, users::email))
+        .filter(users::last_seen.lt(now - inactive))
+        .select((users::id, users::email, users::last_seen, user_metrics::all_columns))
         .get_results(&**conn)?;
 
     let mut true_slackers = vec![];
-    for (user_id, email) in slackers {
+    for (user_id, email, last_seen, metrics) in slackers {
+
+        if last_seen >= local_day_start(&metrics, now - inactive) {
+            continue; // Has been seen on the local day the absence period started
+        }
 
         let (next_existing_due, no_new_words, no_new_quizes) = quiz::things_left_to_do(conn,
                                                                                        user_id)?;
@@ -579,3 +679,54 @@ pub fn get_slackers(conn: &Connection, inactive: Duration) -> Result<Vec<(i32, S
     }
     Ok(true_slackers)
 }
+
+/// Metrics with the default settings, for the tests. Override fields with struct update syntax.
+#[cfg(test)]
+pub fn test_metrics(now: DateTime<Utc>) -> UserMetrics {
+    UserMetrics {
+        id: 1,
+        new_words_since_break: 0,
+        new_words_today: 0,
+        quizes_since_break: 0,
+        quizes_today: 0,
+        break_until: now,
+        today: now,
+        max_words_since_break: 0,
+        max_words_today: 0,
+        max_quizes_since_break: 0,
+        max_quizes_today: 0,
+        break_length: 0,
+        delay_multiplier: 2,
+        initial_delay: 60,
+        streak_limit: 1,
+        cooldown_delay: 0,
+        streak_skill_bump_criteria: 0,
+        timezone: "UTC".to_owned(),
+        day_rollover_hour: 0,
+        leech_threshold: 8,
+        slow_answer_percent: 200,
+        fast_answer_percent: 50,
+        max_good_audio_times: 2,
+        hard_interval_percent: 50,
+        easy_interval_percent: 130,
+    }
+}
+
+#[test]
+fn test_local_day_start() {
+    use chrono::TimeZone;
+
+    let utc = |h, m| Utc.ymd(2026, 3, 29).and_hms(h, m, 0);
+    let mut metrics = UserMetrics {
+        timezone: "Europe/Helsinki".to_owned(),
+        day_rollover_hour: 3,
+        ..test_metrics(utc(0, 0))
+    };
+
+    // The clocks skip from 03:00 to 04:00 local time (01:00 UTC), so the day starts at 04:00.
+    assert_eq!(local_day_start(&metrics, utc(12, 0)), utc(1, 0));
+    assert_eq!(next_local_day_start(&metrics, utc(0, 30)), utc(1, 0));
+
+    metrics.day_rollover_hour = 2;
+    assert_eq!(local_day_start(&metrics, utc(12, 0)), utc(0, 0));
+}
@@ -0,0 +1,345 @@
+//! Bulk import of words and sentences from a zip archive of audio files and a TSV manifest.
+//!
+//! The manifest is `manifest.tsv` at the root of the archive. Every line has the columns
+//! word, accent, explanation, skill nugget, narrator, sentence and audio:
+//!
+//! - The word in kana. With accent markup, the accent column can be left empty.
+//! - The word with accent markup, for example `き・ょうと`. It must read the same as the word.
+//! - The explanation (HTML).
+//! - The name of the skill nugget. Defaults to the word without the markup.
+//! - The name of the narrator of the audio.
+//! - A sentence with accent markup that uses the word. If set, the line imports the sentence
+//!   instead of the word.
+//! - The audio files in the archive, separated by commas.
+//!
+//! Empty lines and lines starting with `#` are skipped. The whole batch is validated first,
+//! and it's imported only if every line is valid, in a single transaction.
+//! The archive is extracted with the `unzip` tool.
+
+use super::*;
+use std::path::{Path, PathBuf, Component};
+use std::process::Command;
+use serde::Serialize;
+use error_chain::bail;
+
+lazy_static! {
+    static ref UNZIP : String = {
+        dotenv::dotenv().ok();
+        std::env::var("GANBARE_UNZIP").unwrap_or_else(|_| "unzip".into())
+    };
+}
+
+pub const MANIFEST_NAME: &str = "manifest.tsv";
+
+const AUDIO_TYPES: &[(&str, &str)] = &[("mp3", "audio/mpeg"),
+                                       ("ogg", "audio/ogg"),
+                                       ("opus", "audio/ogg"),
+                                       ("m4a", "audio/mp4"),
+                                       ("wav", "audio/wav")];
+
+/// Sentences are introduced only after the words, like in the old import tool.
+const SENTENCE_SKILL_LEVEL: i32 = 5;
+
+#[derive(Serialize, Debug)]
+pub struct RowReport {
+    pub line: usize,
+    pub word: String,
+    /// The words that exist already are left as they are; only their audio is added.
+    pub exists: bool,
+    pub errors: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ImportReport {
+    pub rows: Vec<RowReport>,
+    pub committed: bool,
+}
+
+impl ImportReport {
+    pub fn is_valid(&self) -> bool {
+        self.rows.iter().all(|r| r.errors.is_empty())
+    }
+}
+
+#[derive(Debug)]
+struct ManifestRow {
+    word: String,
+    explanation: String,
+    nugget: String,
+    narrator: String,
+    skill_level: i32,
+    files: Vec<(PathBuf, Option<String>, mime::Mime)>,
+}
+
+/// The path of an audio file inside the extracted archive. `None` if the name tries to
+/// point outside the archive.
+fn archive_path(dir: &Path, file_name: &str) -> Option<PathBuf> {
+    let relative = Path::new(file_name);
+    if relative.components().all(|c| match c {
+                                     Component::Normal(_) => true,
+                                     _ => false,
+                                 }) {
+        Some(dir.join(relative))
+    } else {
+        None
+    }
+}
+
+/// Whether the path is a regular file inside the extracted archive. `unzip` restores the
+/// symlinks in the archive, so neither the file nor the directories on the way may be links
+/// that point outside of it.
+fn is_archived_file(dir: &Path, path: &Path) -> bool {
+    let is_file = std::fs::symlink_metadata(path).map(|m| m.file_type().is_file())
+        .unwrap_or(false);
+    let is_inside = match (dir.canonicalize(), path.canonicalize()) {
+        (Ok(dir), Ok(path)) => path.starts_with(dir),
+        _ => false,
+    };
+    is_file && is_inside
+}
+
+fn audio_mime(file_name: &str) -> Option<mime::Mime> {
+    use std::str::FromStr;
+
+    let extension = Path::new(file_name).extension()?.to_str()?.to_lowercase();
+    AUDIO_TYPES.iter()
+        .find(|&&(e, _)| e == extension)
+        .and_then(|&(_, m)| mime::Mime::from_str(m).ok())
+}
+
+/// Parses and validates the manifest. The audio files are looked up in `dir`.
+/// Every line gets a report, and the lines without errors get the parsed row.
+fn parse_manifest(tsv: &str, dir: &Path) -> Vec<(RowReport, Option<ManifestRow>)> {
+    use unicode_normalization::UnicodeNormalization;
+
+    let mut rows: Vec<(RowReport, Option<ManifestRow>)> = vec![];
+
+    for (line_number, line) in tsv.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let columns: Vec<String> = line.split('\t').map(|c| c.trim().nfc().collect()).collect();
+        let column = |i: usize| columns.get(i).map(String::as_str).unwrap_or("");
+        let mut errors = vec![];
+
+        let (word, accent, explanation) = (column(0), column(1), column(2));
+        let (nugget, narrator, sentence) = (column(3), column(4), column(5));
+
+        if columns.len() < 7 {
+            errors.push(format!("Expected 7 columns, found {}.", columns.len()));
+        }
+
+        if !accent.is_empty() && kana::reading(word) != kana::reading(accent) {
+            errors.push(format!("The accent {:?} doesn't read the same as the word {:?}.",
+                                accent,
+                                word));
+        }
+
+        let accent = if accent.is_empty() { word } else { accent };
+        let (item, skill_level) = if sentence.is_empty() {
+            (accent, 0)
+        } else {
+            (sentence, SENTENCE_SKILL_LEVEL)
+        };
+
+        if item.is_empty() {
+            errors.push("No word.".to_owned());
+        } else if let Err(e) = accent::validate(item) {
+            errors.push(format!("Invalid accent markup: {}", e));
+        }
+
+        let nugget = if nugget.is_empty() {
+            accent::strip_marks(accent)
+        } else {
+            nugget.to_owned()
+        };
+        if nugget.is_empty() {
+            errors.push("No skill nugget.".to_owned());
+        }
+
+        if narrator.is_empty() {
+            errors.push("No narrator.".to_owned());
+        }
+
+        let file_names: Vec<&str> =
+            column(6).split(',').map(str::trim).filter(|f| !f.is_empty()).collect();
+        if file_names.is_empty() {
+            errors.push("No audio files.".to_owned());
+        }
+
+        let mut files = vec![];
+        for file_name in file_names {
+            let path = match archive_path(dir, file_name) {
+                Some(path) => path,
+                None => {
+                    errors.push(format!("Invalid audio file name {:?}.", file_name));
+                    continue;
+                }
+            };
+            if !is_archived_file(dir, &path) {
+                errors.push(format!("No audio file {:?} in the archive.", file_name));
+                continue;
+            }
+            match audio_mime(file_name) {
+                Some(mime) => files.push((path, Some(file_name.to_owned()), mime)),
+                None => errors.push(format!("Unsupported audio type: {:?}.", file_name)),
+            }
+        }
+        if let Some(&(ref other, _)) = rows.iter().find(|&&(ref r, _)| !item.is_empty() &&
+                                                                     r.word == item) {
+            errors.push(format!("The same word is on line {}.", other.line));
+        }
+
+        let row = if errors.is_empty() {
+            Some(ManifestRow {
+                     word: item.to_owned(),
+                     explanation: explanation.to_owned(),
+                     nugget,
+                     narrator: narrator.to_owned(),
+                     skill_level,
+                     files,
+                 })
+        } else {
+            None
+        };
+
+        rows.push((RowReport {
+                       line: line_number + 1,
+                       word: item.to_owned(),
+                       exists: false,
+                       errors,
+                   },
+                   row));
+    }
+
+    rows
+}
+
+fn unzip(archive: &Path, dir: &Path) -> Result<()> {
+    let output = Command::new(&*UNZIP).args(&["-qq", "-o"])
+        .arg(archive)
+        .arg("-d")
+        .arg(dir)
+        .output()
+        .chain_err(|| "Couldn't run unzip!")?;
+
+    // Exit code 1 means warnings only, for example about skipped "../" path components.
+    match output.status.code() {
+        Some(0) | Some(1) => Ok(()),
+        _ => {
+            bail!(ErrorKind::InvalidImportArchive(String::from_utf8_lossy(&output.stderr)
+                                                      .into_owned()))
+        }
+    }
+}
+
+/// Validates the archive and reports every line of the manifest. If `commit` is set and
+/// every line is valid, imports the words through `manage::create_or_update_word`,
+/// all or nothing.
+pub fn import(conn: &Connection,
+              archive: &Path,
+              audio_dir: &Path,
+              commit: bool)
+              -> Result<ImportReport> {
+    use schema::words;
+
+    // Extracting next to the audio files, so that saving them is just a rename.
+    let tmp_dir = tempdir::TempDir::new_in(audio_dir, "word_import")?;
+    unzip(archive, tmp_dir.path())?;
+
+    let tsv = std::fs::read_to_string(tmp_dir.path().join(MANIFEST_NAME))
+        .chain_err(|| {
+                       ErrorKind::InvalidImportArchive(format!("No {} in the archive.",
+                                                               MANIFEST_NAME))
+                   })?;
+
+    let mut rows = parse_manifest(&tsv, tmp_dir.path());
+    if rows.is_empty() {
+        bail!(ErrorKind::InvalidImportArchive("The manifest is empty.".into()));
+    }
+
+    for &mut (ref mut report, _) in &mut rows {
+        let count: i64 = words::table.filter(words::word.eq(&report.word))
+            .count()
+            .get_result(&**conn)?;
+        report.exists = count > 0;
+    }
+
+    let (reports, rows): (Vec<RowReport>, Vec<Option<ManifestRow>>) = rows.into_iter().unzip();
+    let mut report = ImportReport {
+        rows: reports,
+        committed: false,
+    };
+
+    if !commit || !report.is_valid() {
+        return Ok(report);
+    }
+
+    // The audio files are saved in a staging directory and moved next to the other audio
+    // files only after the commit, so that a failed import doesn't leave orphan files behind.
+    let staging_dir = tempdir::TempDir::new_in(audio_dir, "word_import_staging")?;
+
+    let imported: Result<()> = conn.transaction(|| {
+        for row in rows.into_iter().filter_map(|r| r) {
+            manage::create_or_update_word(conn,
+                                          manage::NewWordFromStrings {
+                                              word: row.word,
+                                              explanation: row.explanation,
+                                              nugget: row.nugget,
+                                              narrator: &row.narrator,
+                                              files: row.files,
+                                              skill_level: row.skill_level,
+                                              priority: 0,
+                                          },
+                                          staging_dir.path())?;
+        }
+        Ok(())
+    });
+    imported?;
+
+    for entry in std::fs::read_dir(staging_dir.path())? {
+        let entry = entry?;
+        std::fs::rename(entry.path(), audio_dir.join(entry.file_name()))
+            .chain_err(|| "Can't move the imported audio file.")?;
+    }
+
+    info!("Imported {} words.", report.rows.len());
+    report.committed = true;
+
+    Ok(report)
+}
+
+#[test]
+fn test_parse_manifest() {
+    let dir = tempdir::TempDir::new("test_parse_manifest").unwrap();
+    std::fs::write(dir.path().join("kyouto.mp3"), b"").unwrap();
+    std::fs::write(dir.path().join("kyouto2.ogg"), b"").unwrap();
+    std::fs::write(dir.path().join("notes.txt"), b"").unwrap();
+    std::os::unix::fs::symlink(dir.path().join("kyouto.mp3"), dir.path().join("link.mp3")).unwrap();
+
+    let tsv = "# word\taccent\texplanation\tnugget\tnarrator\tsentence\taudio\n\
+               きょうと\tきょ・うと\t京都\t\tTaro\t\tkyouto.mp3, kyouto2.ogg\n\
+               きょうと\tきょ・うと\t京都\t\tTaro\t\tkyouto.mp3\n\
+               \n\
+               あめ\t\t雨\tあめ\t\t\tnotes.txt,missing.mp3\n\
+               いく\tい・く\t\t\tTaro\t\t../kyouto.mp3\r\n\
+               はし\tは・し\t橋\t\tTaro\t\tlink.mp3\n\
+               はし\tは・な\t橋\t\tTaro\t\tkyouto.mp3\n";
+
+    let rows = parse_manifest(tsv, dir.path());
+    let errors: Vec<(usize, usize)> = rows.iter().map(|&(ref r, _)| (r.line, r.errors.len())).collect();
+    // Line 3 is a duplicate, line 5 has no narrator or valid audio files,
+    // line 6 points outside the archive, line 7 to a symlink and on line 8
+    // the accent doesn't read the same as the word.
+    assert_eq!(errors, vec![(2, 0), (3, 1), (5, 3), (6, 1), (7, 1), (8, 1)]);
+
+    let row = rows[0].1.as_ref().unwrap();
+    assert_eq!(row.word, "きょ・うと");
+    assert_eq!(row.nugget, "きょうと");
+    assert_eq!(row.skill_level, 0);
+    assert_eq!(row.files.len(), 2);
+    assert_eq!(format!("{}", row.files[1].2), "audio/ogg");
+    assert!(rows[1].1.is_none());
+}
@@ -0,0 +1,174 @@
+//! Frequency lists and JLPT level lists, for ordering the new words.
+//!
+//! The lists are imported from TSV files with the `import_word_list` tool. Every line has
+//! the word, and optionally its reading in kana in the second column. The lines of a frequency
+//! list are ranked by their order, the most frequent first. JLPT lists have the level
+//! ("N5" or "5") in the third column. Empty lines and lines starting with `#` are skipped.
+//!
+//! The entries are matched with the words by their readings, since the words are
+//! written in kana, with accent markup.
+
+use super::*;
+use serde::Serialize;
+use error_chain::bail;
+
+pub const LIST_TYPES: &[&str] = &["frequency", "jlpt"];
+
+#[derive(Serialize, Debug)]
+pub struct ListCoverage {
+    pub list: WordList,
+    pub entries: i64,
+    /// The entries that match at least one published word.
+    pub entries_covered: i64,
+    /// The published words that match at least one entry.
+    pub words_covered: i64,
+    pub published_words: i64,
+}
+
+/// Parses a JLPT level: "N5" and "5" are both 5.
+fn parse_level(level: &str) -> Option<i32> {
+    let level = level.trim_start_matches(|c| c == 'N' || c == 'n');
+    match level.parse::<i32>() {
+        Ok(level) if level >= 1 && level <= 5 => Some(level),
+        _ => None,
+    }
+}
+
+/// Parses the lines of a TSV list into the entries of the list `list_id`.
+pub fn parse_tsv(list_id: i32, list_type: &str, tsv: &str) -> Result<Vec<NewWordListEntry>> {
+    let mut entries = vec![];
+
+    for (line_number, line) in tsv.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut columns = line.split('\t').map(str::trim);
+        let word = columns.next().unwrap_or("");
+        let reading = columns.next().filter(|r| !r.is_empty()).unwrap_or(word);
+        let reading = kana::reading(reading);
+
+        if word.is_empty() || reading.is_empty() {
+            bail!(ErrorKind::InvalidWordList(format!("No word on line {}", line_number + 1)));
+        }
+
+        let rank = if list_type == "jlpt" {
+            match columns.next().and_then(parse_level) {
+                Some(level) => level,
+                None => {
+                    bail!(ErrorKind::InvalidWordList(format!("No JLPT level on line {}",
+                                                             line_number + 1)))
+                }
+            }
+        } else {
+            entries.len() as i32 + 1
+        };
+
+        entries.push(NewWordListEntry {
+                         list_id,
+                         word: word.to_owned(),
+                         reading,
+                         rank,
+                     });
+    }
+
+    Ok(entries)
+}
+
+/// Imports a list, replacing the earlier list with the same name.
+/// Returns the list and the number of its entries.
+pub fn import(conn: &Connection,
+              list_name: &str,
+              list_type: &str,
+              tsv: &str)
+              -> Result<(WordList, usize)> {
+    use schema::{word_lists, word_list_entries};
+
+    if !LIST_TYPES.contains(&list_type) {
+        bail!(ErrorKind::InvalidInput);
+    }
+
+    conn.transaction(|| {
+        // The entries are deleted in cascade
+        diesel::delete(word_lists::table.filter(word_lists::list_name.eq(list_name)))
+            .execute(&**conn)?;
+
+        let list: WordList = diesel::insert_into(word_lists::table).values(&NewWordList {
+                                                                          list_name,
+                                                                          list_type,
+                                                                      })
+            .get_result(&**conn)?;
+
+        let entries = parse_tsv(list.id, list_type, tsv)?;
+
+        // Postgres limits the number of bind parameters per query
+        for chunk in entries.chunks(1000) {
+            diesel::insert_into(word_list_entries::table).values(chunk).execute(&**conn)?;
+        }
+
+        Ok((list, entries.len()))
+    })
+}
+
+/// How well the published words and each of the lists cover each other.
+pub fn coverage(conn: &Connection) -> Result<Vec<ListCoverage>> {
+    use schema::{word_lists, word_list_entries, words};
+    use diesel::expression::dsl::*;
+
+    let lists: Vec<WordList> = word_lists::table.order(word_lists::list_name.asc())
+        .get_results(&**conn)?;
+
+    let published_words: i64 = words::table.filter(words::published.eq(true))
+        .count()
+        .get_result(&**conn)?;
+
+    let mut coverages = Vec::with_capacity(lists.len());
+
+    for list in lists {
+        let entries: i64 = word_list_entries::table.filter(word_list_entries::list_id.eq(list.id))
+            .count()
+            .get_result(&**conn)?;
+
+        let entries_covered: i64 = sql::<diesel::sql_types::BigInt>(&format!(r###"
+SELECT COUNT(*) FROM word_list_entries AS e
+WHERE
+    e.list_id={} AND
+    EXISTS ( SELECT 1 FROM words AS w WHERE w.published = true AND w.reading = e.reading );
+"###, list.id)) // Injection isn't possible: the id is numerical and non-tainted data.
+            .get_result(&**conn)?;
+
+        let words_covered: i64 = sql::<diesel::sql_types::BigInt>(&format!(r###"
+SELECT COUNT(*) FROM words AS w
+WHERE
+    w.published = true AND
+    EXISTS ( SELECT 1 FROM word_list_entries AS e WHERE e.list_id={} AND e.reading = w.reading );
+"###, list.id)) // Injection isn't possible: the id is numerical and non-tainted data.
+            .get_result(&**conn)?;
+
+        coverages.push(ListCoverage {
+                           list,
+                           entries,
+                           entries_covered,
+                           words_covered,
+                           published_words,
+                       });
+    }
+
+    Ok(coverages)
+}
+
+#[test]
+fn test_parse_tsv() {
+    let frequency = parse_tsv(1, "frequency", "# A comment\nの\nきょ・うと\tキョウト\n\n学校\tがっこう\r\n")
+        .unwrap();
+    let readings: Vec<(&str, i32)> = frequency.iter().map(|e| (e.reading.as_str(), e.rank)).collect();
+    assert_eq!(readings, vec![("の", 1), ("きょうと", 2), ("がっこう", 3)]);
+    assert_eq!(frequency[2].word, "学校");
+
+    let jlpt = parse_tsv(1, "jlpt", "学校\tがっこう\tN5\nあめ\t\t4\n").unwrap();
+    let readings: Vec<(&str, i32)> = jlpt.iter().map(|e| (e.reading.as_str(), e.rank)).collect();
+    assert_eq!(readings, vec![("がっこう", 5), ("あめ", 4)]);
+
+    assert!(parse_tsv(1, "jlpt", "学校\tがっこう\n").is_err());
+    assert!(parse_tsv(1, "jlpt", "学校\tがっこう\tN6\n").is_err());
+}
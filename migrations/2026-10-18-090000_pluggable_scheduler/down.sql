@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM group_memberships WHERE group_id IN (SELECT id FROM user_groups WHERE group_name IN ('sm2_scheduler', 'fsrs_scheduler'));
+DELETE FROM user_groups WHERE group_name IN ('sm2_scheduler', 'fsrs_scheduler');
+ALTER TABLE due_items DROP COLUMN stability;
+ALTER TABLE due_items DROP COLUMN difficulty;
+ALTER TABLE due_items DROP COLUMN ease_factor;
@@ -0,0 +1,8 @@
+-- Your SQL goes here
+ALTER TABLE due_items ADD COLUMN ease_factor DOUBLE PRECISION NOT NULL DEFAULT 2.5;
+ALTER TABLE due_items ADD COLUMN difficulty DOUBLE PRECISION NOT NULL DEFAULT 0;
+ALTER TABLE due_items ADD COLUMN stability DOUBLE PRECISION NOT NULL DEFAULT 0;
+DELETE FROM group_memberships WHERE group_id IN (SELECT id FROM user_groups WHERE group_name IN ('sm2_scheduler', 'fsrs_scheduler'));
+DELETE FROM user_groups WHERE group_name IN ('sm2_scheduler', 'fsrs_scheduler');
+INSERT INTO user_groups (group_name) VALUES ('sm2_scheduler');
+INSERT INTO user_groups (group_name) VALUES ('fsrs_scheduler');
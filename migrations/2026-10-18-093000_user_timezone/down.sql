@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE user_metrics DROP COLUMN day_rollover_hour;
+ALTER TABLE user_metrics DROP COLUMN timezone;
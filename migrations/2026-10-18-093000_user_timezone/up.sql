@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TABLE user_metrics ADD COLUMN timezone VARCHAR NOT NULL DEFAULT 'Europe/Helsinki';
+ALTER TABLE user_metrics ADD COLUMN day_rollover_hour INTEGER NOT NULL DEFAULT 3;
@@ -0,0 +1,8 @@
+-- Your SQL goes here
+CREATE TABLE answer_submissions (
+	user_id INTEGER NOT NULL REFERENCES users,
+	idempotency_key VARCHAR NOT NULL,
+	next_pending_item INTEGER,
+	submitted TIMESTAMPTZ NOT NULL DEFAULT current_timestamp,
+	PRIMARY KEY(user_id, idempotency_key)
+);
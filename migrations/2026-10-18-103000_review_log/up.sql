@@ -0,0 +1,27 @@
+-- Your SQL goes here
+CREATE TABLE review_log (
+	id SERIAL PRIMARY KEY,
+	user_id INTEGER NOT NULL REFERENCES users,
+	asked_id INTEGER NOT NULL,
+	item_type VARCHAR NOT NULL,
+	item_id INTEGER NOT NULL,
+	due_item_id INTEGER,
+	answered_date TIMESTAMPTZ NOT NULL,
+	correct BOOLEAN NOT NULL,
+	answer_level INTEGER,
+	active_answer_time_ms INTEGER NOT NULL,
+	full_answer_time_ms INTEGER,
+	full_spent_time_ms INTEGER NOT NULL,
+	prev_due_delay INTEGER,
+	new_due_delay INTEGER,
+	prev_correct_streak_this_time INTEGER,
+	new_correct_streak_this_time INTEGER,
+	prev_correct_streak_overall INTEGER,
+	new_correct_streak_overall INTEGER,
+	new_due_date TIMESTAMPTZ,
+	scheduler VARCHAR,
+	reconstructed BOOLEAN NOT NULL DEFAULT false
+);
+
+CREATE INDEX review_log_user_id_answered_date ON review_log (user_id, answered_date);
+CREATE INDEX review_log_asked_id ON review_log (asked_id);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE user_metrics DROP COLUMN leech_threshold;
+ALTER TABLE due_items DROP COLUMN suspended;
+ALTER TABLE due_items DROP COLUMN lapses;
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+ALTER TABLE due_items ADD COLUMN lapses INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE due_items ADD COLUMN suspended BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE user_metrics ADD COLUMN leech_threshold INTEGER NOT NULL DEFAULT 8;
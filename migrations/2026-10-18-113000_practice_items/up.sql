@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE pending_items ADD COLUMN practice BOOLEAN NOT NULL DEFAULT false;
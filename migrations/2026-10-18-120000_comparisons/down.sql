@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM group_memberships WHERE group_id = (SELECT id FROM user_groups WHERE group_name = 'comparisons');
+DELETE FROM user_groups WHERE group_name = 'comparisons';
+DROP TABLE c_answered_data;
+DROP TABLE c_asked_data;
+DROP TABLE comparison_data;
+DROP TABLE comparison_pairs;
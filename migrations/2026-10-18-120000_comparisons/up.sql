@@ -0,0 +1,36 @@
+-- Your SQL goes here
+CREATE TABLE comparison_pairs (
+	id SERIAL PRIMARY KEY,
+	skill_id INTEGER NOT NULL REFERENCES skill_nuggets,
+	word_a INTEGER NOT NULL REFERENCES words,
+	word_b INTEGER NOT NULL REFERENCES words,
+	published BOOLEAN NOT NULL DEFAULT false,
+	skill_level INTEGER NOT NULL DEFAULT 0,
+	CHECK (word_a <> word_b)
+);
+
+CREATE TABLE comparison_data (
+	pair_id INTEGER NOT NULL REFERENCES comparison_pairs,
+	due INTEGER NOT NULL REFERENCES due_items,
+	PRIMARY KEY(due, pair_id)
+);
+
+CREATE TABLE c_asked_data (
+	id INTEGER REFERENCES pending_items PRIMARY KEY,
+	pair_id INTEGER NOT NULL REFERENCES comparison_pairs,
+	word_id INTEGER NOT NULL REFERENCES words,
+	first_audio_id INTEGER NOT NULL REFERENCES audio_files,
+	second_audio_id INTEGER NOT NULL REFERENCES audio_files,
+	right_choice INTEGER NOT NULL CHECK (right_choice IN (1, 2))
+);
+
+CREATE TABLE c_answered_data (
+	id INTEGER REFERENCES c_asked_data PRIMARY KEY,
+	answered_choice INTEGER,
+	answered_date TIMESTAMPTZ NOT NULL,
+	active_answer_time_ms INTEGER NOT NULL,
+	full_answer_time_ms INTEGER NOT NULL,
+	full_spent_time_ms INTEGER NOT NULL
+);
+
+INSERT INTO user_groups (group_name) VALUES ('comparisons');
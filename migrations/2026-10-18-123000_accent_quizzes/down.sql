@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM group_memberships WHERE group_id = (SELECT id FROM user_groups WHERE group_name = 'accent_quizzes');
+DELETE FROM user_groups WHERE group_name = 'accent_quizzes';
+DROP TABLE a_answered_data;
+DROP TABLE a_asked_data;
+DROP TABLE accent_data;
@@ -0,0 +1,23 @@
+-- Your SQL goes here
+CREATE TABLE accent_data (
+	word_id INTEGER NOT NULL REFERENCES words,
+	due INTEGER NOT NULL REFERENCES due_items,
+	PRIMARY KEY(due, word_id)
+);
+
+CREATE TABLE a_asked_data (
+	id INTEGER REFERENCES pending_items PRIMARY KEY,
+	word_id INTEGER NOT NULL REFERENCES words,
+	right_nucleus INTEGER CHECK (right_nucleus >= 0)
+);
+
+CREATE TABLE a_answered_data (
+	id INTEGER REFERENCES a_asked_data PRIMARY KEY,
+	answered_nucleus INTEGER CHECK (answered_nucleus >= 0),
+	answered_date TIMESTAMPTZ NOT NULL,
+	active_answer_time_ms INTEGER NOT NULL,
+	full_answer_time_ms INTEGER NOT NULL,
+	full_spent_time_ms INTEGER NOT NULL
+);
+
+INSERT INTO user_groups (group_name) VALUES ('accent_quizzes');
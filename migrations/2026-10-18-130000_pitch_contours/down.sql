@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE user_audio_pitch;
+DROP TABLE audio_pitch;
@@ -0,0 +1,14 @@
+-- Your SQL goes here
+CREATE TABLE audio_pitch (
+	audio_file_id INTEGER REFERENCES audio_files PRIMARY KEY,
+	contour TEXT NOT NULL,
+	extracted TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE user_audio_pitch (
+	user_id INTEGER NOT NULL REFERENCES users,
+	file_name VARCHAR NOT NULL,
+	contour TEXT NOT NULL,
+	extracted TIMESTAMPTZ NOT NULL DEFAULT now(),
+	PRIMARY KEY(user_id, file_name)
+);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM group_memberships WHERE group_id = (SELECT id FROM user_groups WHERE group_name = 'pronunciation_scheduling');
+DELETE FROM user_groups WHERE group_name = 'pronunciation_scheduling';
+DROP TABLE pronunciation_scores;
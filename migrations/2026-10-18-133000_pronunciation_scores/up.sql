@@ -0,0 +1,9 @@
+-- Your SQL goes here
+CREATE TABLE pronunciation_scores (
+	id INTEGER REFERENCES e_asked_data PRIMARY KEY,
+	file_name VARCHAR NOT NULL,
+	score REAL CHECK (score BETWEEN 0 AND 100),
+	scored TIMESTAMPTZ
+);
+
+INSERT INTO user_groups (group_name) VALUES ('pronunciation_scheduling');
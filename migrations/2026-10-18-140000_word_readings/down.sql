@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE words DROP COLUMN romaji;
+ALTER TABLE words DROP COLUMN reading;
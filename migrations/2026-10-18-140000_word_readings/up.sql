@@ -0,0 +1,5 @@
+-- Your SQL goes here
+-- The readings are converted from the words in Rust; the existing words are filled in
+-- by db::check on the next startup.
+ALTER TABLE words ADD COLUMN reading VARCHAR NOT NULL DEFAULT '';
+ALTER TABLE words ADD COLUMN romaji VARCHAR NOT NULL DEFAULT '';
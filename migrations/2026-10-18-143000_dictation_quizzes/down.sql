@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM group_memberships WHERE group_id = (SELECT id FROM user_groups WHERE group_name = 'dictation_quizzes');
+DELETE FROM user_groups WHERE group_name = 'dictation_quizzes';
+DROP TABLE d_answered_data;
+DROP TABLE d_asked_data;
+DROP TABLE dictation_data;
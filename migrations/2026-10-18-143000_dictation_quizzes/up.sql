@@ -0,0 +1,25 @@
+-- Your SQL goes here
+CREATE TABLE dictation_data (
+	word_id INTEGER NOT NULL REFERENCES words,
+	due INTEGER NOT NULL REFERENCES due_items,
+	PRIMARY KEY(due, word_id)
+);
+
+CREATE TABLE d_asked_data (
+	id INTEGER REFERENCES pending_items PRIMARY KEY,
+	word_id INTEGER NOT NULL REFERENCES words,
+	expected_kana VARCHAR NOT NULL
+);
+
+CREATE TABLE d_answered_data (
+	id INTEGER REFERENCES d_asked_data PRIMARY KEY,
+	answered_text VARCHAR NOT NULL,
+	answer_level INTEGER NOT NULL CHECK (answer_level BETWEEN 0 AND 100),
+	answered_date TIMESTAMPTZ NOT NULL,
+	active_answer_time_ms INTEGER NOT NULL,
+	full_answer_time_ms INTEGER NOT NULL,
+	full_spent_time_ms INTEGER NOT NULL,
+	audio_times INTEGER NOT NULL
+);
+
+INSERT INTO user_groups (group_name) VALUES ('dictation_quizzes');
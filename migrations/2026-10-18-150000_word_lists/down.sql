@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM group_memberships WHERE group_id = (SELECT id FROM user_groups WHERE group_name = 'jlpt_ordering');
+DELETE FROM user_groups WHERE group_name = 'jlpt_ordering';
+DELETE FROM group_memberships WHERE group_id = (SELECT id FROM user_groups WHERE group_name = 'frequency_ordering');
+DELETE FROM user_groups WHERE group_name = 'frequency_ordering';
+DROP TABLE word_list_entries;
+DROP TABLE word_lists;
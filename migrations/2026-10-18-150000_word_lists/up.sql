@@ -0,0 +1,22 @@
+-- Your SQL goes here
+CREATE TABLE word_lists (
+	id SERIAL PRIMARY KEY,
+	list_name VARCHAR NOT NULL UNIQUE,
+	list_type VARCHAR NOT NULL CHECK (list_type IN ('frequency', 'jlpt')),
+	imported TIMESTAMPTZ NOT NULL DEFAULT current_timestamp
+);
+
+-- The rank is the position in a frequency list (1 is the most frequent) or the JLPT level
+-- (5 is N5, the easiest). The entries are matched with the words by their readings.
+CREATE TABLE word_list_entries (
+	id SERIAL PRIMARY KEY,
+	list_id INTEGER NOT NULL REFERENCES word_lists ON DELETE CASCADE,
+	word VARCHAR NOT NULL,
+	reading VARCHAR NOT NULL,
+	rank INTEGER NOT NULL
+);
+
+CREATE INDEX word_list_entries_reading ON word_list_entries (reading);
+
+INSERT INTO user_groups (group_name) VALUES ('frequency_ordering');
+INSERT INTO user_groups (group_name) VALUES ('jlpt_ordering');
@@ -0,0 +1,12 @@
+-- Your SQL goes here
+-- New content of a skill nugget is introduced only after the user has reached
+-- the required level on all the prerequisites of the nugget.
+CREATE TABLE skill_prerequisites (
+	skill_id INTEGER NOT NULL REFERENCES skill_nuggets ON DELETE CASCADE,
+	prerequisite_id INTEGER NOT NULL REFERENCES skill_nuggets ON DELETE CASCADE,
+	required_level INTEGER NOT NULL DEFAULT 2 CHECK (required_level > 0),
+	PRIMARY KEY(skill_id, prerequisite_id),
+	CHECK (skill_id <> prerequisite_id)
+);
+
+CREATE INDEX skill_prerequisites_prerequisite_id ON skill_prerequisites (prerequisite_id);
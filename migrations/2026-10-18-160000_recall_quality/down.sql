@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE review_log DROP COLUMN recall;
+ALTER TABLE user_metrics DROP COLUMN easy_interval_percent;
+ALTER TABLE user_metrics DROP COLUMN hard_interval_percent;
+ALTER TABLE user_metrics DROP COLUMN max_good_audio_times;
+ALTER TABLE user_metrics DROP COLUMN fast_answer_percent;
+ALTER TABLE user_metrics DROP COLUMN slow_answer_percent;
@@ -0,0 +1,7 @@
+-- Your SQL goes here
+ALTER TABLE user_metrics ADD COLUMN slow_answer_percent INTEGER NOT NULL DEFAULT 200;
+ALTER TABLE user_metrics ADD COLUMN fast_answer_percent INTEGER NOT NULL DEFAULT 50;
+ALTER TABLE user_metrics ADD COLUMN max_good_audio_times INTEGER NOT NULL DEFAULT 2;
+ALTER TABLE user_metrics ADD COLUMN hard_interval_percent INTEGER NOT NULL DEFAULT 50;
+ALTER TABLE user_metrics ADD COLUMN easy_interval_percent INTEGER NOT NULL DEFAULT 130;
+ALTER TABLE review_log ADD COLUMN recall VARCHAR;
@@ -0,0 +1,15 @@
+-- Your SQL goes here
+-- The content edits done through the manager API. The snapshots are JSON:
+-- "after" is NULL if the item was deleted, and "before" is NULL if a revert re-created it.
+CREATE TABLE revisions (
+	id SERIAL PRIMARY KEY,
+	item_type VARCHAR NOT NULL CHECK (item_type IN ('word', 'question', 'answer', 'exercise', 'variant', 'comparison')),
+	item_id INTEGER NOT NULL,
+	editor_id INTEGER REFERENCES users ON DELETE SET NULL,
+	action VARCHAR NOT NULL CHECK (action IN ('update', 'delete', 'revert')),
+	before VARCHAR,
+	after VARCHAR,
+	edited TIMESTAMPTZ NOT NULL DEFAULT current_timestamp
+);
+
+CREATE INDEX revisions_item ON revisions (item_type, item_id);
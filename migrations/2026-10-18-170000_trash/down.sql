@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE due_items DROP COLUMN parked;
+DROP TABLE trash;
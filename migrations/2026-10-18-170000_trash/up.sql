@@ -0,0 +1,15 @@
+-- Your SQL goes here
+-- The content deleted through the manager API. The items are purged after a retention period.
+-- The words of a trashed audio bundle are trashed along with it: "cascaded_from" is the bundle.
+CREATE TABLE trash (
+	id SERIAL PRIMARY KEY,
+	item_type VARCHAR NOT NULL CHECK (item_type IN ('word', 'question', 'exercise', 'bundle', 'narrator')),
+	item_id INTEGER NOT NULL,
+	trashed_by INTEGER REFERENCES users ON DELETE SET NULL,
+	trashed TIMESTAMPTZ NOT NULL DEFAULT current_timestamp,
+	cascaded_from INTEGER REFERENCES trash ON DELETE CASCADE,
+	UNIQUE (item_type, item_id)
+);
+
+-- The due items of trashed content aren't asked until the content is restored.
+ALTER TABLE due_items ADD COLUMN parked BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,15 @@
+-- Your SQL goes here
+-- Scheduled publishing. Without a group, the item is published at publish_at and unpublished
+-- at unpublish_at, and the schedule is removed once it's applied. With a group, the item is
+-- published only for the members of the group between publish_at and unpublish_at.
+CREATE TABLE publish_schedules (
+	id SERIAL PRIMARY KEY,
+	item_type VARCHAR NOT NULL CHECK (item_type IN ('word', 'question', 'exercise', 'event')),
+	item_id INTEGER NOT NULL,
+	publish_at TIMESTAMPTZ,
+	unpublish_at TIMESTAMPTZ,
+	group_id INTEGER REFERENCES user_groups ON DELETE CASCADE,
+	UNIQUE (item_type, item_id),
+	CHECK (publish_at IS NOT NULL OR unpublish_at IS NOT NULL),
+	CHECK (publish_at < unpublish_at)
+);
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE q_answered_data DROP COLUMN practice;
+ALTER TABLE e_answered_data DROP COLUMN practice;
+ALTER TABLE w_answered_data DROP COLUMN practice;
+ALTER TABLE c_answered_data DROP COLUMN practice;
+ALTER TABLE a_answered_data DROP COLUMN practice;
+ALTER TABLE d_answered_data DROP COLUMN practice;
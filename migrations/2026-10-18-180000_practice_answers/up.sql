@@ -0,0 +1,14 @@
+-- Your SQL goes here
+ALTER TABLE q_answered_data ADD COLUMN practice BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE e_answered_data ADD COLUMN practice BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE w_answered_data ADD COLUMN practice BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE c_answered_data ADD COLUMN practice BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE a_answered_data ADD COLUMN practice BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE d_answered_data ADD COLUMN practice BOOLEAN NOT NULL DEFAULT false;
+
+UPDATE q_answered_data SET practice=true FROM pending_items WHERE pending_items.id=q_answered_data.id AND pending_items.practice;
+UPDATE e_answered_data SET practice=true FROM pending_items WHERE pending_items.id=e_answered_data.id AND pending_items.practice;
+UPDATE w_answered_data SET practice=true FROM pending_items WHERE pending_items.id=w_answered_data.id AND pending_items.practice;
+UPDATE c_answered_data SET practice=true FROM pending_items WHERE pending_items.id=c_answered_data.id AND pending_items.practice;
+UPDATE a_answered_data SET practice=true FROM pending_items WHERE pending_items.id=a_answered_data.id AND pending_items.practice;
+UPDATE d_answered_data SET practice=true FROM pending_items WHERE pending_items.id=d_answered_data.id AND pending_items.practice;
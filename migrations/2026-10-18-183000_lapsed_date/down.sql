@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE due_items DROP COLUMN lapsed_date;
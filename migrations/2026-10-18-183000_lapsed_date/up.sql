@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE due_items ADD COLUMN lapsed_date TIMESTAMPTZ;
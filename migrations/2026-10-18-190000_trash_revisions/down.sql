@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM revisions WHERE action IN ('trash', 'restore');
+ALTER TABLE revisions DROP CONSTRAINT revisions_action_check;
+ALTER TABLE revisions ADD CONSTRAINT revisions_action_check
+	CHECK (action IN ('update', 'delete', 'revert'));
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+-- Moving an item to the trash and restoring it are recorded as revisions too.
+-- Purging an item from the trash is recorded as a deletion without an editor.
+ALTER TABLE revisions DROP CONSTRAINT revisions_action_check;
+ALTER TABLE revisions ADD CONSTRAINT revisions_action_check
+	CHECK (action IN ('update', 'delete', 'revert', 'trash', 'restore'));
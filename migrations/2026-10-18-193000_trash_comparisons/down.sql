@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM trash WHERE item_type = 'comparison';
+ALTER TABLE trash DROP CONSTRAINT trash_item_type_check;
+ALTER TABLE trash ADD CONSTRAINT trash_item_type_check
+	CHECK (item_type IN ('word', 'question', 'exercise', 'bundle', 'narrator'));
@@ -0,0 +1,5 @@
+-- Your SQL goes here
+-- Comparison pairs deleted through the manager API go to the trash too.
+ALTER TABLE trash DROP CONSTRAINT trash_item_type_check;
+ALTER TABLE trash ADD CONSTRAINT trash_item_type_check
+	CHECK (item_type IN ('comparison', 'word', 'question', 'exercise', 'bundle', 'narrator'));
@@ -25,7 +25,7 @@ fn dispatch_events(conn: &Connection,
     use ganbare_backend::EventExperience;
 
     if let Some((Event { name, .. }, EventExperience { event_init, .. })) = ev_exp {
-        if name == "training" && event_init < chrono::offset::Utc::now() - *TRAINING_PERIOD {
+        if name == "training" && event_init < ganbare::clock::now() - *TRAINING_PERIOD {
 
             // User has trained for the whole training period; has he done posttest yet?
             if !event::is_done(conn, "posttest", user_id).err_500()?
@@ -110,6 +110,9 @@ pub fn settings_get(req: &mut Request) -> PencilResult {
     if user::check_user_group(&conn, sess.user_id, "nag_emails").err_500()? {
         context.insert("nag_emails", "on");
     }
+    let metrics = user::get_metrics(&conn, sess.user_id).err_500()?;
+    context.insert("timezone", metrics.timezone);
+    context.insert("day_rollover_hour", metrics.day_rollover_hour.to_string());
 
     req.app.render_template("settings.html", &context)
 }
@@ -117,13 +120,24 @@ pub fn settings_get(req: &mut Request) -> PencilResult {
 pub fn settings_post(req: &mut Request) -> PencilResult {
     let (conn, sess) = auth_user(req, "")?;
 
-    let nag_emails: &str = err_400!(req.form().get("nag_emails"), "Field nag_emails is missing!");
-
-    if nag_emails == "on" {
-        user::join_user_group_by_name(&conn, sess.user_id, "nag_emails").err_500()?;
-    } else {
-        user::remove_user_group_by_name(&conn, sess.user_id, "nag_emails").err_500()?;
-    }
+    let form = req.form();
+    let nag_emails: &str = err_400!(form.get("nag_emails"), "Field nag_emails is missing!");
+    let timezone: &str = err_400!(form.get("timezone"), "Field timezone is missing!");
+    let day_rollover_hour: &str = err_400!(form.get("day_rollover_hour"),
+                                           "Field day_rollover_hour is missing!");
+    let day_rollover_hour = err_400!(day_rollover_hour.parse::<i32>(),
+                                     "Field day_rollover_hour must be an integer!");
+
+    if let Err(e) = user::set_settings(&conn,
+                                       sess.user_id,
+                                       nag_emails == "on",
+                                       timezone,
+                                       day_rollover_hour) {
+        match *e.kind() {
+            errors::ErrorKind::InvalidInput => return Ok(bad_request("Invalid timezone or hour")),
+            _ => return Err(internal_error(e)),
+        }
+    };
 
     redirect("/settings", 303).refresh_cookie(&sess)
 }
@@ -63,6 +63,13 @@ lazy_static! {
             .unwrap_or(14))
     };
 
+    pub static ref TRASH_RETENTION: TimeDuration = {
+        dotenv::dotenv().ok();
+        TimeDuration::days(env::var("GANBARE_TRASH_RETENTION_DAYS")
+            .map(|s| s.parse().unwrap_or(30))
+            .unwrap_or(30))
+    };
+
     pub static ref PASSWORD_STRETCHING_TIME: Duration = {
         dotenv::dotenv().ok();
         Duration::from_millis(env::var("GANBARE_PASSWORD_STRETCHING_MS")
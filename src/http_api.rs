@@ -1,7 +1,5 @@
 
 use super::*;
-use chrono::offset::Utc;
-use diesel::Connection;
 use pencil::{abort, jsonify, Response, redirect};
 use pencil::helpers::{send_file_range, send_from_directory_range};
 use regex;
@@ -9,15 +7,22 @@ use std::io::{self, Read};
 use hyper::header::ContentLength;
 use serde_json;
 use ganbare_backend::time_it;
-use log::{info, debug};
+use log::{info, debug, warn};
 use lazy_static::lazy_static;
 
 use crate::{err_400, try_or, parse};
 use ganbare::audio;
+use ganbare::clock;
+use ganbare::pitch;
+use ganbare::pronunciation;
 use ganbare::quiz;
 use ganbare::models;
 use ganbare::skill;
 use ganbare::manage;
+use ganbare::revision;
+use ganbare::trash;
+use ganbare::publishing;
+use ganbare::word_import;
 use ganbare::event;
 use ganbare::user;
 use test;
@@ -82,7 +87,13 @@ pub fn quiz_audio(req: &mut Request) -> PencilResult {
     let asked_id =
         asked_id.parse::<i32>().expect("Pencil guarantees that Line ID should be an integer.");
 
-    let (file_name, mime_type) = audio::for_quiz(&conn, sess.user_id, asked_id).map_err(|e| match e.kind() {
+    let endpoint = req.endpoint().expect("Pencil guarantees this");
+    let audio = match endpoint.as_ref() {
+        "comparison_audio" => audio::for_comparison(&conn, sess.user_id, asked_id),
+        _ => audio::for_quiz(&conn, sess.user_id, asked_id),
+    };
+
+    let (file_name, mime_type) = audio.map_err(|e| match e.kind() {
                      &ErrorKind::FileNotFound => abort(404).unwrap_err(),
                      e => internal_error(e),
                  })?;
@@ -142,10 +153,36 @@ pub fn quiz_to_json(quiz: quiz::Quiz) -> PencilResult {
         Q(q_json) => jsonify(&q_json),
         E(e_json) => jsonify(&e_json),
         W(w_json) => jsonify(&w_json),
+        C(c_json) => jsonify(&c_json),
+        A(a_json) => jsonify(&a_json),
+        D(d_json) => jsonify(&d_json),
         F(future) => jsonify(&future),
     }
 }
 
+/// The quiz that follows an answer. If the answer was graded on the server, its result
+/// comes along as `answer_result`; without a next quiz, that's the only field.
+fn next_quiz_to_json(conn: &Connection,
+                     user_id: i32,
+                     asked_id: i32,
+                     new_quiz: Option<quiz::Quiz>)
+                     -> PencilResult {
+    let answer_result = quiz::answer_result(conn, user_id, asked_id).err_500()?;
+
+    match (new_quiz, answer_result) {
+        (new_quiz, Some(answer_result)) => {
+            let mut json = match new_quiz {
+                Some(quiz) => serde_json::to_value(&quiz).err_500()?,
+                None => serde_json::Value::Object(Default::default()),
+            };
+            json["answer_result"] = serde_json::to_value(&answer_result).err_500()?;
+            jsonify(&json)
+        }
+        (Some(quiz), None) => quiz_to_json(quiz),
+        (None, None) => jsonify(&()),
+    }
+}
+
 pub fn new_quiz(req: &mut Request) -> PencilResult {
     let (conn, sess) = auth_user(req, "")?;
 
@@ -200,9 +237,10 @@ fn parse_next_quiz_answer(req: &mut Request) -> Result<quiz::Answered> {
         Ok(quiz::Answered::W(models::WAnsweredData {
                                  id: id,
                                  audio_times: audio_times,
-                                 checked_date: Utc::now(),
+                                 checked_date: clock::now(),
                                  active_answer_time_ms: active_answer_time_ms,
                                  full_spent_time_ms: full_spent_time_ms,
+                                 practice: false, // Set by the server
                              }))
     } else if answer_type == "exercise" {
         let id = str::parse::<i32>(parse!(form.get("asked_id")))?;
@@ -216,11 +254,70 @@ fn parse_next_quiz_answer(req: &mut Request) -> Result<quiz::Answered> {
                                  id: id,
                                  audio_times: audio_times,
                                  active_answer_time_ms: active_answer_time_ms,
-                                 answered_date: Utc::now(),
+                                 answered_date: clock::now(),
                                  reflected_time_ms: reflected_time_ms,
                                  full_answer_time_ms: full_answer_time_ms,
                                  answer_level: answer_level,
                                  full_spent_time_ms: full_spent_time_ms,
+                                 practice: false, // Set by the server
+                             }))
+    } else if answer_type == "comparison" {
+        let id = str::parse::<i32>(parse!(form.get("asked_id")))?;
+        let answered_choice = str::parse::<i32>(parse!(form.get("answered_choice")))?;
+        let answered_choice = if answered_choice > 0 {
+            Some(answered_choice)
+        } else {
+            None
+        }; // Negatives mean that the comparison was unanswered (due to time limit)
+        let active_answer_time_ms = str::parse::<i32>(parse!(form.get("active_answer_time")))?;
+        let full_answer_time_ms = str::parse::<i32>(parse!(form.get("full_answer_time")))?;
+        let full_spent_time_ms = str::parse::<i32>(parse!(form.get("full_spent_time")))?;
+        Ok(quiz::Answered::C(models::CAnsweredData {
+                                 id: id,
+                                 answered_choice: answered_choice,
+                                 answered_date: clock::now(),
+                                 active_answer_time_ms: active_answer_time_ms,
+                                 full_answer_time_ms: full_answer_time_ms,
+                                 full_spent_time_ms: full_spent_time_ms,
+                                 practice: false, // Set by the server
+                             }))
+    } else if answer_type == "accent" {
+        let id = str::parse::<i32>(parse!(form.get("asked_id")))?;
+        let answered_nucleus = str::parse::<i32>(parse!(form.get("answered_nucleus")))?;
+        let answered_nucleus = if answered_nucleus >= 0 {
+            Some(answered_nucleus)
+        } else {
+            None
+        }; // Negatives mean that the pitch doesn't drop (heiban)
+        let active_answer_time_ms = str::parse::<i32>(parse!(form.get("active_answer_time")))?;
+        let full_answer_time_ms = str::parse::<i32>(parse!(form.get("full_answer_time")))?;
+        let full_spent_time_ms = str::parse::<i32>(parse!(form.get("full_spent_time")))?;
+        Ok(quiz::Answered::A(models::AAnsweredData {
+                                 id: id,
+                                 answered_nucleus: answered_nucleus,
+                                 answered_date: clock::now(),
+                                 active_answer_time_ms: active_answer_time_ms,
+                                 full_answer_time_ms: full_answer_time_ms,
+                                 full_spent_time_ms: full_spent_time_ms,
+                                 practice: false, // Set by the server
+                             }))
+    } else if answer_type == "dictation" {
+        let id = str::parse::<i32>(parse!(form.get("asked_id")))?;
+        let answered_text = parse!(form.get::<str>("answered_text"));
+        let audio_times = str::parse::<i32>(parse!(form.get("times_audio_played")))?;
+        let active_answer_time_ms = str::parse::<i32>(parse!(form.get("active_answer_time")))?;
+        let full_answer_time_ms = str::parse::<i32>(parse!(form.get("full_answer_time")))?;
+        let full_spent_time_ms = str::parse::<i32>(parse!(form.get("full_spent_time")))?;
+        Ok(quiz::Answered::D(models::DAnsweredData {
+                                 id: id,
+                                 answered_text: answered_text.to_owned(),
+                                 answer_level: 0, // Graded by the server
+                                 answered_date: clock::now(),
+                                 active_answer_time_ms: active_answer_time_ms,
+                                 full_answer_time_ms: full_answer_time_ms,
+                                 full_spent_time_ms: full_spent_time_ms,
+                                 audio_times: audio_times,
+                                 practice: false, // Set by the server
                              }))
     } else if answer_type == "question" {
         let id = str::parse::<i32>(parse!(form.get("asked_id")))?;
@@ -236,28 +333,107 @@ fn parse_next_quiz_answer(req: &mut Request) -> Result<quiz::Answered> {
         Ok(quiz::Answered::Q(models::QAnsweredData {
                                  id: id,
                                  answered_qa_id: answered_qa_id,
-                                 answered_date: Utc::now(),
+                                 answered_date: clock::now(),
                                  active_answer_time_ms: active_answer_time_ms,
                                  full_answer_time_ms: full_answer_time_ms,
                                  full_spent_time_ms: full_spent_time_ms,
+                                 practice: false, // Set by the server
                              }))
     } else {
         Err(ErrorKind::FormParseError.into())
     }
 }
 
+/// Links the recording that the user made while answering an exercise with the answer.
+/// The recording is specified with `event`, `quiz_number` and `rec_number`, like in
+/// `get_useraudio`. If the score affects the scheduling, the recording is scored right away;
+/// otherwise it's left for the background thread to score.
+fn register_exercise_recording(req: &mut Request,
+                               conn: &Connection,
+                               user_id: i32,
+                               answer: &quiz::Answered)
+                               -> Result<()> {
+    let asked_id = match *answer {
+        quiz::Answered::E(ref answered) => answered.id,
+        _ => return Ok(()),
+    };
+    let form = req.form();
+    let event_name = match form.get::<str>("event") {
+        Some(event_name) => event_name,
+        None => return Ok(()),
+    };
+    let quiz_number = str::parse::<usize>(parse!(form.get("quiz_number")))?;
+    let rec_number = str::parse::<usize>(parse!(form.get("rec_number")))?;
+
+    let (event, _) = event::require_ongoing(conn, event_name, user_id)?;
+    let filename = try_or!(event::get_userdata(conn,
+                                               &event,
+                                               user_id,
+                                               &format!("quiz_{}_rec_{}", quiz_number, rec_number))?,
+                           else return Err(ErrorKind::FileNotFound.into()));
+
+    pronunciation::register_recording(conn, user_id, asked_id, &filename.data)?;
+
+    if user::check_user_group(conn, user_id, "pronunciation_scheduling")? {
+        pronunciation::score(conn, &*AUDIO_DIR, &*USER_AUDIO_DIR, asked_id)?;
+    }
+    Ok(())
+}
+
 pub fn next_quiz(req: &mut Request) -> PencilResult {
     let (conn, sess) = auth_user(req, "")?;
 
     let answer = err_400!(parse_next_quiz_answer(req),
                           "Can't parse form data? {:?}",
                           req.form());
-    
-    let new_quiz = time_it!("next_quiz",
-                            conn.transaction(||
-                                quiz::get_next_quiz(&conn, sess.user_id, answer)
-                            ).err_500_debug(sess.user_id, &*req)
-                        )?;
+
+    let asked_id = answer.asked_id();
+    let idempotency_key = req.form().get::<str>("idempotency_key").map(str::to_owned);
+
+    // A retry or a second answer to the same item is ignored, so its recording is ignored too.
+    let awaits_answer = quiz::awaits_answer(&conn,
+                                            sess.user_id,
+                                            asked_id,
+                                            idempotency_key.as_ref().map(String::as_str))
+        .err_500()?;
+
+    if awaits_answer {
+        if let Err(e) = register_exercise_recording(req, &conn, sess.user_id, &answer) {
+            warn!("Couldn't register the recording of an exercise answer: {:?}", e);
+        }
+    }
+
+    let new_quiz = match idempotency_key {
+        Some(key) => time_it!("next_quiz",
+                              quiz::get_next_quiz_idempotent(&conn, sess.user_id, answer, &key)),
+        None => time_it!("next_quiz", quiz::get_next_quiz(&conn, sess.user_id, answer)),
+    };
+
+    let new_quiz = match new_quiz {
+        Ok(new_quiz) => new_quiz,
+        Err(e) => match *e.kind() {
+            ErrorKind::InvalidInput => return Ok(bad_request("Invalid answer or idempotency key.")),
+            _ => return Err(e).err_500_debug(sess.user_id, &*req),
+        },
+    };
+
+    next_quiz_to_json(&conn, sess.user_id, asked_id, new_quiz).refresh_cookie(&sess)
+}
+
+fn parse_cram_skill(req: &mut Request) -> Result<Option<i32>> {
+    match req.args().get("skill") {
+        Some(skill) => Ok(Some(skill.parse::<i32>()?)),
+        None => Ok(None),
+    }
+}
+
+pub fn new_cram_quiz(req: &mut Request) -> PencilResult {
+    let (conn, sess) = auth_user(req, "")?;
+
+    let skill = err_400!(parse_cram_skill(req), "Can't parse the skill nugget id");
+
+    let new_quiz = time_it!("new_cram_quiz",
+                            quiz::get_cram_quiz(&conn, sess.user_id, skill).err_500())?;
 
     match new_quiz {
             Some(quiz) => quiz_to_json(quiz),
@@ -266,11 +442,34 @@ pub fn next_quiz(req: &mut Request) -> PencilResult {
         .refresh_cookie(&sess)
 }
 
+pub fn next_cram_quiz(req: &mut Request) -> PencilResult {
+    let (conn, sess) = auth_user(req, "")?;
+
+    let skill = err_400!(parse_cram_skill(req), "Can't parse the skill nugget id");
+    let answer = err_400!(parse_next_quiz_answer(req),
+                          "Can't parse form data? {:?}",
+                          req.form());
+
+    let asked_id = answer.asked_id();
+
+    let new_quiz = match time_it!("next_cram_quiz",
+                                  quiz::get_next_cram_quiz(&conn, sess.user_id, answer, skill)) {
+        Ok(new_quiz) => new_quiz,
+        Err(e) => match *e.kind() {
+            ErrorKind::InvalidInput => return Ok(bad_request("Not a pending practice item.")),
+            _ => return Err(e).err_500_debug(sess.user_id, &*req),
+        },
+    };
+
+    next_quiz_to_json(&conn, sess.user_id, asked_id, new_quiz).refresh_cookie(&sess)
+}
+
 pub fn next_quiz_testing(req: &mut Request) -> PencilResult {
     let (conn, sess) = auth_user(req, "")?;
     let answer = err_400!(parse_next_quiz_answer(req),
                           "Can't parse form data? {:?}",
                           req.form());
+    let asked_id = answer.asked_id();
 
     let new_quiz = if let Some((ev, _)) =
         ganbare::event::is_ongoing(&conn, "pretest", sess.user_id).err_500()? {
@@ -285,14 +484,94 @@ pub fn next_quiz_testing(req: &mut Request) -> PencilResult {
         None
     };
 
-    match new_quiz {
-            Some(quiz) => quiz_to_json(quiz),
-            None => jsonify(&()),
-        }
-        .refresh_cookie(&sess)
+    next_quiz_to_json(&conn, sess.user_id, asked_id, new_quiz).refresh_cookie(&sess)
+}
+
+pub fn prefetch_quizzes(req: &mut Request) -> PencilResult {
+    let (conn, sess) = auth_user(req, "")?;
+
+    let count = match req.form().get::<str>("count") {
+        Some(count) => err_400!(count.parse::<usize>(), "Can't parse the count of quizzes"),
+        None => 10,
+    };
+
+    if count > 50 {
+        return Ok(bad_request("Too many quizzes requested at once! It must be 50 or less."));
+    }
+
+    let quizzes = time_it!("prefetch_quizzes",
+                           quiz::prefetch_quizzes(&conn, sess.user_id, count)
+                               .err_500_debug(sess.user_id, &*req))?;
+
+    let quizzes = quizzes.into_iter()
+        .map(|quiz| {
+            // A comparison plays two audio clips; the second one has an endpoint of its own.
+            let audio_urls = match quiz {
+                quiz::Quiz::C(ref c) => {
+                    vec![format!("/api/audio.mp3?{}", c.asked_id),
+                         format!("/api/comparison_audio.mp3?{}", c.asked_id)]
+                }
+                ref quiz => {
+                    quiz.asked_id()
+                        .map(|id| format!("/api/audio.mp3?{}", id))
+                        .into_iter()
+                        .collect()
+                }
+            };
+            let mut json = serde_json::to_value(&quiz)?;
+            json["audio_urls"] = serde_json::to_value(&audio_urls)?;
+            Ok(json)
+        })
+        .collect::<StdResult<Vec<_>, serde_json::Error>>()
+        .err_500()?;
+
+    jsonify(&quizzes).refresh_cookie(&sess)
+}
+
+const FORECAST_DAYS: usize = 30;
+
+pub fn forecast(req: &mut Request) -> PencilResult {
+    let (conn, sess) = auth_user(req, "")?;
+
+    let forecast = quiz::forecast(&conn, sess.user_id, FORECAST_DAYS)
+        .err_500_debug(sess.user_id, &*req)?;
+
+    jsonify(&forecast).refresh_cookie(&sess)
+}
+
+pub fn sync_answers(req: &mut Request) -> PencilResult {
+    let (conn, sess) = auth_user(req, "")?;
+
+    let mut text = String::new();
+    req.read_to_string(&mut text).err_500()?;
+
+    let answers: Vec<quiz::Answered> = err_400!(serde_json::from_str(&text),
+                                                "Error decoding JSON");
+
+    if answers.len() > 500 {
+        return Ok(bad_request("Too many answers at once! Send them in smaller batches."));
+    }
+
+    let results = time_it!("sync_answers",
+                           quiz::sync_answers(&conn, sess.user_id, answers)
+                               .err_500_debug(sess.user_id, &*req))?;
+
+    jsonify(&results).refresh_cookie(&sess)
 }
 
 
+/// Searches words, questions and skill nuggets. Only the editors see the unpublished items.
+pub fn search(req: &mut Request) -> PencilResult {
+    let (conn, sess) = auth_user(req, "")?;
+
+    let query = err_400!(req.args().get("q"), "q must be specified").to_owned();
+    let editor = user::check_user_group(&conn, sess.user_id, "editors").err_500()?;
+
+    let results = ganbare::search::search(&conn, &query, editor).err_500()?;
+
+    jsonify(&results).refresh_cookie(&sess)
+}
+
 pub fn get_item(req: &mut Request) -> PencilResult {
     let (conn, sess) = auth_user(req, "editors")?;
 
@@ -316,6 +595,11 @@ pub fn get_item(req: &mut Request) -> PencilResult {
                 .ok_or_else(|| abort(404).unwrap_err())?;
             jsonify(&item)
         }
+        "get_comparison" => {
+            let item = manage::get_comparison(&conn, id).err_500()?
+                .ok_or_else(|| abort(404).unwrap_err())?;
+            jsonify(&item)
+        }
         _ => return Err(internal_error("no such endpoint!")),
     };
 
@@ -333,13 +617,13 @@ pub fn del_item(req: &mut Request) -> PencilResult {
     let endpoint = req.endpoint().expect("Pencil guarantees this");
     let json = match endpoint.as_ref() {
         "del_narrator" => {
-            if !audio::del_narrator(&conn, id).err_500()? {
+            if !trash::trash(&conn, "narrator", id, sess.user_id).err_500()? {
                 return abort(404);
             }
             jsonify(&())
         }
         "del_bundle" => {
-            if !audio::del_bundle(&conn, id).err_500()? {
+            if !trash::trash(&conn, "bundle", id, sess.user_id).err_500()? {
                 return abort(404);
             }
             jsonify(&())
@@ -361,19 +645,25 @@ pub fn del_item(req: &mut Request) -> PencilResult {
             jsonify(&())
         }
         "del_word" => {
-            if manage::remove_word(&conn, id).err_500()?.is_none() {
+            if !trash::trash(&conn, "word", id, sess.user_id).err_500()? {
                 return abort(404);
             }
             jsonify(&())
         }
         "del_question" => {
-            if !manage::remove_question(&conn, id).err_500()? {
+            if !trash::trash(&conn, "question", id, sess.user_id).err_500()? {
                 return abort(404);
             }
             jsonify(&())
         }
         "del_exercise" => {
-            if !manage::remove_exercise(&conn, id).err_500()? {
+            if !trash::trash(&conn, "exercise", id, sess.user_id).err_500()? {
+                return abort(404);
+            }
+            jsonify(&())
+        }
+        "del_comparison" => {
+            if !trash::trash(&conn, "comparison", id, sess.user_id).err_500()? {
                 return abort(404);
             }
             jsonify(&())
@@ -455,6 +745,18 @@ pub fn get_all(req: &mut Request) -> PencilResult {
             let items = ganbare::event::get_all(&conn).err_500()?;
             jsonify(&items)
         }
+        "get_comparisons" => {
+            let items = manage::get_comparisons(&conn).err_500()?;
+            jsonify(&items)
+        }
+        "get_skill_prerequisites" => {
+            let items = skill::get_prerequisites(&conn).err_500()?;
+            jsonify(&items)
+        }
+        "get_word_lists" => {
+            let items = ganbare::word_lists::coverage(&conn).err_500()?;
+            jsonify(&items)
+        }
         _ => return Err(internal_error("no such endpoint!")),
     };
 
@@ -477,6 +779,14 @@ pub fn get_user_details(req: &mut Request) -> PencilResult {
             let items = skill::get_asked_items(&conn, id).err_500()?;
             jsonify(&items)
         }
+        "get_user_forecast" => {
+            let forecast = quiz::forecast(&conn, id, FORECAST_DAYS).err_500()?;
+            jsonify(&forecast)
+        }
+        "get_pronunciation_scores" => {
+            let scores = pronunciation::get_scores(&conn, id).err_500()?;
+            jsonify(&scores)
+        }
         _ => return Err(internal_error("no such endpoint!")),
     };
 
@@ -501,6 +811,9 @@ pub fn set_published(req: &mut Request) -> PencilResult {
         "publish_exercises" => {
             manage::publish_exercise(&conn, id, true).err_500()?;
         }
+        "publish_comparisons" => {
+            manage::publish_comparison(&conn, id, true).err_500()?;
+        }
         "unpublish_words" => {
             manage::publish_word(&conn, id, false).err_500()?;
         }
@@ -510,6 +823,9 @@ pub fn set_published(req: &mut Request) -> PencilResult {
         "unpublish_exercises" => {
             manage::publish_exercise(&conn, id, false).err_500()?;
         }
+        "unpublish_comparisons" => {
+            manage::publish_comparison(&conn, id, false).err_500()?;
+        }
         _ => return Err(internal_error("no such endpoint!")),
     };
     let mut resp = Response::new_empty();
@@ -544,10 +860,16 @@ pub fn update_item(req: &mut Request) -> PencilResult {
 
             let item = err_400!(serde_json::from_str(&text), "Error decoding JSON");
 
-            let updated_item = try_or!(
-                manage::update_word(&conn, id, item, &*IMAGES_DIR).err_500()?,
-                else return abort(404)
-            );
+            let updated_item = match revision::track(&conn, sess.user_id, "word", id, || {
+                manage::update_word(&conn, id, item, &*IMAGES_DIR)
+            }) {
+                Ok(item) => item,
+                Err(e) => match *e.kind() {
+                    ErrorKind::InvalidAccent(ref reason) => return Ok(bad_request(reason)),
+                    _ => return Err(internal_error(e)),
+                },
+            };
+            let updated_item = try_or!(updated_item, else return abort(404));
 
             json = jsonify(&updated_item);
 
@@ -557,7 +879,9 @@ pub fn update_item(req: &mut Request) -> PencilResult {
             let item = err_400!(serde_json::from_str(&text), "Error decoding JSON");
 
             let updated_item = try_or!(
-                manage::update_exercise(&conn, id, item).err_500()?,
+                revision::track(&conn, sess.user_id, "exercise", id, || {
+                    manage::update_exercise(&conn, id, item)
+                }).err_500()?,
                 else return abort(404)
             );
 
@@ -568,18 +892,42 @@ pub fn update_item(req: &mut Request) -> PencilResult {
             let item = err_400!(serde_json::from_str(&text), "Error decoding JSON");
 
             let updated_item = try_or!(
-                manage::update_question(&conn, id, item).err_500()?,
+                revision::track(&conn, sess.user_id, "question", id, || {
+                    manage::update_question(&conn, id, item)
+                }).err_500()?,
                 else return abort(404)
             );
 
             json = jsonify(&updated_item);
         }
+        "update_comparison" => {
+
+            let item = err_400!(serde_json::from_str(&text), "Error decoding JSON");
+
+            let updated_item = match revision::track(&conn, sess.user_id, "comparison", id, || {
+                manage::update_comparison(&conn, id, item)
+            }) {
+                Ok(item) => item,
+                Err(e) => match *e.kind() {
+                    ErrorKind::InvalidInput => {
+                        return Ok(bad_request("The words of a pair must differ and read the same."))
+                    }
+                    ErrorKind::InvalidAccent(ref reason) => return Ok(bad_request(reason)),
+                    _ => return Err(internal_error(e)),
+                },
+            };
+            let updated_item = try_or!(updated_item, else return abort(404));
+
+            json = jsonify(&updated_item);
+        }
         "update_answer" => {
 
             let item = err_400!(serde_json::from_str(&text), "Error decoding JSON");
 
             let updated_item = try_or!(
-                manage::update_answer(&conn, id, item, &*IMAGES_DIR).err_500()?,
+                revision::track(&conn, sess.user_id, "answer", id, || {
+                    manage::update_answer(&conn, id, item, &*IMAGES_DIR)
+                }).err_500()?,
                 else return abort(404)
             );
 
@@ -590,7 +938,9 @@ pub fn update_item(req: &mut Request) -> PencilResult {
             let item = err_400!(serde_json::from_str(&text), "Error decoding JSON");
 
             let updated_item = try_or!(
-                manage::update_variant(&conn, id, item).err_500()?,
+                revision::track(&conn, sess.user_id, "variant", id, || {
+                    manage::update_variant(&conn, id, item)
+                }).err_500()?,
                 else return abort(404)
             );
 
@@ -759,6 +1109,244 @@ pub fn post_exercise(req: &mut Request) -> PencilResult {
     redirect(&new_url, 303).refresh_cookie(&sess)
 }
 
+pub fn post_comparison(req: &mut Request) -> PencilResult {
+
+    let (conn, sess) = auth_user(req, "editors")?;
+
+    let mut text = String::new();
+    req.read_to_string(&mut text).err_500()?;
+
+    use ganbare::models::{UpdateComparisonPair, NewComparisonPair};
+
+    let pair: UpdateComparisonPair =
+        err_400!(serde_json::from_str(&text), "Error when parsing the JSON.");
+
+    fn parse_pair(pair: &UpdateComparisonPair) -> Result<NewComparisonPair> {
+        let pair = NewComparisonPair {
+            skill_id: pair.skill_id.ok_or_else(|| Error::from_kind(ErrorKind::FormParseError))?,
+            word_a: pair.word_a.ok_or_else(|| Error::from_kind(ErrorKind::FormParseError))?,
+            word_b: pair.word_b.ok_or_else(|| Error::from_kind(ErrorKind::FormParseError))?,
+            skill_level: pair.skill_level.unwrap_or(0),
+        };
+        Ok(pair)
+    }
+
+    let new_pair = err_400!(parse_pair(&pair), "Fields missing from UpdateComparisonPair");
+
+    let id = match manage::post_comparison(&conn, new_pair) {
+        Ok(id) => id,
+        Err(e) => match *e.kind() {
+            ErrorKind::InvalidInput => {
+                return Ok(bad_request("The words of a pair must differ and read the same."))
+            }
+            ErrorKind::InvalidAccent(ref reason) => return Ok(bad_request(reason)),
+            _ => return Err(internal_error(e)),
+        },
+    };
+
+    let new_url = format!("/api/comparisons/{}", id);
+
+    redirect(&new_url, 303).refresh_cookie(&sess)
+}
+
+/// Sets or removes the prerequisite `prerequisite_id` of the skill nugget `skill_id`.
+/// When setting, the body is a JSON `SkillPrerequisite` with the same ids as in the URL.
+pub fn skill_prerequisite(req: &mut Request) -> PencilResult {
+    let (conn, sess) = auth_user(req, "editors")?;
+
+    let skill_id = req.view_args
+        .remove("skill_id")
+        .expect("Pencil guarantees that Line ID should exist as an arg.");
+    let skill_id =
+        skill_id.parse::<i32>().expect("Pencil guarantees that Line ID should be an integer.");
+    let prerequisite_id = req.view_args
+        .remove("prerequisite_id")
+        .expect("Pencil guarantees that Line ID should exist as an arg.");
+    let prerequisite_id = prerequisite_id.parse::<i32>()
+        .expect("Pencil guarantees that Line ID should be an integer.");
+
+    let endpoint = req.endpoint().expect("Pencil guarantees this");
+    let json = match endpoint.as_ref() {
+        "set_skill_prerequisite" => {
+
+            use ganbare::models::SkillPrerequisite;
+
+            let mut text = String::new();
+            req.read_to_string(&mut text).err_500()?;
+            let prerequisite: SkillPrerequisite = err_400!(serde_json::from_str(&text),
+                                                           "Can't decode JSON: {:?}",
+                                                           &text);
+
+            if prerequisite.skill_id != skill_id || prerequisite.prerequisite_id != prerequisite_id {
+                return Ok(bad_request("the ids in the URL must be the same as in the JSON \
+                                       content!"));
+            }
+
+            let prerequisite = match skill::set_prerequisite(&conn, &prerequisite) {
+                Ok(prerequisite) => prerequisite,
+                Err(e) => match *e.kind() {
+                    ErrorKind::InvalidInput => {
+                        return Ok(bad_request("A skill can't be its own prerequisite, \
+                                               even indirectly, and the level must be positive."))
+                    }
+                    _ => return Err(internal_error(e)),
+                },
+            };
+            jsonify(&prerequisite)
+        }
+        "del_skill_prerequisite" => {
+            let prerequisite = try_or!(skill::remove_prerequisite(&conn, skill_id, prerequisite_id)
+                                           .err_500()?,
+                else return abort(404));
+            jsonify(&prerequisite)
+        }
+        _ => return Err(internal_error("no such endpoint!")),
+    };
+
+    json.refresh_cookie(&sess)
+}
+
+pub fn revisions(req: &mut Request) -> PencilResult {
+    let (conn, sess) = auth_user(req, "editors")?;
+
+    let id = req.view_args
+        .remove("id")
+        .expect("Pencil guarantees that Line ID should exist as an arg.");
+    let id = id.parse::<i32>().expect("Pencil guarantees that Line ID should be an integer.");
+
+    let endpoint = req.endpoint().expect("Pencil guarantees this");
+    let json = match endpoint.as_ref() {
+        "get_revisions" => {
+            let item_type = req.view_args
+                .remove("item_type")
+                .expect("Pencil guarantees that Line ID should exist as an arg.");
+            if !revision::ITEM_TYPES.contains(&item_type.as_str()) {
+                return abort(404);
+            }
+            let revisions = revision::get_history(&conn, &item_type, id).err_500()?;
+            jsonify(&revisions)
+        }
+        "revert_revision" => {
+            let revision = try_or!(revision::revert(&conn, sess.user_id, id).err_500()?,
+                else return abort(404));
+            jsonify(&revision)
+        }
+        _ => return Err(internal_error("no such endpoint!")),
+    };
+
+    json.refresh_cookie(&sess)
+}
+
+pub fn get_trash(req: &mut Request) -> PencilResult {
+    let (conn, sess) = auth_user(req, "editors")?;
+
+    let items = trash::get_all(&conn).err_500()?;
+
+    jsonify(&items).refresh_cookie(&sess)
+}
+
+pub fn restore_trashed(req: &mut Request) -> PencilResult {
+    let (conn, sess) = auth_user(req, "editors")?;
+
+    let item_type = req.view_args
+        .remove("item_type")
+        .expect("Pencil guarantees that Line ID should exist as an arg.");
+    let id = req.view_args
+        .remove("id")
+        .expect("Pencil guarantees that Line ID should exist as an arg.");
+    let id = id.parse::<i32>().expect("Pencil guarantees that Line ID should be an integer.");
+
+    if !trash::ITEM_TYPES.contains(&item_type.as_str()) {
+        return abort(404);
+    }
+
+    if !trash::restore(&conn, &item_type, id, sess.user_id).err_500()? {
+        return abort(404);
+    }
+
+    jsonify(&()).refresh_cookie(&sess)
+}
+
+/// Imports a zip archive of audio files and a TSV manifest, uploaded as "archive".
+/// With the form field "dry_run" set, only validates it. Responds with a report of
+/// every line of the manifest; if the archive had errors, nothing is imported.
+pub fn import_words(req: &mut Request) -> PencilResult {
+    let (conn, sess) = auth_user(req, "editors")?;
+
+    let dry_run = req.form().get("dry_run").map_or(false, |v| v == "true");
+    let archive = err_400!(req.files().get("archive"), "archive missing").path.clone();
+
+    let report = match word_import::import(&conn, &archive, &*AUDIO_DIR, !dry_run) {
+        Ok(report) => report,
+        Err(e) => match *e.kind() {
+            ErrorKind::InvalidImportArchive(ref reason) => return Ok(bad_request(reason)),
+            _ => return Err(internal_error(e)),
+        },
+    };
+
+    let mut resp = jsonify(&report)?;
+    if !dry_run && !report.committed {
+        resp.status_code = 400;
+    }
+    resp.refresh_cookie(&sess)
+}
+
+pub fn get_schedules(req: &mut Request) -> PencilResult {
+    let (conn, sess) = auth_user(req, "editors")?;
+
+    let schedules = publishing::get_all(&conn).err_500()?;
+
+    jsonify(&schedules).refresh_cookie(&sess)
+}
+
+pub fn schedule_publishing(req: &mut Request) -> PencilResult {
+    let (conn, sess) = auth_user(req, "editors")?;
+
+    let item_type = req.view_args
+        .remove("item_type")
+        .expect("Pencil guarantees that Line ID should exist as an arg.");
+    let id = req.view_args
+        .remove("id")
+        .expect("Pencil guarantees that Line ID should exist as an arg.");
+    let id = id.parse::<i32>().expect("Pencil guarantees that Line ID should be an integer.");
+
+    if !publishing::ITEM_TYPES.contains(&item_type.as_str()) {
+        return abort(404);
+    }
+
+    let endpoint = req.endpoint().expect("Pencil guarantees this");
+    let json = match endpoint.as_ref() {
+        "set_schedule" => {
+            let mut text = String::new();
+            req.read_to_string(&mut text).err_500()?;
+            let schedule: publishing::Schedule = err_400!(serde_json::from_str(&text),
+                                                          "Error decoding JSON");
+
+            let schedule = match publishing::set_schedule(&conn, &item_type, id, &schedule) {
+                Ok(schedule) => schedule,
+                Err(e) => match *e.kind() {
+                    ErrorKind::InvalidInput => {
+                        return Ok(bad_request("A schedule needs a publishing or an \
+                                               unpublishing time, and the publishing must \
+                                               come first."))
+                    }
+                    _ => return Err(internal_error(e)),
+                },
+            };
+            jsonify(&schedule)
+        }
+        "del_schedule" => {
+            if !publishing::remove_schedule(&conn, &item_type, id).err_500()? {
+                return abort(404);
+            }
+            jsonify(&())
+        }
+        _ => return Err(internal_error("no such endpoint!")),
+    };
+
+    json.refresh_cookie(&sess)
+}
+
 pub fn save_eventdata(req: &mut Request) -> PencilResult {
     let (conn, sess) = auth_user(req, "")?;
 
@@ -813,6 +1401,18 @@ pub fn user(req: &mut Request) -> PencilResult {
             ganbare::user::remove_user_group_by_id(&conn, user_id, group_id).err_500()?;
             jsonify(&())
         }
+        "unsuspend_due_item" => {
+
+            let due_item_id = req.view_args
+                .remove("due_item_id")
+                .expect("Pencil guarantees that Line ID should exist as an arg.");
+            let due_item_id = due_item_id.parse::<i32>()
+                .expect("Pencil guarantees that Line ID should be an integer.");
+
+            let due_item = try_or!(manage::unsuspend_due_item(&conn, user_id, due_item_id).err_500()?,
+                else return abort(404));
+            jsonify(&due_item)
+        }
         "set_metrics" => {
 
             use ganbare::models::UpdateUserMetrics;
@@ -901,6 +1501,60 @@ pub fn post_useraudio(req: &mut Request) -> PencilResult {
     jsonify(&(quiz_number, rec_number)).refresh_cookie(&sess)
 }
 
+/// The pitch contour of an audio file. If the recording of the user is specified
+/// with `event`, `quiz_number` and `rec_number`, its contour is returned too.
+/// The editors may get the contour of any file, the learners only of their exercises.
+pub fn get_pitch(req: &mut Request) -> PencilResult {
+    let (conn, sess) = auth_user(req, "")?;
+
+    let id =
+        req.view_args.get("id").expect("Pencil guarantees that Line ID should exist as an arg.");
+    let id = id.parse::<i32>().expect("Pencil guarantees that Line ID should be an integer.");
+
+    if !user::check_user_group(&conn, sess.user_id, "editors").err_500()? &&
+       !pitch::is_exercise_audio_of(&conn, sess.user_id, id).err_500()? {
+        return abort(401);
+    }
+
+    let audio = pitch::for_audio_file(&conn, &*AUDIO_DIR, id).map_err(|e| match e.kind() {
+                     &ErrorKind::FileNotFound => abort(404).unwrap_err(),
+                     e => internal_error(e),
+                 })?;
+
+    let event_name = req.args().get("event").map(|s| s.to_owned());
+    let recording = if let Some(event_name) = event_name {
+        let quiz_number = err_400!(req.args().get("quiz_number").and_then(|d| d.parse::<usize>().ok()),
+                     "quiz_number must be specified");
+        let rec_number = err_400!(req.args().get("rec_number").and_then(|d| d.parse::<usize>().ok()),
+                     "rec_number must be specified");
+
+        let (event, _) = event::require_ongoing(&conn, &event_name, sess.user_id).err_401()?;
+
+        let filename = try_or!(
+            event::get_userdata(&conn,
+                                &event,
+                                sess.user_id,
+                                &format!("quiz_{}_rec_{}", quiz_number, rec_number)
+                            ).err_500()?,
+            else return abort(404)
+        );
+
+        Some(pitch::for_user_audio(&conn, &*USER_AUDIO_DIR, sess.user_id, &filename.data)
+            .map_err(|e| match e.kind() {
+                         &ErrorKind::FileNotFound => abort(404).unwrap_err(),
+                         e => internal_error(e),
+                     })?)
+    } else {
+        None
+    };
+
+    jsonify(&pitch::PitchOverlay {
+                audio: audio,
+                recording: recording,
+            })
+            .refresh_cookie(&sess)
+}
+
 pub fn get_useraudio(req: &mut Request) -> PencilResult {
 
     let (conn, sess) = auth_user(req, "")?;
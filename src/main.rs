@@ -67,6 +67,7 @@ lazy_static! {
 pub fn background_control_thread() {
     use std::thread::sleep;
     use std::error::Error;
+    use std::time::Instant;
 
     let conn;
     loop {
@@ -89,6 +90,9 @@ pub fn background_control_thread() {
         .credentials(EMAIL_SMTP_USERNAME.as_str(), EMAIL_SMTP_PASSWORD.as_str())
         .build();
 
+    let mut trash_purged: Option<Instant> = None;
+    let mut recordings_scored: Option<Instant> = None;
+
     loop {
         sleep(Duration::from_secs(5));
 
@@ -136,6 +140,56 @@ pub fn background_control_thread() {
             }
         }
 
+        // A retry can't come later than the session of the original request expires
+        match ganbare::quiz::clean_old_answer_submissions(&conn, *SESSION_EXPIRE) {
+            Ok(count) => {
+                if count != 0 {
+                    info!("Deleted {} old answer submissions.", count);
+                }
+            }
+            Err(e) => {
+                error!("background_control_thread::clean_old_answer_submissions: Error: {}",
+                       e)
+            }
+        }
+
+        match ganbare::publishing::apply_schedules(&conn) {
+            Ok(count) => {
+                if count != 0 {
+                    info!("Applied {} scheduled publishings.", count);
+                }
+            }
+            Err(e) => {
+                error!("background_control_thread::apply_schedules: Error: {}",
+                       e)
+            }
+        }
+
+        // Scoring decodes audio, so the recordings are scored in batches once a minute.
+        // The recordings of the users whose scheduling depends on it are scored when answering.
+        if recordings_scored.map_or(true, |t| t.elapsed() > Duration::from_secs(60)) {
+            recordings_scored = Some(Instant::now());
+            match ganbare::pronunciation::score_unscored(&conn, &*AUDIO_DIR, &*USER_AUDIO_DIR) {
+                Ok(count) => {
+                    if count != 0 {
+                        info!("Scored {} exercise recordings.", count);
+                    }
+                }
+                Err(e) => {
+                    error!("background_control_thread::score_unscored: Error: {}",
+                           e)
+                }
+            }
+        }
+
+        // Purging goes through the whole trash, so it's done only once an hour
+        if trash_purged.map_or(true, |t| t.elapsed() > Duration::from_secs(60 * 60)) {
+            trash_purged = Some(Instant::now());
+            if let Err(e) = ganbare::trash::purge(&conn, *TRASH_RETENTION) {
+                error!("background_control_thread::trash::purge: Error: {}", e)
+            }
+        }
+
         match AUDIO_CACHE.clean_expired() {
             Ok((remaining, removed)) if removed > 0 => {
                 debug!("Removed an old temp audio recordings. Remaining: {}, Removed: {}",
@@ -483,6 +537,7 @@ pub fn main() {
     app.post("/api/user_audio?event=<event_name:string>",
              "post_useraudio",
              http_api::post_useraudio);
+    app.get("/api/search", "search", http_api::search);
     app.get("/api/nuggets", "get_nuggets", http_api::get_all);
     app.get("/api/users", "get_users", http_api::get_all);
     app.get("/api/users/<id:int>/skills",
@@ -491,6 +546,12 @@ pub fn main() {
     app.get("/api/users/<id:int>/asked_items",
             "get_asked_items",
             http_api::get_user_details);
+    app.get("/api/users/<id:int>/forecast",
+            "get_user_forecast",
+            http_api::get_user_details);
+    app.get("/api/users/<id:int>/pronunciation_scores",
+            "get_pronunciation_scores",
+            http_api::get_user_details);
     app.get("/api/events", "get_events", http_api::get_all);
     app.put("/api/events/<id:int>",
             "update_event",
@@ -525,7 +586,25 @@ pub fn main() {
     app.get("/api/words/<id:int>", "get_word", http_api::get_item);
     app.post("/api/questions", "post_question", http_api::post_question);
     app.post("/api/exercises", "post_exercise", http_api::post_exercise);
+    app.get("/api/comparisons", "get_comparisons", http_api::get_all);
+    app.get("/api/comparisons/<id:int>",
+            "get_comparison",
+            http_api::get_item);
+    app.post("/api/comparisons", "post_comparison", http_api::post_comparison);
+    app.put("/api/comparisons/<id:int>",
+            "update_comparison",
+            http_api::update_item);
+    app.delete("/api/comparisons/<id:int>",
+               "del_comparison",
+               http_api::del_item);
+    app.put("/api/comparisons/<id:int>?publish",
+            "publish_comparisons",
+            http_api::set_published);
+    app.put("/api/comparisons/<id:int>?unpublish",
+            "unpublish_comparisons",
+            http_api::set_published);
     app.delete("/api/words/<id:int>", "del_word", http_api::del_item);
+    app.post("/api/words/import", "import_words", http_api::import_words);
     app.delete("/api/questions/<id:int>",
                "del_question",
                http_api::del_item);
@@ -538,15 +617,28 @@ pub fn main() {
     app.put("/api/users/<user_id:int>?remove_group=<group_id:int>",
             "remove_group",
             http_api::user);
+    app.put("/api/users/<user_id:int>?unsuspend=<due_item_id:int>",
+            "unsuspend_due_item",
+            http_api::user);
     app.put("/api/users/<user_id:int>?settings=metrics",
             "set_metrics",
             http_api::user);
     app.get("/api/groups", "get_groups", http_api::get_all);
+    app.get("/api/word_lists", "get_word_lists", http_api::get_all);
     app.delete("/api/users/<id:int>", "del_user", http_api::del_item);
     app.delete("/api/users/<id:int>/due_and_pending_items",
                "del_due_and_pending_items",
                http_api::del_item);
     app.delete("/api/skills/<id:int>", "del_skill", http_api::del_item);
+    app.get("/api/skill_prerequisites",
+            "get_skill_prerequisites",
+            http_api::get_all);
+    app.put("/api/skills/<skill_id:int>/prerequisites/<prerequisite_id:int>",
+            "set_skill_prerequisite",
+            http_api::skill_prerequisite);
+    app.delete("/api/skills/<skill_id:int>/prerequisites/<prerequisite_id:int>",
+               "del_skill_prerequisite",
+               http_api::skill_prerequisite);
     app.delete("/api/events/<id:int>/<user_id:int>",
                "del_event_exp",
                http_api::del_item);
@@ -581,9 +673,33 @@ pub fn main() {
     app.put("/api/exercises/variants/<id:int>",
             "update_variant",
             http_api::update_item);
+    app.get("/api/revisions/<item_type:string>/<id:int>",
+            "get_revisions",
+            http_api::revisions);
+    app.post("/api/revisions/<id:int>/revert",
+             "revert_revision",
+             http_api::revisions);
+    app.get("/api/trash", "get_trash", http_api::get_trash);
+    app.post("/api/trash/<item_type:string>/<id:int>/restore",
+             "restore_trashed",
+             http_api::restore_trashed);
+    app.get("/api/schedules", "get_schedules", http_api::get_schedules);
+    app.put("/api/schedules/<item_type:string>/<id:int>",
+            "set_schedule",
+            http_api::schedule_publishing);
+    app.delete("/api/schedules/<item_type:string>/<id:int>",
+               "del_schedule",
+               http_api::schedule_publishing);
 
     app.get("/api/new_quiz", "new_quiz", http_api::new_quiz);
     app.post("/api/next_quiz", "next_quiz", http_api::next_quiz);
+    app.post("/api/prefetch_quizzes",
+             "prefetch_quizzes",
+             http_api::prefetch_quizzes);
+    app.post("/api/sync_answers", "sync_answers", http_api::sync_answers);
+    app.get("/api/forecast", "forecast", http_api::forecast);
+    app.get("/api/cram_quiz", "new_cram_quiz", http_api::new_cram_quiz);
+    app.post("/api/cram_quiz", "next_cram_quiz", http_api::next_cram_quiz);
 
     app.get("/api/new_quiz_testing",
             "new_quiz_testing",
@@ -595,9 +711,15 @@ pub fn main() {
     app.get("/api/audio/<audio_name:string>",
             "get_audio",
             http_api::get_audio);
+    app.get("/api/audio/<id:int>/pitch",
+            "get_pitch",
+            http_api::get_pitch);
     app.get("/api/audio.mp3?<audio_name:string>",
             "quiz_audio",
             http_api::quiz_audio);
+    app.get("/api/comparison_audio.mp3?<audio_name:string>",
+            "comparison_audio",
+            http_api::quiz_audio);
     app.get("/api/images/<filename:string>",
             "get_image",
             http_api::get_image);
@@ -246,7 +246,12 @@ pub fn add_word_post(req: &mut Request) -> PencilResult {
 
     let word = parse_form(req).map_err(|_| abort(400).unwrap_err())?;
 
-    manage::create_or_update_word(&conn, word, &*AUDIO_DIR).err_500()?;
+    if let Err(e) = manage::create_or_update_word(&conn, word, &*AUDIO_DIR) {
+        match *e.kind() {
+            ErrorKind::InvalidAccent(ref reason) => return Ok(bad_request(reason)),
+            _ => return Err(internal_error(e)),
+        }
+    }
 
     redirect("/add_word", 303).refresh_cookie(&sess)
 }
@@ -32,9 +32,11 @@ $("#settingsMenu").click(function( event ) { event.stopPropagation(); });
 
 function moraize(word: string): {mora:string, rising: boolean, falling: boolean, flatEnd: boolean}[] {
 	console.log(word);
+	// The words are kana, and the small kana join the preceding mora like in the backend.
+	// The romaji is only used for the transcriptions on the info page.
 	function isYouon(i: number): boolean {
 		let c = word.charAt(i);
-		return (c === "ゃ" || c === "ゅ" || c === "ょ" || c === "y" || c === "h" || c === "s")
+		return ("ぁぃぅぇぉゃゅょゎァィゥェォャュョヮ".indexOf(c) >= 0 || c === "y" || c === "h" || c === "s")
 	};
 
 	function isVowel(i: number): boolean {
@@ -48,7 +50,7 @@ function moraize(word: string): {mora:string, rising: boolean, falling: boolean,
 	}
 
 	function isFalling(i: number): boolean {
-		return (word.charAt(i) === "・")
+		return (word.charAt(i) === "・" || word.charAt(i) === "*")
 	}
 
 	function isFlatEnd(i: number): boolean {
@@ -16,6 +16,76 @@ function stripAccents(word: string): string {
 	return word.replace('・', '').replace('＝', '').replace('／', '');
 }
 
+/* The accent markup rules are the same as in the accent module of the backend:
+words are written in kana, and only sentences, where ／ marks the rise of a phrase,
+may contain anything else. */
+
+const SMALL_KANA = "ぁぃぅぇぉゃゅょゎァィゥェォャュョヮ";
+
+function normalizeAccent(word: string): string {
+	let normalized = (<any>word).normalize ? (<any>word).normalize("NFKC") : word;
+	return normalized.replace(/=/g, "＝").replace(/\//g, "／");
+}
+
+function isKana(c: string): boolean {
+	let code = c.charCodeAt(0);
+	return (code >= 0x3041 && code <= 0x3096) || (code >= 0x309D && code <= 0x309E)
+		|| (code >= 0x30A1 && code <= 0x30FA) || (code >= 0x30FC && code <= 0x30FE);
+}
+
+function isNucleusMark(c: string): boolean {
+	return c === "・" || c === "*";
+}
+
+/* Returns the reason why the markup is invalid, or null if it's valid. */
+function accentError(word: string): string {
+	word = normalizeAccent(word);
+
+	if (word.indexOf("／") >= 0) {
+		let inPhrase = false;
+		let prevWasMark = true;
+		for (let i = 0; i < word.length; i++) {
+			let c = word.charAt(i);
+			if (c === "／") {
+				if (inPhrase) { return "A phrase doesn't end before the next ／"; }
+				inPhrase = true;
+				prevWasMark = true;
+			} else if (isNucleusMark(c) || c === "＝") {
+				if (!inPhrase || prevWasMark) { return "A stray accent mark"; }
+				inPhrase = false;
+				prevWasMark = true;
+			} else {
+				prevWasMark = false;
+			}
+		}
+		return inPhrase ? "The last phrase doesn't end" : null;
+	}
+
+	let morae = 0;
+	let nucleus = -1;
+	let flatEnd = false;
+	for (let i = 0; i < word.length; i++) {
+		let c = word.charAt(i);
+		if (flatEnd) {
+			return "Nothing may follow ＝";
+		} else if (isNucleusMark(c)) {
+			if (morae === 0) { return "The accent mark precedes every mora"; }
+			if (nucleus >= 0) { return "More than one accent mark"; }
+			nucleus = morae - 1;
+		} else if (c === "＝") {
+			if (morae === 0 || nucleus >= 0) { return "＝ only ends a heiban word"; }
+			flatEnd = true;
+		} else if (SMALL_KANA.indexOf(c) >= 0) {
+			if (morae === 0 || nucleus === morae - 1) { return c + " doesn't belong to any mora"; }
+		} else if (isKana(c)) {
+			morae++;
+		} else {
+			return c + " is not kana";
+		}
+	}
+	return morae === 0 ? "No morae" : null;
+}
+
 function createSemaphore(count: number) : (argument?: any) =>void {
 
 	var semaphore = count;
@@ -38,8 +108,10 @@ function createSemaphore(count: number) : (argument?: any) =>void {
 
 function moraize(word: string): {mora:string, rising: boolean, falling: boolean, flatEnd: boolean}[] {
 
+	word = normalizeAccent(word);
+
 	function isYouon(i: number): boolean {
-		return (word.charAt(i) === "ゃ" || word.charAt(i) === "ゅ" || word.charAt(i) === "ょ")
+		return (SMALL_KANA.indexOf(word.charAt(i)) >= 0)
 	};
 
 	function isRising(i: number): boolean {
@@ -47,7 +119,7 @@ function moraize(word: string): {mora:string, rising: boolean, falling: boolean,
 	}
 
 	function isFalling(i: number): boolean {
-		return (word.charAt(i) === "・")
+		return isNucleusMark(word.charAt(i))
 	}
 
 	function isFlatEnd(i: number): boolean {
@@ -215,11 +287,13 @@ var prifilter_value = $("#priorityFilterValue");
 var prifilter_toggle = $("#priorityFilterToggle");
 var pubstuff_toggle = $("#onlyPublishedStuffToggle");
 var unpubstuff_toggle = $("#onlyUnPublishedStuffToggle");
+var search_query = $("#searchQuery");
 
 let priority_filter_value = prifilter_value.val();
 let priority_filter_toggle = prifilter_toggle.is(":checked");
 let pubstuff_value = pubstuff_toggle.is(":checked");
 let unpubstuff_value = unpubstuff_toggle.is(":checked");
+let search_skill_ids = null; // null when not searching
 
 let shown_nuggets = new Array();
 let globalJobNumber = 0;
@@ -268,6 +342,35 @@ unpubstuff_toggle.change(() => {
 	drawList(nugget_resp, bundle_resp, narrator_resp);
 });
 
+let searchTimeout = null;
+
+search_query.on("input", () => {
+	window.clearTimeout(searchTimeout);
+	searchTimeout = window.setTimeout(() => {
+		let query = search_query.val().trim();
+		function redraw() {
+			n_list.html("");
+			shown_nuggets = new Array();
+			loading_msg.show();
+			loading_msg.text("Loaded. Rendering content. ");
+			drawList(nugget_resp, bundle_resp, narrator_resp);
+		}
+		if (query === "") {
+			search_skill_ids = null;
+			redraw();
+			return;
+		}
+		$.getJSON("/api/search", {q: query}, (resp) => {
+			if (search_query.val().trim() !== query) { return; } // A newer search is coming
+			search_skill_ids = {};
+			resp.words.forEach((w) => { search_skill_ids[w.skill_nugget] = true; });
+			resp.questions.forEach((q) => { search_skill_ids[q.skill_id] = true; });
+			resp.skills.forEach((s) => { search_skill_ids[s.id] = true; });
+			redraw();
+		});
+	}, 300);
+});
+
 let currentlyDragged = null;
 let dropTargets = new Array();
 
@@ -423,9 +526,12 @@ function drawList(nugget_resp, bundle_resp, narrator_resp) {
 				|| questions.some((i) => { return i.published })
 				|| exercises.some((i) => { return i.published });
 
+		let matchesSearch = search_skill_ids === null || search_skill_ids[tuple[0].id] === true;
+
 		if (( ! priority_filter_toggle || thereIsPriorityStuff )
 			&& ( ! pubstuff_value || thereIsPublishedStuff )
-			&& ( ! unpubstuff_value || ! thereIsPublishedStuff ) ) {
+			&& ( ! unpubstuff_value || ! thereIsPublishedStuff )
+			&& matchesSearch ) {
 			shown_nuggets.push(tuple);
 			console.log(tuple[0].skill_summary);
 		}
@@ -614,6 +720,12 @@ function drawList(nugget_resp, bundle_resp, narrator_resp) {
 				});
 				w_word_edit.click(function(ev){ ev.stopPropagation(); });
 				wordEdit.on('input', function() {
+					let error = accentError(wordEdit.val());
+					wordEdit.attr("title", error || "");
+					wordEdit.css("border-color", error ? "red" : "");
+					if (error) {
+						return;
+					}
 					word.word = wordEdit.val();
 					c_header.html('Word: ' + accentuate(word.word, true));
 					var request = {
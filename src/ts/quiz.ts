@@ -301,7 +301,7 @@ function startRecording(eventName: string, callback: (recording: boolean, startC
 }
 
 
-type Quiz = FutureJson | QuestionJson | WordJson | ExerciseJson;
+type Quiz = FutureJson | QuestionJson | WordJson | ExerciseJson | ComparisonJson | AccentJson | DictationJson;
 
 interface FutureJson {
     quiz_type: "future",
@@ -324,6 +324,13 @@ interface AnsweredQuestion {
 	active_answer_time: number,
 	full_answer_time: number,
 	full_spent_time: number,
+	idempotency_key: string,
+}
+
+interface AccentedWord {
+    morae: string[],
+    nucleus: number | null,
+    pattern: "heiban" | "atamadaka" | "nakadaka" | "odaka",
 }
 
 interface WordJson {
@@ -332,6 +339,7 @@ interface WordJson {
     word: string,
     explanation: string,
     show_accents: boolean,
+    accent: AccentedWord | null,
 }
 
 interface AnsweredWord {
@@ -340,6 +348,7 @@ interface AnsweredWord {
 	times_audio_played: number,
 	active_answer_time: number,
 	full_spent_time: number,
+	idempotency_key: string,
 }
 
 interface ExerciseJson {
@@ -349,6 +358,17 @@ interface ExerciseJson {
     word: string,
     explanation: string,
     must_record: boolean,
+    audio_id: number,
+}
+
+interface PitchContour {
+	hop_ms: number,
+	f0: number[],
+}
+
+interface PitchOverlay {
+	audio: PitchContour,
+	recording: PitchContour,
 }
 
 interface AnsweredExercise {
@@ -360,6 +380,70 @@ interface AnsweredExercise {
 	reflected_time: number,
 	full_answer_time: number,
 	full_spent_time: number,
+	idempotency_key: string,
+	event?: string,
+	quiz_number?: number,
+	rec_number?: number,
+}
+
+interface ComparisonJson {
+    quiz_type: "comparison",
+    asked_id: number,
+    word: string,
+    explanation: string,
+}
+
+interface AnsweredComparison {
+	type: "comparison",
+	asked_id: number,
+	answered_choice: number,
+	active_answer_time: number,
+	full_answer_time: number,
+	full_spent_time: number,
+	idempotency_key: string,
+}
+
+interface AccentJson {
+    quiz_type: "accent",
+    asked_id: number,
+    word: string,
+    explanation: string,
+    morae: string[],
+}
+
+interface AnsweredAccent {
+	type: "accent",
+	asked_id: number,
+	answered_nucleus: number,
+	active_answer_time: number,
+	full_answer_time: number,
+	full_spent_time: number,
+	idempotency_key: string,
+}
+
+interface AnswerResult {
+	asked_id: number,
+	correct: boolean,
+	right_nucleus: number | null,
+	word: string | null,
+	right_choice: number | null,
+}
+
+interface DictationJson {
+    quiz_type: "dictation",
+    asked_id: number,
+    explanation: string,
+}
+
+interface AnsweredDictation {
+	type: "dictation",
+	asked_id: number,
+	answered_text: string,
+	times_audio_played: number,
+	active_answer_time: number,
+	full_answer_time: number,
+	full_spent_time: number,
+	idempotency_key: string,
 }
 
 interface quizData {
@@ -370,6 +454,7 @@ interface quizData {
 	active_answer_time?: number,
 	playbackStartedInstant?: number,
 	playbackEndedInstant?: number,
+	recording?: [number, number],
 	answered: boolean,
 	sent: boolean,
 }
@@ -426,6 +511,7 @@ var wordStatus = $("#wordStatus");
 var word_avatar = $("#wordAvatar");
 var word_play_button = $("#wordStartButton");
 var wordExplanation = $("#wordExplanation");
+var pitchCanvas = $("#pitchCanvas");
 var soundIcon = $(".soundicon");
 var wordOkButton = $("#wordOkButton");
 var exerciseOkButton = $("#exerciseOkButton");
@@ -498,6 +584,7 @@ function cleanState() : void {
 	exerciseSuccessButton.hide();
 	wordShowButton.hide();
 	userWordShowButton.hide();
+	pitchCanvas.hide();
 	wordButtonLabel.hide();
 	wordOkButton.hide();
 	avatar.hide();
@@ -544,6 +631,16 @@ function breakTime(future: FutureJson) : void {
 	questionStatus.slideDown(normalSpeed);
 }
 
+function newIdempotencyKey(): string {
+	return Date.now().toString(36) + Math.random().toString(36).slice(2);
+}
+
+/* The result of an answer that is graded on the server comes along with the next quiz.
+If there's no next quiz, the result is all there is. */
+function nextQuizOf(result: any): Quiz {
+	return (result === null || result.quiz_type === undefined) ? null : result;
+}
+
 function createSemaphore(count: number) : (argument?: any) =>void {
 
 	var semaphore = count;
@@ -639,6 +736,7 @@ function answerExercise(isCorrect: boolean, exercise: ExerciseJson, quiz_data: q
 		}
 	}
 	var answeredInstant = Date.now();
+	var idempotencyKey = newIdempotencyKey(); // Retries must send the same key
 	function postAnswerExercise() {
 		console.log("postAnswerExercise", exercise);
 		let answered: AnsweredExercise = {
@@ -650,7 +748,13 @@ function answerExercise(isCorrect: boolean, exercise: ExerciseJson, quiz_data: q
 			reflected_time: answeredInstant - quiz_data.pronouncedInstant,
 			full_answer_time: answeredInstant - quiz_data.askedInstant,
 			full_spent_time: answeredInstant - quiz_data.startedInstant,
+			idempotency_key: idempotencyKey,
 		};
+		if (quiz_data.recording) {
+			answered.event = exercise.event_name;
+			answered.quiz_number = quiz_data.recording[0];
+			answered.rec_number = quiz_data.recording[1];
+		}
 		var jqxhr = $.post(next_quiz_api, answered, function(result) {
 			clearError();
 			console.log("postAnswerExercise: got result");
@@ -676,6 +780,7 @@ function answerWord(word: WordJson, quiz_data: quizData) {
 		});
 	}, normalSlow);
 	var wordAnsweredInstant = Date.now();
+	var idempotencyKey = newIdempotencyKey(); // Retries must send the same key
 	function postAnswerWord() {
 		let answered: AnsweredWord = {
 			type: "word",
@@ -683,6 +788,7 @@ function answerWord(word: WordJson, quiz_data: quizData) {
 			times_audio_played: timesAudioPlayed,
 			active_answer_time: quiz_data.active_answer_time,
 			full_spent_time: wordAnsweredInstant - quiz_data.wordShownInstant,
+			idempotency_key: idempotencyKey,
 		};
 		var jqxhr = $.post(next_quiz_api, answered, function(result) {
 			clearError();
@@ -741,6 +847,7 @@ function answerQuestion(ansId: number, isCorrect: boolean, question: QuestionJso
 		nextQuestion();
 	}); }, timeAfterClick);
 
+	var idempotencyKey = newIdempotencyKey(); // Retries must send the same key
 	function postAnswerQuestion() {
 		let answered: AnsweredQuestion = {
 			type: "question",
@@ -749,6 +856,7 @@ function answerQuestion(ansId: number, isCorrect: boolean, question: QuestionJso
 			active_answer_time: answeredInstant - quiz_data.playbackEndedInstant,
 			full_answer_time: answeredInstant - quiz_data.playbackStartedInstant,
 			full_spent_time: answeredInstant - quiz_data.startedInstant,
+			idempotency_key: idempotencyKey,
 		};
 		var jqxhr = $.post(next_quiz_api, answered, function(result) {
 			clearError();
@@ -828,6 +936,327 @@ function showQuestion(question: QuestionJson) {
 	
 }
 
+function answerComparison(choice: number, comparison: ComparisonJson, button: JQuery, quiz_data: quizData) {
+	if (quiz_data.answered) { return; };
+	quiz_data.answered = true;
+	var answeredInstant = Date.now();
+	var top = (button === null) ? answerList.height()/2 : $(button).position().top + ($(button).height()/2);
+	questionExplanation.hide();
+
+	/* The right answer is known only after the server has graded the answer. */
+	function showAnswerResult(answerResult: AnswerResult) {
+		var mark = null;
+		if (answerResult.correct) {
+			mark = maru;
+			questionStatus.text("Oikein! Seuraava kysymys.");
+			correct.play();
+		} else if (choice > 0) {
+			mark = batsu;
+			questionStatus.text("Pieleen meni, kokeile uudestaan!");
+			wrong.play();
+		} else {
+			mark = batsu;
+			questionStatus.text("Aika loppui!");
+			wrong.play();
+		}
+		mark.css("top", top + "px");
+		mark.show();
+		mark.removeClass("hidden");
+		setTimeout(function() { mark.fadeOut(normalSpeed); }, 1700);
+		questionStatus.show();
+	}
+
+	var nextQuestion = createSemaphore(2);
+
+	var idempotencyKey = newIdempotencyKey(); // Retries must send the same key
+	function postAnswerComparison() {
+		let answered: AnsweredComparison = {
+			type: "comparison",
+			asked_id: comparison.asked_id,
+			answered_choice: choice,
+			active_answer_time: answeredInstant - quiz_data.playbackEndedInstant,
+			full_answer_time: answeredInstant - quiz_data.playbackStartedInstant,
+			full_spent_time: answeredInstant - quiz_data.startedInstant,
+			idempotency_key: idempotencyKey,
+		};
+		var jqxhr = $.post(next_quiz_api, answered, function(result) {
+			clearError();
+			console.log("postAnswerComparison: got result");
+			showAnswerResult(result.answer_result);
+			setTimeout(function() { answerList.slideUp(normalSpeed, function() {
+				topmessage.fadeOut();
+				questionExplanation.text("Loading...");
+				questionExplanation.slideDown(normalSpeed);
+				nextQuestion();
+			}); }, 2200);
+			nextQuestion(() => { showQuiz(nextQuizOf(result)) });
+		});
+		jqxhr.fail(function(e) {
+			connectionFailMessage(e);
+			setTimeout(postAnswerComparison, 3000);
+		});
+	};
+	postAnswerComparison();
+}
+
+function showComparison(comparison: ComparisonJson) {
+	console.log(comparison);
+	questionSectionFlexContainer.show();
+	questionSection.show();
+	questionExplanation.html(comparison.explanation);
+	avatar.show();
+	avatar.css('opacity', '0');
+	questionExplanation.slideDown(normalSpeed, function() { avatar.fadeTo(normalSpeed, 1); });
+	let quiz_data: quizData = { startedInstant: Date.now(), answered: false, sent: false };
+
+	[1, 2].forEach(function(choice) {
+		var newAnswerButton = prototypeAnswer.clone();
+		newAnswerButton.children("button")
+			.text(choice === 1 ? "Ensimmäinen" : "Toinen")
+			.one('click', function() {
+				answerComparison(choice, comparison, this, quiz_data);
+			});
+		answerList.append(newAnswerButton);
+	});
+	var firstAudio = new Howl({ src: ['/api/audio.mp3?'+comparison.asked_id]});
+	var secondAudio = new Howl({ src: ['/api/comparison_audio.mp3?'+comparison.asked_id]});
+
+	play_button.one('click', function() {
+		console.log("comparison started");
+		quiz_data.playbackStartedInstant = Date.now();
+		questionStatus.slideUp(normalSpeed);
+		questionSection.css("min-height", questionSection.css("height"));
+		main.css("min-height", main.css("height"));
+		avatar.fadeOut(quiteFast);
+		questionText.html("Kumpi on " + accentuate(comparison.word, false) + "?");
+
+		firstAudio.once('end', function() {
+			setTimeout(function() { secondAudio.play(); }, 700);
+		});
+		secondAudio.once('end', function() {
+			quiz_data.playbackEndedInstant = Date.now();
+			topmessage.text("Vastausaikaa 8 s");
+			topmessage.fadeIn();
+			answerList.slideDown(normalSpeed);
+			window.setTimeout(function() { if (quiz_data.answered) {return}; topmessage.text("Vastausaikaa 3 s"); }, 5000);
+			window.setTimeout(function() { if (quiz_data.answered) {return}; topmessage.text("Vastausaikaa 2 s"); }, 6000);
+			window.setTimeout(function() { if (quiz_data.answered) {return}; topmessage.text("Vastausaikaa 1 s"); }, 7000);
+			window.setTimeout(function() {
+				if (quiz_data.answered) {return};
+				topmessage.fadeOut();
+				answerComparison(-1, comparison, null, quiz_data);
+			}, 8000);
+		});
+		firstAudio.play();
+	});
+
+	setLoadError(firstAudio, "comparisonAudio", comparison);
+	setLoadError(secondAudio, "comparisonAudio", comparison);
+}
+
+function answerAccent(nucleus: number, accent: AccentJson, button: JQuery, quiz_data: quizData) {
+	if (quiz_data.answered) { return; };
+	quiz_data.answered = true;
+	var answeredInstant = Date.now();
+	var top = $(button).position().top + ($(button).height()/2);
+	questionExplanation.hide();
+
+	/* The right answer is known only after the server has graded the answer. */
+	function showAnswerResult(answerResult: AnswerResult) {
+		var mark = null;
+		if (answerResult.correct) {
+			mark = maru;
+			questionStatus.text("Oikein! Seuraava kysymys.");
+			correct.play();
+		} else {
+			mark = batsu;
+			questionStatus.text("Pieleen meni, kokeile uudestaan!");
+			wrong.play();
+		}
+		mark.css("top", top + "px");
+		mark.show();
+		mark.removeClass("hidden");
+		setTimeout(function() { mark.fadeOut(normalSpeed); }, 1700);
+		questionStatus.show();
+		questionText.html(accentuate(accent.morae.map(function(mora, i) {
+			return (i === answerResult.right_nucleus) ? mora + "・" : mora;
+		}).join(""), true));
+	}
+
+	var nextQuestion = createSemaphore(2);
+
+	var idempotencyKey = newIdempotencyKey(); // Retries must send the same key
+	function postAnswerAccent() {
+		let answered: AnsweredAccent = {
+			type: "accent",
+			asked_id: accent.asked_id,
+			answered_nucleus: nucleus,
+			active_answer_time: answeredInstant - quiz_data.playbackEndedInstant,
+			full_answer_time: answeredInstant - quiz_data.playbackStartedInstant,
+			full_spent_time: answeredInstant - quiz_data.startedInstant,
+			idempotency_key: idempotencyKey,
+		};
+		var jqxhr = $.post(next_quiz_api, answered, function(result) {
+			clearError();
+			console.log("postAnswerAccent: got result");
+			showAnswerResult(result.answer_result);
+			setTimeout(function() { answerList.slideUp(normalSpeed, function() {
+				questionExplanation.text("Loading...");
+				questionExplanation.slideDown(normalSpeed);
+				nextQuestion();
+			}); }, 2200);
+			nextQuestion(() => { showQuiz(nextQuizOf(result)) });
+		});
+		jqxhr.fail(function(e) {
+			connectionFailMessage(e);
+			setTimeout(postAnswerAccent, 3000);
+		});
+	};
+	postAnswerAccent();
+}
+
+/* The learner chooses the mora after which the pitch drops, or that it doesn't drop at all.
+The server grades the answer. */
+function showAccent(accent: AccentJson) {
+	console.log(accent);
+	questionSectionFlexContainer.show();
+	questionSection.show();
+	questionExplanation.html(accent.explanation);
+	avatar.show();
+	avatar.css('opacity', '0');
+	questionExplanation.slideDown(normalSpeed, function() { avatar.fadeTo(normalSpeed, 1); });
+	let quiz_data: quizData = { startedInstant: Date.now(), answered: false, sent: false };
+
+	let choices = accent.morae.map(function(mora, i) { return {nucleus: i, text: "Laskee tavun " + mora + " jälkeen"}; });
+	choices.push({nucleus: -1, text: "Ei laske"});
+	choices.forEach(function(choice) {
+		var newAnswerButton = prototypeAnswer.clone();
+		newAnswerButton.children("button")
+			.text(choice.text)
+			.one('click', function() {
+				answerAccent(choice.nucleus, accent, this, quiz_data);
+			});
+		answerList.append(newAnswerButton);
+	});
+	var wordAudio = new Howl({ src: ['/api/audio.mp3?'+accent.asked_id]});
+
+	play_button.one('click', function() {
+		console.log("accent quiz started");
+		quiz_data.playbackStartedInstant = Date.now();
+		questionStatus.slideUp(normalSpeed);
+		questionSection.css("min-height", questionSection.css("height"));
+		main.css("min-height", main.css("height"));
+		avatar.fadeOut(quiteFast);
+		questionText.html("Missä sävelkorko laskee? " + accent.word);
+
+		wordAudio.once('end', function() {
+			quiz_data.playbackEndedInstant = Date.now();
+			answerList.slideDown(normalSpeed);
+		});
+		wordAudio.play();
+	});
+
+	setLoadError(wordAudio, "accentAudio", accent);
+}
+
+function answerDictation(text: string, dictation: DictationJson, quiz_data: quizData) {
+	if (quiz_data.answered) { return; };
+	quiz_data.answered = true;
+	var answeredInstant = Date.now();
+	questionText.text("Vastasit: " + text);
+	questionExplanation.hide();
+	var nextQuestion = createSemaphore(2);
+
+	var idempotencyKey = newIdempotencyKey(); // Retries must send the same key
+	function postAnswerDictation() {
+		let answered: AnsweredDictation = {
+			type: "dictation",
+			asked_id: dictation.asked_id,
+			answered_text: text,
+			times_audio_played: timesAudioPlayed,
+			active_answer_time: answeredInstant - quiz_data.playbackEndedInstant,
+			full_answer_time: answeredInstant - quiz_data.playbackStartedInstant,
+			full_spent_time: answeredInstant - quiz_data.startedInstant,
+			idempotency_key: idempotencyKey,
+		};
+		var jqxhr = $.post(next_quiz_api, answered, function(result) {
+			clearError();
+			console.log("postAnswerDictation: got result");
+			// The right answer is known only after the server has graded the answer.
+			questionStatus.text("Oikea vastaus: " + result.answer_result.word);
+			questionStatus.show();
+			setTimeout(function() { answerList.slideUp(normalSpeed, function() {
+				questionExplanation.text("Loading...");
+				questionExplanation.slideDown(normalSpeed);
+				nextQuestion();
+			}); }, 2200);
+			nextQuestion(() => { showQuiz(nextQuizOf(result)) });
+		});
+		jqxhr.fail(function(e) {
+			connectionFailMessage(e);
+			setTimeout(postAnswerDictation, 3000);
+		});
+	};
+	postAnswerDictation();
+}
+
+/* The learner types in kana what they heard. The audio can be replayed before answering.
+The server grades the answer, so the right answer is only shown after answering. */
+function showDictation(dictation: DictationJson) {
+	console.log(dictation);
+	questionSectionFlexContainer.show();
+	questionSection.show();
+	questionExplanation.html(dictation.explanation);
+	avatar.show();
+	avatar.css('opacity', '0');
+	questionExplanation.slideDown(normalSpeed, function() { avatar.fadeTo(normalSpeed, 1); });
+	let quiz_data: quizData = { startedInstant: Date.now(), answered: false, sent: false };
+
+	var wordAudio = new Howl({ src: ['/api/audio.mp3?'+dictation.asked_id]});
+
+	var dictationInput = $('<input type="text" lang="ja" autocomplete="off">');
+	var replayButton = prototypeAnswer.clone();
+	replayButton.children("button")
+		.text("Kuuntele uudestaan")
+		.click(function() {
+			timesAudioPlayed++;
+			wordAudio.play();
+		});
+	var answerButton = prototypeAnswer.clone();
+	answerButton.prepend(dictationInput);
+	answerButton.children("button")
+		.text("Vastaa")
+		.click(function() {
+			answerDictation(<string>dictationInput.val(), dictation, quiz_data);
+		});
+	dictationInput.keyup(function(e) {
+		if (e.which === 13) {
+			answerDictation(<string>dictationInput.val(), dictation, quiz_data);
+		}
+	});
+	answerList.append(answerButton);
+	answerList.append(replayButton);
+
+	play_button.one('click', function() {
+		console.log("dictation started");
+		quiz_data.playbackStartedInstant = Date.now();
+		questionStatus.slideUp(normalSpeed);
+		questionSection.css("min-height", questionSection.css("height"));
+		main.css("min-height", main.css("height"));
+		avatar.fadeOut(quiteFast);
+		questionText.text("Kirjoita kuulemasi kanoilla.");
+
+		wordAudio.once('end', function() {
+			quiz_data.playbackEndedInstant = Date.now();
+			answerList.slideDown(normalSpeed, function() { dictationInput.focus(); });
+		});
+		timesAudioPlayed++;
+		wordAudio.play();
+	});
+
+	setLoadError(wordAudio, "dictationAudio", dictation);
+}
+
 function showWord(word: WordJson) {
 	wordSection.show();
 	word_avatar.hide();
@@ -888,6 +1317,51 @@ function showWord(word: WordJson) {
 	}, 200);
 }
 
+/* Draws the contours on a logarithmic frequency scale, the narrator in teal and the user in pink.
+Unvoiced frames are nulls, so they leave gaps in the lines. */
+function drawPitch(overlay: PitchOverlay) {
+	let canvas = <HTMLCanvasElement>pitchCanvas.get(0);
+	let ctx = canvas.getContext("2d");
+	ctx.clearRect(0, 0, canvas.width, canvas.height);
+
+	let contours = [overlay.audio, overlay.recording].filter((c) => c !== null);
+	let voiced = [].concat(...contours.map((c) => c.f0.filter((f) => f !== null)));
+	if (voiced.length === 0) {
+		return;
+	}
+	let min = Math.log(Math.min(...voiced)) - 0.1;
+	let max = Math.log(Math.max(...voiced)) + 0.1;
+	let frames = Math.max(...contours.map((c) => c.f0.length));
+
+	function drawContour(contour: PitchContour, color: string) {
+		ctx.strokeStyle = color;
+		ctx.lineWidth = 3;
+		ctx.beginPath();
+		let penDown = false;
+		contour.f0.forEach((f, i) => {
+			if (f === null) {
+				penDown = false;
+				return;
+			}
+			let x = i / frames * canvas.width;
+			let y = canvas.height - (Math.log(f) - min) / (max - min) * canvas.height;
+			if (penDown) {
+				ctx.lineTo(x, y);
+			} else {
+				ctx.moveTo(x, y);
+				penDown = true;
+			}
+		});
+		ctx.stroke();
+	}
+
+	drawContour(overlay.audio, "#2bb3a3");
+	if (overlay.recording !== null) {
+		drawContour(overlay.recording, "#e8609c");
+	}
+	pitchCanvas.fadeIn();
+}
+
 function showExercise(exercise: ExerciseJson) {
 	startRecording(exercise.event_name, (recording_supported, start_recording, finished_recording, when_recording_done) => {
 		if (exercise.must_record && !recording_supported) {
@@ -969,6 +1443,7 @@ function showExercise(exercise: ExerciseJson) {
 					timesAudioPlayed++;
 					when_recording_done((resp) => {
 						if (resp !== null) {
+							quiz_data.recording = resp;
 							console.log("Recording done & uploaded. Now fetching audio from:", '/api/user_audio.ogg?event='+exercise.event_name+'&quiz_number='+resp[0]+'&rec_number='+resp[1]);
 							// HTML5 is required because Chrome doesn't support audio/ogg; codecs=opus without it
 							userAudio = new Howl({ src: [
@@ -979,6 +1454,7 @@ function showExercise(exercise: ExerciseJson) {
 								userWordShowButton.fadeIn();
 							});
 							setUserWordShowButton(userAudio);
+							$.getJSON('/api/audio/'+exercise.audio_id+'/pitch?event='+exercise.event_name+'&quiz_number='+resp[0]+'&rec_number='+resp[1], drawPitch);
 						}
 					});
 				} else {
@@ -1051,6 +1527,12 @@ function showQuiz(quiz: Quiz): void {
 		showWord(quiz);
 	} else if (quiz.quiz_type === "exercise") {
 		showExercise(quiz);
+	} else if (quiz.quiz_type === "comparison") {
+		showComparison(quiz);
+	} else if (quiz.quiz_type === "accent") {
+		showAccent(quiz);
+	} else if (quiz.quiz_type === "dictation") {
+		showDictation(quiz);
 	} else if (quiz.quiz_type === "future") {
 		startBreak(quiz);
 	}
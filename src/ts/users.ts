@@ -271,6 +271,29 @@ function format_date(d) {
 	return date+" "+time;
 }
 
+function leech_controls(user_id: number, due_item, item: JQuery) {
+	if (!due_item.suspended) {
+		return;
+	}
+	let marker = $('<strong> LEECH, suspended </strong>').appendTo(item);
+	$('<button class="compact">Unsuspend</button>')
+		.appendTo(item)
+		.click(function() {
+			var request = {
+				type: 'PUT',
+				url: "/api/users/"+user_id+"?unsuspend="+due_item.id,
+				contentType: "application/json",
+				data: "",
+				success: function() {
+					marker.remove();
+					$(this).remove();
+				},
+				context: this,
+			};
+			$.ajax(request);
+		});
+}
+
 function list_user_skills(user_id: number) {
 	$.getJSON("/api/users/"+user_id+"/skills", function(skill_resp) {
 		let userDetails = $("#userDetails");
@@ -286,6 +309,9 @@ function list_user_skills(user_id: number) {
 			let q_data = asked_resp[0];
 			let e_data = asked_resp[1];
 			let w_data = asked_resp[2];
+			let c_data = asked_resp[3];
+			let a_data = asked_resp[4];
+			let d_data = asked_resp[5];
 			q_data.forEach(function(data) {
 				let due_item = data[0];
 				let q = data[2];
@@ -302,6 +328,22 @@ function list_user_skills(user_id: number) {
 					skill.asked.push({due_item: due_item, quiz: e});
 				}
 			});
+			c_data.forEach(function(data) {
+				let due_item = data[0];
+				let c = data[2];
+				let skill = skills[c.skill_id];
+				if (skill !== undefined) {
+					skill.asked.push({due_item: due_item, quiz: c});
+				}
+			});
+			a_data.concat(d_data).forEach(function(data) {
+				let due_item = data[0];
+				let w = data[2];
+				let skill = skills[w.skill_nugget];
+				if (skill !== undefined) {
+					skill.asked.push({due_item: due_item, quiz: w});
+				}
+			});
 			w_data.forEach(function(data) {
 				let pending = data[0];
 				let w = data[2];
@@ -318,14 +360,38 @@ function list_user_skills(user_id: number) {
 					if (q.due_item.item_type === "word") {
 						$("<li>"+ q.due_item.item_type +" ("+q.quiz.id+"): "+q.quiz.word+"</li>").appendTo(list);
 					} else if (q.due_item.item_type === "question") {
-						$("<li>"+ q.due_item.item_type +" ("+q.quiz.id+"): "+": "+q.quiz.q_name+" Due: "+format_date(q.due_item.due_date)+" Streak: "+q.due_item.correct_streak_overall+" Microstreak: "+q.due_item.correct_streak_this_time+"</li>").appendTo(list);
+						let item = $("<li>"+ q.due_item.item_type +" ("+q.quiz.id+"): "+": "+q.quiz.q_name+" Due: "+format_date(q.due_item.due_date)+" Streak: "+q.due_item.correct_streak_overall+" Microstreak: "+q.due_item.correct_streak_this_time+" Lapses: "+q.due_item.lapses+"</li>").appendTo(list);
+						leech_controls(user_id, q.due_item, item);
 					} else if (q.due_item.item_type === "exercise") {
-						$("<li>"+ q.due_item.item_type +" ("+q.quiz.id+"): "+s.skill.skill_summary+" Due: "+format_date(q.due_item.due_date)+" Streak: "+q.due_item.correct_streak_overall+" Microstreak: "+q.due_item.correct_streak_this_time+"</li>").appendTo(list);
+						let item = $("<li>"+ q.due_item.item_type +" ("+q.quiz.id+"): "+s.skill.skill_summary+" Due: "+format_date(q.due_item.due_date)+" Streak: "+q.due_item.correct_streak_overall+" Microstreak: "+q.due_item.correct_streak_this_time+" Lapses: "+q.due_item.lapses+"</li>").appendTo(list);
+						leech_controls(user_id, q.due_item, item);
+					} else if (q.due_item.item_type === "comparison") {
+						let item = $("<li>"+ q.due_item.item_type +" ("+q.quiz.id+"): words "+q.quiz.word_a+" & "+q.quiz.word_b+" Due: "+format_date(q.due_item.due_date)+" Streak: "+q.due_item.correct_streak_overall+" Microstreak: "+q.due_item.correct_streak_this_time+" Lapses: "+q.due_item.lapses+"</li>").appendTo(list);
+						leech_controls(user_id, q.due_item, item);
+					} else if (q.due_item.item_type === "accent" || q.due_item.item_type === "dictation") {
+						let item = $("<li>"+ q.due_item.item_type +" ("+q.quiz.id+"): "+q.quiz.word+" Due: "+format_date(q.due_item.due_date)+" Streak: "+q.due_item.correct_streak_overall+" Microstreak: "+q.due_item.correct_streak_this_time+" Lapses: "+q.due_item.lapses+"</li>").appendTo(list);
+						leech_controls(user_id, q.due_item, item);
 					}
 				});
 			});
+			list_pronunciation_scores(user_id, userDetails);
 		});
 	})
 }
 
+function list_pronunciation_scores(user_id: number, userDetails: JQuery) {
+	$.getJSON("/api/users/"+user_id+"/pronunciation_scores", function(scores) {
+		if (scores.length === 0) {
+			return;
+		}
+		$("<h2>Pronunciation scores</h2>").appendTo(userDetails);
+		let list = $("<ul></ul>").appendTo(userDetails);
+		scores.forEach(function(s) {
+			let score = s.recording.score === null ? (s.recording.scored === null ? "not scored yet" : "couldn't be scored") : s.recording.score.toFixed(0);
+			let self_assessment = s.answer_level > 0 ? "correct" : "wrong";
+			$("<li>"+format_date(s.answered_date)+" word ("+s.word_id+"): Score: "+score+" Self-assessment: "+self_assessment+"</li>").appendTo(list);
+		});
+	});
+}
+
 })